@@ -0,0 +1,669 @@
+//! Assembler for a subset of Octo, the inverse of `disasm`.
+//!
+//! Supported: `: label`, `:const name value`, `:alias name register`,
+//! `:call target`, `:byte value`, every instruction `Instruction` can encode,
+//! `if ... then`, `if ... begin`/`else`/`end`, `loop`/`again` and bare numbers
+//! as data bytes. A bare label name calls it. Like Octo, a `jump main` is put
+//! at the start unless `: main` is the first thing in the program.
+
+use crate::constants::{RAM_SIZE, START_ADDR};
+use crate::instruction::Instruction;
+use crate::symbols::{SourceLine, SymbolMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1-based.
+    pub line: u32,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// An assembled rom, loaded at `START_ADDR`.
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub rom: Vec<u8>,
+    pub labels: BTreeMap<String, u16>,
+    /// Source line each instruction was assembled from.
+    pub lines: BTreeMap<u16, u32>,
+    /// Runs of data bytes.
+    pub data: Vec<RangeInclusive<u16>>,
+}
+
+impl Program {
+    /// The symbol map of the program, `source_file` as written in the
+    /// symbol file.
+    pub fn symbols(&self, source_file: &Path) -> SymbolMap {
+        let mut symbols = SymbolMap::default();
+        for (name, &address) in &self.labels {
+            symbols.insert_label(address, name);
+        }
+        for range in &self.data {
+            symbols.insert_data(range.clone());
+        }
+        for (&address, &line) in &self.lines {
+            let file = source_file.to_path_buf();
+            symbols.insert_line(address, SourceLine { file, line });
+        }
+        symbols
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    let tokens = tokenize(source);
+
+    let mut first = Assembler::new(&tokens, START_ADDR, BTreeMap::new(), false);
+    first.run()?;
+    let mut labels = first.labels;
+
+    // Like Octo, execution starts at `main`
+    let needs_jump = labels.get("main").is_some_and(|&main| main != START_ADDR);
+    let origin = if needs_jump {
+        labels.values_mut().for_each(|address| *address += 2);
+        START_ADDR + 2
+    } else {
+        START_ADDR
+    };
+
+    let mut last = Assembler::new(&tokens, origin, labels, true);
+    last.run()?;
+    let mut rom = Vec::new();
+    if needs_jump {
+        rom = Instruction::Jump {
+            nnn: last.labels["main"],
+        }
+        .encode();
+    }
+    rom.extend(last.rom);
+
+    Ok(Program {
+        rom,
+        labels: last.labels,
+        lines: last.lines,
+        data: last.data,
+    })
+}
+
+struct Token<'a> {
+    text: &'a str,
+    line: u32,
+}
+
+// Octo tokens are separated by whitespace, `#` comments out the rest of a line
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    source
+        .lines()
+        .enumerate()
+        .flat_map(|(number, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace().map(move |text| Token {
+                text,
+                line: number as u32 + 1,
+            })
+        })
+        .collect()
+}
+
+// Blocks waiting for their closing keyword
+enum Block {
+    // Offset in the rom of the jump over the block
+    If { jump: usize, line: u32 },
+    Else { jump: usize, line: u32 },
+    Loop { start: u16, line: u32 },
+}
+
+// One pass over the source. The first pass only finds the label addresses,
+// names it doesn't know yet stand for 0.
+struct Assembler<'a> {
+    tokens: &'a [Token<'a>],
+    pos: usize,
+    line: u32,
+    origin: u16,
+    rom: Vec<u8>,
+    labels: BTreeMap<String, u16>,
+    defined: HashSet<String>,
+    constants: HashMap<String, i32>,
+    aliases: HashMap<String, usize>,
+    lines: BTreeMap<u16, u32>,
+    data: Vec<RangeInclusive<u16>>,
+    blocks: Vec<Block>,
+    last_pass: bool,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(
+        tokens: &'a [Token<'a>],
+        origin: u16,
+        labels: BTreeMap<String, u16>,
+        last_pass: bool,
+    ) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            line: 1,
+            origin,
+            rom: Vec::new(),
+            labels,
+            defined: HashSet::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            lines: BTreeMap::new(),
+            data: Vec::new(),
+            blocks: Vec::new(),
+            last_pass,
+        }
+    }
+
+    fn run(&mut self) -> Result<(), AsmError> {
+        while self.pos < self.tokens.len() {
+            self.statement_in_memory().map_err(|message| AsmError {
+                line: self.line,
+                message,
+            })?;
+        }
+
+        match self.blocks.last() {
+            None => Ok(()),
+            Some(Block::If { line, .. } | Block::Else { line, .. }) => Err(AsmError {
+                line: *line,
+                message: "'if ... begin' without 'end'".to_string(),
+            }),
+            Some(Block::Loop { line, .. }) => Err(AsmError {
+                line: *line,
+                message: "'loop' without 'again'".to_string(),
+            }),
+        }
+    }
+
+    // Addresses past the end of memory wrap, the statement reaching there is
+    // rejected once it's done
+    fn here(&self) -> u16 {
+        self.end() as u16
+    }
+
+    fn end(&self) -> usize {
+        self.origin as usize + self.rom.len()
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| "unexpected end of file".to_string())?;
+        self.pos += 1;
+        self.line = token.line;
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|token| token.text)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("expected '{}', got '{}'", expected, token)),
+        }
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.lines.insert(self.here(), self.line);
+        self.rom.extend(instruction.encode());
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let here = self.here();
+        match self.data.last_mut() {
+            Some(range) if range.end().wrapping_add(1) == here => *range = *range.start()..=here,
+            _ => self.data.push(here..=here),
+        }
+        self.rom.push(byte);
+    }
+
+    fn statement_in_memory(&mut self) -> Result<(), String> {
+        if self.end() == RAM_SIZE {
+            self.next()?;
+            return Err("out of memory".to_string());
+        }
+        self.statement()?;
+        if self.end() > RAM_SIZE {
+            return Err("out of memory".to_string());
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), String> {
+        let token = self.next()?;
+        match token {
+            ":" => {
+                let name = self.next()?;
+                if !self.defined.insert(name.to_string()) {
+                    return Err(format!("label '{}' defined twice", name));
+                }
+                let here = self.here();
+                self.labels.insert(name.to_string(), here);
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.value()?;
+                self.constants.insert(name.to_string(), value);
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let register = self.register()?;
+                self.aliases.insert(name.to_string(), register);
+            }
+            ":call" => {
+                let nnn = self.address()?;
+                self.emit(Instruction::Call { nnn });
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte);
+            }
+
+            "return" | ";" => self.emit(Instruction::Ret),
+            "clear" => self.emit(Instruction::ClearScreen),
+            "exit" => self.emit(Instruction::Exit),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(Instruction::ScrollDown { n });
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(Instruction::ScrollUp { n });
+            }
+            "scroll-right" => self.emit(Instruction::ScrollRight),
+            "scroll-left" => self.emit(Instruction::ScrollLeft),
+            "lores" => self.emit(Instruction::LowRes),
+            "hires" => self.emit(Instruction::HighRes),
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(Instruction::SelectPlanes { n });
+            }
+            "audio" => self.emit(Instruction::LoadAudioPattern),
+
+            "jump" => {
+                let nnn = self.address()?;
+                self.emit(Instruction::Jump { nnn });
+            }
+            "jump0" => {
+                let nnn = self.address()?;
+                self.emit(Instruction::JumpPlusV0 { nnn });
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble()?;
+                self.emit(Instruction::Draw { x, y, n });
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let instruction = if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    match token {
+                        "save" => Instruction::SaveVxVy { x, y },
+                        _ => Instruction::LoadVxVy { x, y },
+                    }
+                } else {
+                    match token {
+                        "save" => Instruction::SaveVx { x },
+                        _ => Instruction::LoadVx { x },
+                    }
+                };
+                self.emit(instruction);
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::SaveFlags { x });
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::LoadFlags { x });
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(Instruction::SetVxBcdToI { x });
+            }
+
+            "i" => self.i_assignment()?,
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                self.emit(match token {
+                    "delay" => Instruction::SetDtVx { x },
+                    "buzzer" => Instruction::SetStVx { x },
+                    _ => Instruction::SetPitchVx { x },
+                });
+            }
+
+            "if" => self.if_statement()?,
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, line }) => {
+                    let else_jump = self.rom.len();
+                    self.emit(Instruction::Jump { nnn: 0 });
+                    self.patch_jump(jump)?;
+                    self.blocks.push(Block::Else {
+                        jump: else_jump,
+                        line,
+                    });
+                }
+                _ => return Err("'else' without 'if ... begin'".to_string()),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. } | Block::Else { jump, .. }) => self.patch_jump(jump)?,
+                _ => return Err("'end' without 'if ... begin'".to_string()),
+            },
+            "loop" => {
+                let start = self.here();
+                self.blocks.push(Block::Loop {
+                    start,
+                    line: self.line,
+                });
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, .. }) => self.emit(Instruction::Jump { nnn: start }),
+                _ => return Err("'again' without 'loop'".to_string()),
+            },
+
+            _ if self.is_register(token) => self.register_assignment(token)?,
+            _ if token.starts_with(':') => {
+                return Err(format!("unsupported directive '{}'", token));
+            }
+            _ if parse_number(token).is_some() => {
+                self.pos -= 1;
+                let byte = self.byte()?;
+                self.emit_byte(byte);
+            }
+            // Anything else calls a label, which may come later in the source
+            _ => {
+                if self.constants.contains_key(token) {
+                    return Err(format!("'{}' is a constant, not a label", token));
+                }
+                let nnn = match self.labels.get(token) {
+                    Some(&address) => address,
+                    None if !self.last_pass => 0,
+                    None => return Err(format!("unknown name '{}'", token)),
+                };
+                check_range(nnn as i32, 0, 0xFFF, "address")?;
+                self.emit(Instruction::Call { nnn });
+            }
+        }
+        Ok(())
+    }
+
+    fn i_assignment(&mut self) -> Result<(), String> {
+        match self.next()? {
+            ":=" => match self.peek() {
+                Some("long") => {
+                    self.next()?;
+                    let nnnn = self.value()?;
+                    check_range(nnnn, 0, 0xFFFF, "address")?;
+                    self.emit(Instruction::LongSetI { nnnn: nnnn as u16 });
+                }
+                Some("hex") => {
+                    self.next()?;
+                    let x = self.register()?;
+                    self.emit(Instruction::SetVxFontToI { x });
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    let x = self.register()?;
+                    self.emit(Instruction::SetVxBigFontToI { x });
+                }
+                _ => {
+                    let nnn = self.address()?;
+                    self.emit(Instruction::SetI { nnn });
+                }
+            },
+            "+=" => {
+                let x = self.register()?;
+                self.emit(Instruction::AddVxToI { x });
+            }
+            operator => return Err(format!("unsupported operator 'i {}'", operator)),
+        }
+        Ok(())
+    }
+
+    fn register_assignment(&mut self, register: &str) -> Result<(), String> {
+        let x = self.register_named(register)?;
+        let operator = self.next()?;
+        let source = self.peek().filter(|token| self.is_register(token));
+        let instruction = match (operator, source) {
+            (":=", Some(_)) => Instruction::SetVxVy {
+                x,
+                y: self.register()?,
+            },
+            (":=", None) => match self.peek() {
+                Some("delay") => {
+                    self.next()?;
+                    Instruction::SetVxDt { x }
+                }
+                Some("key") => {
+                    self.next()?;
+                    Instruction::SetVxKey { x }
+                }
+                Some("random") => {
+                    self.next()?;
+                    Instruction::SetVxRnd {
+                        x,
+                        nn: self.byte()?,
+                    }
+                }
+                _ => Instruction::SetVxNN {
+                    x,
+                    nn: self.byte()?,
+                },
+            },
+            ("+=", Some(_)) => Instruction::AddVxVy {
+                x,
+                y: self.register()?,
+            },
+            ("+=", None) => Instruction::AddVxNN {
+                x,
+                nn: self.byte()?,
+            },
+            ("-=", Some(_)) => Instruction::SubVxVy {
+                x,
+                y: self.register()?,
+            },
+            ("-=", None) => Instruction::AddVxNN {
+                x,
+                nn: self.byte()?.wrapping_neg(),
+            },
+            ("=-", Some(_)) => Instruction::SubVyVx {
+                x,
+                y: self.register()?,
+            },
+            ("|=", Some(_)) => Instruction::OrVxVy {
+                x,
+                y: self.register()?,
+            },
+            ("&=", Some(_)) => Instruction::AndVxVy {
+                x,
+                y: self.register()?,
+            },
+            ("^=", Some(_)) => Instruction::XorVxVy {
+                x,
+                y: self.register()?,
+            },
+            (">>=", Some(_)) => Instruction::RShiftVx {
+                x,
+                y: self.register()?,
+            },
+            ("<<=", Some(_)) => Instruction::LShiftVx {
+                x,
+                y: self.register()?,
+            },
+            ("=-" | "|=" | "&=" | "^=" | ">>=" | "<<=", None) => {
+                return Err(format!("'{}' needs a register on its right", operator));
+            }
+            _ => return Err(format!("unsupported operator '{} {}'", register, operator)),
+        };
+        self.emit(instruction);
+        Ok(())
+    }
+
+    // `if <condition> then` skips the next instruction unless the condition
+    // holds, `if <condition> begin` jumps over the block instead
+    fn if_statement(&mut self) -> Result<(), String> {
+        let x = self.register()?;
+        let operator = self.next()?;
+        let skip = match operator {
+            "key" => Instruction::SkipVxUp { x },
+            "-key" => Instruction::SkipVxDown { x },
+            "==" | "!=" => {
+                let equal = operator == "==";
+                if self.peek().is_some_and(|token| self.is_register(token)) {
+                    let y = self.register()?;
+                    if equal {
+                        Instruction::SkipVxNeqVy { x, y }
+                    } else {
+                        Instruction::SkipVxEqVy { x, y }
+                    }
+                } else {
+                    let nn = self.byte()?;
+                    if equal {
+                        Instruction::SkipVxNeqNN { x, nn }
+                    } else {
+                        Instruction::SkipVxEqNN { x, nn }
+                    }
+                }
+            }
+            _ => return Err(format!("unsupported condition '{}'", operator)),
+        };
+
+        match self.next()? {
+            "then" => {
+                if self.peek().is_none() {
+                    return Err("expected statement after then".to_string());
+                }
+                self.emit(skip);
+            }
+            "begin" => {
+                let line = self.line;
+                self.emit(negate(skip));
+                let jump = self.rom.len();
+                self.emit(Instruction::Jump { nnn: 0 });
+                self.blocks.push(Block::If { jump, line });
+            }
+            token => return Err(format!("expected 'then' or 'begin', got '{}'", token)),
+        }
+        Ok(())
+    }
+
+    // Points the jump at `offset` in the rom to the current address
+    fn patch_jump(&mut self, offset: usize) -> Result<(), String> {
+        let nnn = self.here();
+        check_range(nnn as i32, 0, 0xFFF, "address")?;
+        let bytes = Instruction::Jump { nnn }.encode();
+        self.rom[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        self.register_named(token).is_ok()
+    }
+
+    fn register(&mut self) -> Result<usize, String> {
+        let token = self.next()?;
+        self.register_named(token)
+    }
+
+    fn register_named(&self, token: &str) -> Result<usize, String> {
+        if let Some(&register) = self.aliases.get(token) {
+            return Ok(register);
+        }
+        let mut chars = token.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('v' | 'V'), Some(digit), None) if digit.is_ascii_hexdigit() => {
+                Ok(digit.to_digit(16).unwrap() as usize)
+            }
+            _ => Err(format!("expected a register, got '{}'", token)),
+        }
+    }
+
+    // A number, constant or label
+    fn value(&mut self) -> Result<i32, String> {
+        let token = self.next()?;
+        if let Some(number) = parse_number(token) {
+            return Ok(number);
+        }
+        if let Some(&value) = self.constants.get(token) {
+            return Ok(value);
+        }
+        match self.labels.get(token) {
+            Some(&address) => Ok(address as i32),
+            None if !self.last_pass => Ok(0),
+            None => Err(format!("unknown name '{}'", token)),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let value = self.value()?;
+        check_range(value, -128, 0xFF, "byte")?;
+        Ok(value as u8)
+    }
+
+    fn nibble(&mut self) -> Result<u8, String> {
+        let value = self.value()?;
+        check_range(value, 0, 0xF, "nibble")?;
+        Ok(value as u8)
+    }
+
+    fn address(&mut self) -> Result<u16, String> {
+        let value = self.value()?;
+        check_range(value, 0, 0xFFF, "address")?;
+        Ok(value as u16)
+    }
+}
+
+// The skip running the next instruction in the opposite case
+fn negate(skip: Instruction) -> Instruction {
+    match skip {
+        Instruction::SkipVxEqNN { x, nn } => Instruction::SkipVxNeqNN { x, nn },
+        Instruction::SkipVxNeqNN { x, nn } => Instruction::SkipVxEqNN { x, nn },
+        Instruction::SkipVxEqVy { x, y } => Instruction::SkipVxNeqVy { x, y },
+        Instruction::SkipVxNeqVy { x, y } => Instruction::SkipVxEqVy { x, y },
+        Instruction::SkipVxDown { x } => Instruction::SkipVxUp { x },
+        Instruction::SkipVxUp { x } => Instruction::SkipVxDown { x },
+        other => other,
+    }
+}
+
+fn parse_number(token: &str) -> Option<i32> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i32::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i32::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn check_range(value: i32, min: i32, max: i32, kind: &str) -> Result<(), String> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(format!("{} {} out of range", kind, value))
+    }
+}
@@ -0,0 +1,243 @@
+use crate::constants::AUDIO_PATTERN_SIZE;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+pub const DEFAULT_PITCH: f32 = 440.0;
+pub const DEFAULT_VOLUME: f32 = 0.25;
+/// XO-CHIP pitch register value that plays a pattern at 4000 bits per second.
+pub const DEFAULT_PATTERN_PITCH: u8 = 64;
+
+const FRAMES_PER_SECOND: f32 = 60.0;
+
+/// Destination for the mono samples the emulator renders once per frame.
+pub trait AudioSink {
+    fn sample_rate(&self) -> u32;
+
+    /// Receives one frame worth of samples in the -1.0..=1.0 range.
+    fn write(&mut self, samples: &[f32]);
+
+    /// Called once no more samples follow, reports any error writing them.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Discards everything, for runs that should stay silent.
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn sample_rate(&self) -> u32 {
+        44100
+    }
+
+    fn write(&mut self, _samples: &[f32]) {}
+}
+
+/// Writes 16-bit mono PCM into a WAV file, the header is completed by
+/// `finish` or on drop.
+pub struct WavSink {
+    writer: BufWriter<File>,
+    sample_rate: u32,
+    samples_written: u32,
+    // The first write that failed, nothing is written after it
+    error: Option<io::Error>,
+}
+
+impl WavSink {
+    const HEADER_SIZE: u32 = 44;
+
+    pub fn create(path: &Path, sample_rate: u32) -> io::Result<Self> {
+        let mut sink = Self {
+            writer: BufWriter::new(File::create(path)?),
+            sample_rate,
+            samples_written: 0,
+            error: None,
+        };
+        sink.write_header()?;
+        Ok(sink)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let data_size = self.samples_written * 2;
+        let w = &mut self.writer;
+        w.write_all(b"RIFF")?;
+        w.write_all(&(Self::HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        w.write_all(b"WAVEfmt ")?;
+        w.write_all(&16u32.to_le_bytes())?;
+        w.write_all(&1u16.to_le_bytes())?; // PCM
+        w.write_all(&1u16.to_le_bytes())?; // mono
+        w.write_all(&self.sample_rate.to_le_bytes())?;
+        w.write_all(&(self.sample_rate * 2).to_le_bytes())?;
+        w.write_all(&2u16.to_le_bytes())?;
+        w.write_all(&16u16.to_le_bytes())?;
+        w.write_all(b"data")?;
+        w.write_all(&data_size.to_le_bytes())
+    }
+
+    // Patches the sizes into the header and flushes the file
+    fn complete(&mut self) -> io::Result<()> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+}
+
+impl AudioSink for WavSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        if self.error.is_some() {
+            return;
+        }
+        for sample in samples {
+            let pcm = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            if let Err(e) = self.writer.write_all(&pcm.to_le_bytes()) {
+                self.error = Some(e);
+                return;
+            }
+            self.samples_written += 1;
+        }
+    }
+
+    /// Completes the header over the samples written before any error.
+    fn finish(&mut self) -> io::Result<()> {
+        let completed = self.complete();
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => completed,
+        }
+    }
+}
+
+impl Drop for WavSink {
+    // Errors only reach whoever calls `finish` first
+    fn drop(&mut self) {
+        let _ = self.complete();
+    }
+}
+
+/// Checks a buzzer pitch is a positive number of hertz.
+pub fn check_pitch(pitch: f32) -> Result<(), String> {
+    if pitch.is_finite() && pitch > 0.0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "pitch must be a positive number of hertz, not {}",
+            pitch
+        ))
+    }
+}
+
+/// Checks a volume is within 0..=1.
+pub fn check_volume(volume: f32) -> Result<(), String> {
+    if (0.0..=1.0).contains(&volume) {
+        Ok(())
+    } else {
+        Err(format!("volume must be between 0 and 1, not {}", volume))
+    }
+}
+
+/// Bits per second an XO-CHIP audio pattern is played at for a pitch register value.
+pub fn pattern_rate(pitch: u8) -> f64 {
+    4000.0 * 2f64.powf((pitch as f64 - 64.0) / 48.0)
+}
+
+/// Buzzer feeding an `AudioSink`, driven by the sound timer.
+///
+/// Plays a square wave, or the XO-CHIP audio pattern once a rom has loaded one.
+pub struct Audio {
+    sink: Box<dyn AudioSink>,
+    pitch: f32,
+    volume: f32,
+    // Position inside the current wave period or pattern loop, 0.0..1.0,
+    // kept across frames so the waveform doesn't restart every 1/60s
+    phase: f64,
+    // Fractional samples carried over when the rate isn't a multiple of 60
+    pending_samples: f32,
+    buffer: Vec<f32>,
+}
+
+impl Audio {
+    /// Fails for a pitch or volume rejected by `check_pitch` or `check_volume`.
+    pub fn new(sink: Box<dyn AudioSink>, pitch: f32, volume: f32) -> Result<Self, String> {
+        check_pitch(pitch)?;
+        check_volume(volume)?;
+        Ok(Self {
+            sink,
+            pitch,
+            volume,
+            phase: 0.0,
+            pending_samples: 0.0,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Tells the sink no more samples follow, see `AudioSink::finish`.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.sink.finish()
+    }
+
+    /// Renders one frame while `playing` and silence otherwise.
+    ///
+    /// With a `pattern` its 128 bits are looped at `pattern_rate(pattern_pitch)`,
+    /// otherwise a square wave at the configured pitch is played.
+    pub fn render_frame(
+        &mut self,
+        playing: bool,
+        pattern: Option<&[u8; AUDIO_PATTERN_SIZE]>,
+        pattern_pitch: u8,
+    ) {
+        let sample_rate = self.sink.sample_rate() as f32;
+        self.pending_samples += sample_rate / FRAMES_PER_SECOND;
+        let count = self.pending_samples as usize;
+        self.pending_samples -= count as f32;
+
+        self.buffer.clear();
+        if !playing {
+            self.phase = 0.0;
+            self.buffer.resize(count, 0.0);
+        } else if let Some(pattern) = pattern {
+            let bits = (AUDIO_PATTERN_SIZE * 8) as f64;
+            let step = pattern_rate(pattern_pitch) / sample_rate as f64;
+            for _ in 0..count {
+                let level = pattern_level(pattern, self.phase * bits, step);
+                self.buffer.push(self.volume * (2.0 * level - 1.0) as f32);
+                self.phase = (self.phase + step / bits).fract();
+            }
+        } else {
+            let step = self.pitch as f64 / sample_rate as f64;
+            for _ in 0..count {
+                let high = self.phase < 0.5;
+                self.buffer
+                    .push(if high { self.volume } else { -self.volume });
+                self.phase = (self.phase + step).fract();
+            }
+        }
+
+        self.sink.write(&self.buffer);
+    }
+}
+
+/// Average value of the pattern bits covered by `[start, start + width)`.
+///
+/// Each output sample spans `width` pattern bits, averaging over the whole
+/// span instead of picking a single bit keeps fast patterns from aliasing.
+fn pattern_level(pattern: &[u8; AUDIO_PATTERN_SIZE], start: f64, width: f64) -> f64 {
+    let bits = AUDIO_PATTERN_SIZE * 8;
+    let end = start + width;
+    let mut pos = start;
+    let mut sum = 0.0;
+    while pos < end {
+        let next = (pos.floor() + 1.0).min(end);
+        let bit = pos as usize % bits;
+        if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+            sum += next - pos;
+        }
+        pos = next;
+    }
+    sum / width
+}
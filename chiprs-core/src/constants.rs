@@ -0,0 +1,15 @@
+pub const EMU_SCREEN_WIDTH: usize = 64;
+pub const EMU_SCREEN_HEIGHT: usize = 32;
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
+pub const RAM_SIZE: usize = 0x10000;
+pub const V_SIZE: usize = 16;
+pub const STACK_SIZE: usize = 16;
+pub const KEYPAD_SIZE: usize = 16;
+pub const RPL_FLAGS_SIZE: usize = 16;
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+
+pub const START_ADDR: u16 = 0x200;
+
+/// Instructions executed per 60Hz frame, between two timer decrements.
+pub const TICKS_PER_FRAME: usize = 11;
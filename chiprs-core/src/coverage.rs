@@ -0,0 +1,257 @@
+//! Which instructions a rom ran and which ways its skips went.
+//!
+//! Coverage files are plain text, one record per line, `#` starts a comment:
+//!
+//! ```text
+//! # address  times run
+//! exec 0200 1
+//! exec 0212 5
+//! # address  times the next instruction was skipped, times it wasn't
+//! skip 0212 3 2
+//! ```
+//!
+//! Addresses are hex. Files from several runs, e.g. one per test script,
+//! are combined with `merge`.
+
+use crate::constants::START_ADDR;
+use crate::disasm::{Disassembly, Line};
+use crate::instruction::Instruction;
+use crate::symbols::SymbolMap;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    counts: BTreeMap<u16, u64>,
+    // Times skipped and times not skipped
+    skips: BTreeMap<u16, (u64, u64)>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `next` is the address execution went on at
+    pub(crate) fn record(&mut self, pc: u16, instruction: &Instruction, next: u16) {
+        *self.counts.entry(pc).or_default() += 1;
+        if instruction.is_skip() {
+            let (skipped, not_skipped) = self.skips.entry(pc).or_default();
+            if next == pc.wrapping_add(instruction.size()) {
+                *not_skipped += 1;
+            } else {
+                *skipped += 1;
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut coverage = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            coverage
+                .parse_record(line)
+                .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        Ok(coverage)
+    }
+
+    fn parse_record(&mut self, record: &str) -> Result<(), String> {
+        let fields = record.split_whitespace().collect::<Vec<_>>();
+        let count = |count: &str| {
+            count
+                .parse::<u64>()
+                .map_err(|_| format!("invalid count '{}'", count))
+        };
+        match fields.as_slice() {
+            ["exec", address, times] => {
+                *self.counts.entry(parse_address(address)?).or_default() += count(times)?;
+            }
+            ["skip", address, skipped, not_skipped] => {
+                let entry = self.skips.entry(parse_address(address)?).or_default();
+                entry.0 += count(skipped)?;
+                entry.1 += count(not_skipped)?;
+            }
+            _ => return Err(format!("malformed record '{}'", record)),
+        }
+        Ok(())
+    }
+
+    /// Adds the counts of another run.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (&address, &count) in &other.counts {
+            *self.counts.entry(address).or_default() += count;
+        }
+        for (&address, &(skipped, not_skipped)) in &other.skips {
+            let entry = self.skips.entry(address).or_default();
+            entry.0 += skipped;
+            entry.1 += not_skipped;
+        }
+    }
+
+    /// Times the instruction at `address` ran.
+    pub fn count(&self, address: u16) -> u64 {
+        self.counts.get(&address).copied().unwrap_or(0)
+    }
+
+    /// Times the skip at `address` skipped and didn't skip the next instruction.
+    pub fn skips(&self, address: u16) -> (u64, u64) {
+        self.skips.get(&address).copied().unwrap_or((0, 0))
+    }
+
+    // The rom's code, including what only computed jumps reach
+    fn disassemble(&self, rom: &[u8], symbols: &SymbolMap) -> Disassembly {
+        let end = START_ADDR as usize + rom.len();
+        let mut entry_points = vec![START_ADDR];
+        entry_points.extend(
+            self.counts
+                .keys()
+                .filter(|&&address| address >= START_ADDR && (address as usize) < end),
+        );
+        Disassembly::of_rom_from(rom, &entry_points, symbols)
+    }
+
+    /// The rom as Octo source, every instruction prefixed by the times it
+    /// ran, `#####` if never, and every skip followed by the times it skipped.
+    pub fn annotate(&self, rom: &[u8], symbols: &SymbolMap) -> String {
+        let disassembly = self.disassemble(rom, symbols);
+        let (mut instructions, mut run) = (0, 0);
+        let (mut directions, mut taken) = (0, 0);
+
+        let mut out = String::new();
+        for (address, line) in disassembly.lines() {
+            if let Some(label) = disassembly.label(address) {
+                let _ = writeln!(out, "{:>10}  {:4}  : {}", "", "", label);
+            }
+            let text = disassembly.format_line(line);
+            let Line::Instruction(instruction) = line else {
+                let _ = writeln!(out, "{:>10}  {:04X}  \t{}", "", address, text);
+                continue;
+            };
+
+            let count = self.count(address);
+            instructions += 1;
+            run += (count > 0) as u32;
+            let count = match count {
+                0 => "#####".to_string(),
+                count => count.to_string(),
+            };
+            let _ = write!(out, "{:>10}  {:04X}  \t{}", count, address, text);
+
+            if instruction.is_skip() {
+                let (skipped, not_skipped) = self.skips(address);
+                directions += 2;
+                taken += (skipped > 0) as u32 + (not_skipped > 0) as u32;
+                let _ = write!(out, "\t# skipped {} of {}", skipped, skipped + not_skipped);
+                // Not when the count column already says it never ran
+                match (skipped, not_skipped) {
+                    (0, 0) => {}
+                    (0, _) => out.push_str(", never skipped"),
+                    (_, 0) => out.push_str(", always skipped"),
+                    _ => {}
+                }
+            }
+            out.push('\n');
+        }
+
+        let percent = |part: u32, all: u32| 100.0 * part as f64 / all.max(1) as f64;
+        let _ = writeln!(
+            out,
+            "\n# instructions run: {} of {} ({:.1}%)",
+            run,
+            instructions,
+            percent(run, instructions)
+        );
+        let _ = writeln!(
+            out,
+            "# skip directions taken: {} of {} ({:.1}%)",
+            taken,
+            directions,
+            percent(taken, directions)
+        );
+        out
+    }
+
+    /// An lcov tracefile for the source lines in `symbols`, skips are
+    /// branches with the skipping direction first.
+    pub fn lcov(&self, rom: &[u8], symbols: &SymbolMap) -> String {
+        // Per source file, the highest count of the instructions on each line
+        // and the skips on them
+        let mut files = BTreeMap::<PathBuf, SourceFile>::new();
+        let disassembly = self.disassemble(rom, symbols);
+        for (address, line) in disassembly.lines() {
+            let (Line::Instruction(instruction), Some(source)) =
+                (line, symbols.source_line(address))
+            else {
+                continue;
+            };
+            let file = files.entry(source.file.clone()).or_default();
+            let count = file.lines.entry(source.line).or_default();
+            *count = (*count).max(self.count(address));
+            if instruction.is_skip() {
+                file.skips.push((source.line, address));
+            }
+        }
+
+        let mut out = String::new();
+        for (path, file) in files {
+            let _ = writeln!(out, "TN:\nSF:{}", path.display());
+            let mut hit = 0;
+            for (branch, &(line, address)) in file.skips.iter().enumerate() {
+                let (skipped, not_skipped) = self.skips(address);
+                let ran = self.count(address) > 0;
+                for (direction, times) in [skipped, not_skipped].into_iter().enumerate() {
+                    hit += (times > 0) as usize;
+                    // `-` when the skip itself never ran
+                    let times = if ran {
+                        times.to_string()
+                    } else {
+                        "-".to_string()
+                    };
+                    let _ = writeln!(out, "BRDA:{},{},{},{}", line, branch, direction, times);
+                }
+            }
+            let _ = writeln!(out, "BRF:{}\nBRH:{}", 2 * file.skips.len(), hit);
+            for (line, count) in &file.lines {
+                let _ = writeln!(out, "DA:{},{}", line, count);
+            }
+            let lines_hit = file.lines.values().filter(|&&count| count > 0).count();
+            let _ = writeln!(
+                out,
+                "LF:{}\nLH:{}\nend_of_record",
+                file.lines.len(),
+                lines_hit
+            );
+        }
+        out
+    }
+}
+
+#[derive(Default)]
+struct SourceFile {
+    lines: BTreeMap<u32, u64>,
+    // Line and address of each skip
+    skips: Vec<(u32, u16)>,
+}
+
+fn parse_address(address: &str) -> Result<u16, String> {
+    u16::from_str_radix(address.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid address '{}'", address))
+}
+
+/// The coverage file text, `parse` reads it back.
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (address, count) in &self.counts {
+            writeln!(f, "exec {:04X} {}", address, count)?;
+        }
+        for (address, (skipped, not_skipped)) in &self.skips {
+            writeln!(f, "skip {:04X} {} {}", address, skipped, not_skipped)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use crate::constants::V_SIZE;
+use crate::Emulator;
+
+/// Read-only copy of the registers, timers and call stack.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CpuState {
+    pub pc: u16,
+    pub i: u16,
+    pub v: [u8; V_SIZE],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// Return addresses, the innermost call last.
+    pub stack: Vec<u16>,
+}
+
+impl Emulator {
+    pub fn cpu_state(&self) -> CpuState {
+        CpuState {
+            pc: self.counter,
+            i: self.i_reg,
+            v: self.v_reg,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            stack: self.stack[..self.stack_ptr as usize].to_vec(),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+// Larger bodies are skipped rather than read into memory
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// Debug Adapter Protocol messages, each a JSON body after a `Content-Length`
+/// header, usually over stdin and stdout.
+///
+/// The input is read on its own thread so the frontend can poll for requests.
+/// Malformed messages and failed writes are kept for the frontend to report.
+pub struct Connection {
+    // Messages, or why one was skipped
+    incoming: Receiver<Result<Value, String>>,
+    output: Box<dyn Write + Send>,
+    seq: u64,
+    errors: Vec<String>,
+    // The client is gone, nothing more is written
+    write_failed: bool,
+}
+
+impl Connection {
+    pub fn new(input: impl Read + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            let mut input = BufReader::new(input);
+            while let Some(message) = read_message(&mut input) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            incoming,
+            output: Box::new(output),
+            seq: 0,
+            errors: Vec::new(),
+            write_failed: false,
+        }
+    }
+
+    /// Waits for the next message, `None` once the input is closed.
+    pub fn recv(&mut self) -> Option<Value> {
+        loop {
+            match self.incoming.recv().ok()? {
+                Ok(message) => return Some(message),
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+
+    /// The next message if one arrived, `Disconnected` once the input is closed.
+    pub fn try_recv(&mut self) -> Result<Value, TryRecvError> {
+        loop {
+            match self.incoming.try_recv()? {
+                Ok(message) => return Ok(message),
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+
+    /// Errors since the last call, oldest first.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn respond(&mut self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    pub fn respond_error(&mut self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }));
+    }
+
+    pub fn event(&mut self, event: &str, body: Value) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    fn send(&mut self, mut message: Value) {
+        if self.write_failed {
+            return;
+        }
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        let body = message.to_string();
+        let result = write!(
+            self.output,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .and_then(|_| self.output.flush());
+        if let Err(e) = result {
+            self.errors.push(format!(
+                "unable to write to the debug adapter client: {}",
+                e
+            ));
+            self.write_failed = true;
+        }
+    }
+}
+
+// The next message or why it was skipped, `None` at the end of input
+fn read_message(input: &mut impl BufRead) -> Option<Result<Value, String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let Some(length) = length else {
+        return Some(Err(
+            "debug adapter message without a Content-Length header".to_string()
+        ));
+    };
+    if length > MAX_MESSAGE_SIZE {
+        let skipped = io::copy(&mut input.take(length as u64), &mut io::sink()).ok()?;
+        if skipped < length as u64 {
+            return None;
+        }
+        return Some(Err(format!(
+            "debug adapter message of {} bytes is too large",
+            length
+        )));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body).ok()?;
+    Some(serde_json::from_slice(&body).map_err(|e| format!("invalid debug adapter message: {}", e)))
+}
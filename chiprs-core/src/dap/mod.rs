@@ -0,0 +1,617 @@
+mod connection;
+
+use crate::dap::connection::Connection;
+use crate::debugger::{Breakpoint, StopReason};
+use crate::keys::ChipKey;
+use crate::quirks::Quirks;
+use crate::symbols::SymbolMap;
+use crate::{Emulator, Instruction};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::TryRecvError;
+
+// CHIP-8 has a single thread of execution
+const THREAD_ID: u64 = 1;
+
+// `variablesReference` of each scope
+const REGISTERS: u64 = 1;
+const TIMERS: u64 = 2;
+const KEYS: u64 = 3;
+
+/// What `launch` asked to run.
+pub struct Launch {
+    pub program: PathBuf,
+    pub quirks: Option<Quirks>,
+    pub symbols: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunState {
+    Running,
+    Stopped,
+    Exited,
+}
+
+impl RunState {
+    fn of(emu: &Emulator) -> Self {
+        if emu.has_exited() {
+            Self::Exited
+        } else if emu.is_paused() || emu.fault().is_some() {
+            Self::Stopped
+        } else {
+            Self::Running
+        }
+    }
+}
+
+// A `next` over a subroutine call, run until the instruction after it
+struct StepOver {
+    address: u16,
+    // The breakpoint was added for the step and is removed afterwards
+    temporary: bool,
+}
+
+/// Debug Adapter Protocol server, for debugging from editors.
+///
+/// Like the window controls it drives the emulator through pausing, resuming
+/// and `Emulator::next`, the frontend keeps running frames and calls `poll`
+/// once per frame. Source breakpoints need a symbol file mapping lines to
+/// addresses, instruction breakpoints work without one.
+///
+/// Only built with the `dap` feature.
+pub struct DapServer {
+    connection: Connection,
+    launch_request: Value,
+    stop_on_entry: bool,
+    symbols: SymbolMap,
+    state: RunState,
+    // Reason for the next stop when it comes from a request
+    pending_reason: Option<&'static str>,
+    step_over: Option<StepOver>,
+    source_breakpoints: HashMap<PathBuf, Vec<u16>>,
+    instruction_breakpoints: Vec<u16>,
+}
+
+impl DapServer {
+    /// Serves the client on stdin and stdout, as editors launch adapters.
+    pub fn start() -> Self {
+        Self::new(io::stdin(), io::stdout())
+    }
+
+    pub fn new(input: impl Read + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        Self {
+            connection: Connection::new(input, output),
+            launch_request: Value::Null,
+            stop_on_entry: false,
+            symbols: SymbolMap::default(),
+            state: RunState::Stopped,
+            pending_reason: None,
+            step_over: None,
+            source_breakpoints: HashMap::new(),
+            instruction_breakpoints: Vec::new(),
+        }
+    }
+
+    /// Answers `initialize` until the client sends `launch`, `None` when it
+    /// disconnects first.
+    ///
+    /// `launched` or `launch_failed` has to be called next.
+    pub fn wait_for_launch(&mut self) -> Option<Launch> {
+        while let Some(request) = self.connection.recv() {
+            match request["command"].as_str().unwrap_or("") {
+                "initialize" => self.connection.respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsConditionalBreakpoints": true,
+                        "supportsLogPoints": true,
+                        "supportsInstructionBreakpoints": true,
+                        "supportsReadMemoryRequest": true,
+                        "supportsStepBack": true,
+                    }),
+                ),
+                "launch" => match parse_launch(&request["arguments"]) {
+                    Ok(launch) => {
+                        self.stop_on_entry = request["arguments"]["stopOnEntry"]
+                            .as_bool()
+                            .unwrap_or(false);
+                        self.launch_request = request;
+                        return Some(launch);
+                    }
+                    Err(e) => self.connection.respond_error(&request, &e),
+                },
+                "disconnect" => {
+                    self.connection.respond(&request, Value::Null);
+                    return None;
+                }
+                _ => self
+                    .connection
+                    .respond_error(&request, "no program launched yet"),
+            }
+        }
+        None
+    }
+
+    pub fn launch_failed(&mut self, message: &str) {
+        let request = self.launch_request.take();
+        self.connection.respond_error(&request, message);
+    }
+
+    /// Waits for the configuration requests, the emulator stays paused until
+    /// `configurationDone`.
+    pub fn launched(&mut self, emu: &mut Emulator, symbols: SymbolMap) {
+        self.symbols = symbols;
+        if !emu.is_paused() {
+            emu.pause_or_resume();
+        }
+        self.state = RunState::Stopped;
+        let request = self.launch_request.take();
+        self.connection.respond(&request, Value::Null);
+        self.connection.event("initialized", Value::Null);
+    }
+
+    /// Prints a message in the client's debug console.
+    pub fn output(&mut self, message: &str) {
+        self.connection.event(
+            "output",
+            json!({ "category": "console", "output": format!("{}\n", message) }),
+        );
+    }
+
+    /// Malformed messages from the client and failed replies since the last
+    /// call, for the frontend to report.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        self.connection.take_errors()
+    }
+
+    /// Tells the client the program is gone, e.g. the window was closed.
+    pub fn terminated(&mut self) {
+        self.connection.event("terminated", Value::Null);
+    }
+
+    /// Answers the pending requests and reports the emulator stopping or
+    /// running again, false once the client disconnected.
+    pub fn poll(&mut self, emu: &mut Emulator) -> bool {
+        loop {
+            match self.connection.try_recv() {
+                Ok(request) => {
+                    if !self.handle(&request, emu) {
+                        return false;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+
+        let state = RunState::of(emu);
+        if state != self.state {
+            self.state = state;
+            match state {
+                RunState::Running => self.connection.event(
+                    "continued",
+                    json!({ "threadId": THREAD_ID, "allThreadsContinued": true }),
+                ),
+                RunState::Stopped => self.report_stop(emu),
+                RunState::Exited => {
+                    self.connection.event("exited", json!({ "exitCode": 0 }));
+                    self.terminated();
+                }
+            }
+        }
+        true
+    }
+
+    // False when the client asked to disconnect
+    fn handle(&mut self, request: &Value, emu: &mut Emulator) -> bool {
+        let arguments = &request["arguments"];
+        let body = match request["command"].as_str().unwrap_or("") {
+            "configurationDone" => {
+                self.connection.respond(request, Value::Null);
+                if self.stop_on_entry {
+                    self.stopped("entry", None);
+                } else {
+                    self.resume(emu);
+                }
+                return true;
+            }
+            "setBreakpoints" => self.set_breakpoints(arguments, emu),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(arguments, emu),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] })),
+            "stackTrace" => Ok(self.stack_trace(arguments, emu)),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+                    { "name": "Timers", "variablesReference": TIMERS, "expensive": false },
+                    { "name": "Keys", "variablesReference": KEYS, "expensive": false },
+                ]
+            })),
+            "variables" => Ok(variables(arguments, emu)),
+            "readMemory" => read_memory(arguments, emu),
+            "continue" => {
+                self.resume(emu);
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" | "stepIn" | "stepBack" | "reverseContinue"
+                if self.state != RunState::Stopped =>
+            {
+                Err("the program isn't stopped".to_string())
+            }
+            "next" => {
+                self.connection.respond(request, Value::Null);
+                self.next(emu);
+                return true;
+            }
+            "stepIn" => {
+                self.connection.respond(request, Value::Null);
+                self.step(emu);
+                return true;
+            }
+            "stepBack" => {
+                self.connection.respond(request, Value::Null);
+                emu.step_back();
+                self.pending_reason = Some("step");
+                self.report_stop(emu);
+                return true;
+            }
+            "reverseContinue" => {
+                self.connection.respond(request, Value::Null);
+                self.reverse_continue(emu);
+                return true;
+            }
+            "pause" => {
+                if self.state == RunState::Running {
+                    self.pending_reason = Some("pause");
+                    emu.pause_or_resume();
+                }
+                Ok(Value::Null)
+            }
+            "disconnect" => {
+                self.connection.respond(request, Value::Null);
+                return false;
+            }
+            command => Err(format!("unsupported request '{}'", command)),
+        };
+
+        match body {
+            Ok(body) => self.connection.respond(request, body),
+            Err(e) => self.connection.respond_error(request, &e),
+        }
+        true
+    }
+
+    fn resume(&mut self, emu: &mut Emulator) {
+        if emu.is_paused() && emu.fault().is_none() {
+            emu.pause_or_resume();
+            // The client knows, no `continued` event
+            self.state = RunState::Running;
+        }
+    }
+
+    fn step(&mut self, emu: &mut Emulator) {
+        // A fault is reported as the reason of the stop
+        let _ = emu.next();
+        self.pending_reason = Some("step");
+        self.report_stop(emu);
+    }
+
+    // Steps over subroutine calls by running until the instruction after them
+    fn next(&mut self, emu: &mut Emulator) {
+        let pc = emu.counter;
+        let instruction = emu.fetch(pc as usize);
+        // A faulted call can't run, stepping reports the fault again
+        if !matches!(instruction, Instruction::Call { .. }) || emu.fault().is_some() {
+            self.step(emu);
+            return;
+        }
+
+        let address = pc.wrapping_add(instruction.size());
+        let temporary = !emu.debugger().has_breakpoint(address);
+        emu.debugger_mut().add_breakpoint(address);
+        self.step_over = Some(StepOver { address, temporary });
+        self.pending_reason = Some("step");
+        self.resume(emu);
+    }
+
+    // Steps back until a breakpoint or the start of the recorded history
+    fn reverse_continue(&mut self, emu: &mut Emulator) {
+        let mut reason = "step";
+        while emu.step_back() {
+            if emu.debugger().has_breakpoint(emu.counter) {
+                reason = "breakpoint";
+                break;
+            }
+        }
+        self.pending_reason = Some(reason);
+        self.report_stop(emu);
+    }
+
+    fn report_stop(&mut self, emu: &mut Emulator) {
+        let mut reason = self.pending_reason.take();
+        if let Some(step) = self.step_over.take() {
+            if step.temporary {
+                emu.debugger_mut().remove_breakpoint(step.address);
+            }
+            // Stopped somewhere else on the way
+            if reason == Some("step") && emu.counter != step.address {
+                reason = None;
+            }
+        }
+
+        if let Some(fault) = emu.fault() {
+            let fault = fault.to_string();
+            self.stopped("exception", Some(&fault));
+            return;
+        }
+        match (reason, emu.debugger().last_stop()) {
+            (Some(reason), _) => self.stopped(reason, None),
+            (None, Some(stop @ StopReason::Watchpoint { .. })) => {
+                let stop = stop.to_string();
+                self.stopped("data breakpoint", Some(&stop));
+            }
+            (None, Some(stop)) => {
+                let stop = stop.to_string();
+                self.stopped("breakpoint", Some(&stop));
+            }
+            (None, None) => self.stopped("pause", None),
+        }
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<&str>) {
+        self.state = RunState::Stopped;
+        self.connection.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "text": text,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        );
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value, emu: &mut Emulator) -> Result<Value, String> {
+        let path = arguments["source"]["path"]
+            .as_str()
+            .map(PathBuf::from)
+            .ok_or("missing source path")?;
+        for address in self.source_breakpoints.remove(&path).unwrap_or_default() {
+            emu.debugger_mut().remove_breakpoint(address);
+        }
+
+        let mut addresses = Vec::new();
+        let mut results = Vec::new();
+        for requested in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let line = requested["line"].as_u64().unwrap_or(0) as u32;
+            let Some((address, line)) = self.symbols.address_of(&path, line) else {
+                results.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": "no code at this line",
+                }));
+                continue;
+            };
+            match new_breakpoint(requested) {
+                Ok(breakpoint) => {
+                    emu.debugger_mut().set_breakpoint(address, breakpoint);
+                    addresses.push(address);
+                    results.push(json!({
+                        "verified": true,
+                        "line": line,
+                        "instructionReference": address_reference(address),
+                    }));
+                }
+                Err(e) => results.push(json!({ "verified": false, "line": line, "message": e })),
+            }
+        }
+
+        self.source_breakpoints.insert(path, addresses);
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    fn set_instruction_breakpoints(
+        &mut self,
+        arguments: &Value,
+        emu: &mut Emulator,
+    ) -> Result<Value, String> {
+        for address in self.instruction_breakpoints.drain(..) {
+            emu.debugger_mut().remove_breakpoint(address);
+        }
+
+        let mut results = Vec::new();
+        for requested in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let offset = requested["offset"].as_i64().unwrap_or(0);
+            let address = requested["instructionReference"]
+                .as_str()
+                .and_then(parse_reference)
+                .and_then(|address| u16::try_from(address + offset).ok());
+            let result = address
+                .ok_or_else(|| "invalid instruction reference".to_string())
+                .and_then(|address| Ok((address, new_breakpoint(requested)?)));
+            match result {
+                Ok((address, breakpoint)) => {
+                    emu.debugger_mut().set_breakpoint(address, breakpoint);
+                    self.instruction_breakpoints.push(address);
+                    results.push(json!({
+                        "verified": true,
+                        "instructionReference": address_reference(address),
+                    }));
+                }
+                Err(e) => results.push(json!({ "verified": false, "message": e })),
+            }
+        }
+        Ok(json!({ "breakpoints": results }))
+    }
+
+    // The current instruction, then the calls leading to it from the stack
+    fn stack_trace(&self, arguments: &Value, emu: &Emulator) -> Value {
+        let cpu = emu.cpu_state();
+        let mut pcs = vec![cpu.pc];
+        pcs.extend(cpu.stack.iter().rev().map(|ret| ret.wrapping_sub(2)));
+
+        let frames = pcs
+            .iter()
+            .enumerate()
+            .map(|(id, &pc)| {
+                // Frames are named after the subroutine the caller jumped to
+                let name = match pcs.get(id + 1).map(|&call| emu.fetch(call as usize)) {
+                    Some(Instruction::Call { nnn }) => self.symbols.describe(nnn),
+                    _ => "main".to_string(),
+                };
+                let mut frame = json!({
+                    "id": id,
+                    "name": name,
+                    "line": 0,
+                    "column": 0,
+                    "instructionPointerReference": address_reference(pc),
+                });
+                if let Some(source) = self.symbols.source_line(pc) {
+                    frame["source"] = json!({ "path": source.file });
+                    frame["line"] = json!(source.line);
+                    frame["column"] = json!(1);
+                }
+                frame
+            })
+            .collect::<Vec<_>>();
+
+        let total = frames.len();
+        let start = arguments["startFrame"].as_u64().unwrap_or(0) as usize;
+        let levels = match arguments["levels"].as_u64() {
+            Some(0) | None => total,
+            Some(levels) => levels as usize,
+        };
+        let frames = frames
+            .into_iter()
+            .skip(start)
+            .take(levels)
+            .collect::<Vec<_>>();
+        json!({ "stackFrames": frames, "totalFrames": total })
+    }
+}
+
+fn parse_launch(arguments: &Value) -> Result<Launch, String> {
+    let program = arguments["program"]
+        .as_str()
+        .ok_or("missing 'program' in the launch arguments")?;
+    let quirks = match arguments["platform"].as_str() {
+        Some(platform) => Some(platform.parse()?),
+        None => None,
+    };
+    Ok(Launch {
+        program: PathBuf::from(program),
+        quirks,
+        symbols: arguments["symbols"].as_str().map(PathBuf::from),
+    })
+}
+
+fn new_breakpoint(requested: &Value) -> Result<Breakpoint, String> {
+    Breakpoint::new(
+        requested["condition"].as_str().filter(|s| !s.is_empty()),
+        requested["logMessage"].as_str().filter(|s| !s.is_empty()),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn address_reference(address: u16) -> String {
+    format!("0x{:04X}", address)
+}
+
+fn parse_reference(reference: &str) -> Option<i64> {
+    let digits = reference
+        .strip_prefix("0x")
+        .or_else(|| reference.strip_prefix("0X"))
+        .unwrap_or(reference);
+    i64::from_str_radix(digits, 16).ok()
+}
+
+fn variables(arguments: &Value, emu: &Emulator) -> Value {
+    let cpu = emu.cpu_state();
+    let byte = |name: String, value: u8| json!({ "name": name, "value": format!("0x{:02X}", value), "variablesReference": 0 });
+
+    let variables = match arguments["variablesReference"].as_u64() {
+        Some(REGISTERS) => {
+            let mut variables = cpu
+                .v
+                .iter()
+                .enumerate()
+                .map(|(x, &v)| byte(format!("V{:X}", x), v))
+                .collect::<Vec<_>>();
+            variables.push(json!({
+                "name": "I",
+                "value": address_reference(cpu.i),
+                "variablesReference": 0,
+                "memoryReference": address_reference(cpu.i),
+            }));
+            variables.push(json!({
+                "name": "PC",
+                "value": address_reference(cpu.pc),
+                "variablesReference": 0,
+                "memoryReference": address_reference(cpu.pc),
+            }));
+            variables
+        }
+        Some(TIMERS) => vec![
+            byte("DT".to_string(), cpu.delay_timer),
+            byte("ST".to_string(), cpu.sound_timer),
+        ],
+        Some(KEYS) => {
+            let states = emu.key_states();
+            (0..16)
+                .filter_map(ChipKey::from_hex)
+                .map(|key| {
+                    let pressed = states.get(&key).copied().unwrap_or(false);
+                    json!({
+                        "name": key.to_string(),
+                        "value": if pressed { "down" } else { "up" },
+                        "variablesReference": 0,
+                    })
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    json!({ "variables": variables })
+}
+
+fn read_memory(arguments: &Value, emu: &Emulator) -> Result<Value, String> {
+    let address = arguments["memoryReference"]
+        .as_str()
+        .and_then(parse_reference)
+        .ok_or("invalid memory reference")?
+        .saturating_add(arguments["offset"].as_i64().unwrap_or(0));
+    let count = arguments["count"].as_i64().unwrap_or(0).max(0);
+
+    // Bytes outside the address space are reported as unreadable
+    let start = address.clamp(0, 0x10000);
+    let end = address.saturating_add(count).clamp(start, 0x10000);
+    let data = (start..end)
+        .map(|address| emu.peek(address as u16))
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "address": format!("0x{:04X}", start),
+        "data": base64(&data),
+        "unreadableBytes": count.saturating_sub(end - start),
+    }))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (i, &b)| group | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,396 @@
+use crate::constants::RAM_SIZE;
+use crate::error::EmulatorError;
+use crate::expr::{Context, Expr, LogMessage, ParseError};
+use crate::instruction::{Instruction, InstructionClass};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// Why `Emulator::tick` stopped running instructions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint {
+        pc: u16,
+    },
+    ClassBreakpoint {
+        pc: u16,
+        class: InstructionClass,
+    },
+    NewAddress {
+        pc: u16,
+    },
+    Watchpoint {
+        pc: u16,
+        address: u16,
+        access: Access,
+        old: u8,
+        new: u8,
+    },
+    Fault(EmulatorError),
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Breakpoint { pc } => write!(f, "breakpoint at {:04X}", pc),
+            Self::ClassBreakpoint { pc, class } => {
+                write!(f, "{} instruction at {:04X}", class, pc)
+            }
+            Self::NewAddress { pc } => write!(f, "first execution of {:04X}", pc),
+            Self::Watchpoint {
+                pc,
+                address,
+                access: Access::Write,
+                old,
+                new,
+            } => write!(
+                f,
+                "write {:04X} at {:04X}: {:02X} -> {:02X}",
+                address, pc, old, new
+            ),
+            Self::Watchpoint {
+                pc,
+                address,
+                access,
+                new,
+                ..
+            } => write!(f, "{} {:04X} at {:04X}: {:02X}", access, address, pc, new),
+            Self::Fault(fault) => write!(f, "{}", fault),
+        }
+    }
+}
+
+impl From<EmulatorError> for StopReason {
+    fn from(fault: EmulatorError) -> Self {
+        Self::Fault(fault)
+    }
+}
+
+/// How an instruction touched memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Reading the instruction itself.
+    Fetch,
+    Read,
+    Write,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fetch => write!(f, "fetch"),
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+        }
+    }
+}
+
+/// Accesses a watchpoint triggers on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// Reads, including fetching instructions.
+    Read,
+    Write,
+    /// Writes storing a different value.
+    Change,
+}
+
+impl WatchKind {
+    fn matches(self, access: Access, old: u8, new: u8) -> bool {
+        match self {
+            Self::Read => access != Access::Write,
+            Self::Write => access == Access::Write,
+            Self::Change => access == Access::Write && old != new,
+        }
+    }
+}
+
+impl FromStr for WatchKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "change" => Ok(Self::Change),
+            _ => Err(format!(
+                "unknown watchpoint kind '{}', expected read, write or change",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<u16>,
+    pub kind: WatchKind,
+}
+
+// Logpoint messages kept until the frontend takes them, older ones are dropped
+const MAX_LOGS: usize = 1024;
+
+/// A PC breakpoint, stopping only when its condition holds.
+///
+/// A breakpoint with a log message is a logpoint, it records the formatted
+/// message instead of stopping.
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoint {
+    condition: Option<Expr>,
+    log_message: Option<LogMessage>,
+    hits: u32,
+}
+
+impl Breakpoint {
+    /// Parses the `Expr` condition and the `LogMessage`, both optional.
+    pub fn new(condition: Option<&str>, log_message: Option<&str>) -> Result<Self, ParseError> {
+        Ok(Self {
+            condition: condition.map(Expr::parse).transpose()?,
+            log_message: log_message.map(LogMessage::parse).transpose()?,
+            hits: 0,
+        })
+    }
+
+    /// Times the address was reached, whether the condition held or not.
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn is_logpoint(&self) -> bool {
+        self.log_message.is_some()
+    }
+}
+
+/// Breakpoints checked by `Emulator::tick` before every instruction.
+///
+/// Stopping pauses the emulator. Resuming runs the instruction it is paused
+/// on without checking it, whether it stopped there or was stepped onto.
+/// Stepping with `Emulator::next` never breaks.
+///
+/// Watchpoints are checked on every memory access instead and pause once the
+/// instruction making the access has finished, even when stepping.
+pub struct Debugger {
+    breakpoints: BTreeMap<u16, Breakpoint>,
+    class_breakpoints: HashSet<InstructionClass>,
+    break_on_new_address: bool,
+    watchpoints: Vec<Watchpoint>,
+    // First watchpoint hit by the instruction being executed
+    watch_hit: Option<StopReason>,
+    // Addresses executed since the last reset
+    visited: Vec<bool>,
+    // The instruction resumed from, run without breaking on it
+    resuming: Option<u16>,
+    last_stop: Option<StopReason>,
+    logs: VecDeque<String>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: BTreeMap::new(),
+            class_breakpoints: HashSet::new(),
+            break_on_new_address: false,
+            watchpoints: Vec::new(),
+            watch_hit: None,
+            visited: vec![false; RAM_SIZE],
+            resuming: None,
+            last_stop: None,
+            logs: VecDeque::new(),
+        }
+    }
+
+    /// Adds an unconditional breakpoint, keeping an existing one at `pc`.
+    pub fn add_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.entry(pc).or_default();
+    }
+
+    /// Adds or replaces the breakpoint at `pc`.
+    pub fn set_breakpoint(&mut self, pc: u16, breakpoint: Breakpoint) {
+        self.breakpoints.insert(pc, breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.remove(&pc);
+    }
+
+    /// Adds or removes a breakpoint, returns whether one is now set.
+    pub fn toggle_breakpoint(&mut self, pc: u16) -> bool {
+        if self.breakpoints.remove(&pc).is_none() {
+            self.breakpoints.insert(pc, Breakpoint::default());
+            return true;
+        }
+        false
+    }
+
+    pub fn has_breakpoint(&self, pc: u16) -> bool {
+        self.breakpoints.contains_key(&pc)
+    }
+
+    pub fn breakpoint(&self, pc: u16) -> Option<&Breakpoint> {
+        self.breakpoints.get(&pc)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.keys().copied()
+    }
+
+    /// Breaks before any instruction of `class`.
+    pub fn add_class_breakpoint(&mut self, class: InstructionClass) {
+        self.class_breakpoints.insert(class);
+    }
+
+    pub fn remove_class_breakpoint(&mut self, class: InstructionClass) {
+        self.class_breakpoints.remove(&class);
+    }
+
+    /// Breaks the first time an address is executed after a reset.
+    pub fn set_break_on_new_address(&mut self, enabled: bool) {
+        self.break_on_new_address = enabled;
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) {
+        self.watchpoints.retain(|existing| existing != watchpoint);
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Takes the messages logged by logpoints, oldest first.
+    pub fn take_logs(&mut self) -> Vec<String> {
+        self.logs.drain(..).collect()
+    }
+
+    /// Why the emulator last stopped, until it's resumed or reset.
+    pub fn last_stop(&self) -> Option<&StopReason> {
+        self.last_stop.as_ref()
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        !self.breakpoints.is_empty()
+            || !self.class_breakpoints.is_empty()
+            || self.break_on_new_address
+    }
+
+    pub(crate) fn check(
+        &mut self,
+        pc: u16,
+        instruction: &Instruction,
+        context: &Context,
+    ) -> Option<StopReason> {
+        if self.resuming.take() == Some(pc) {
+            return None;
+        }
+
+        let class = instruction.class();
+        let reason = if self.hit_breakpoint(pc, context) {
+            StopReason::Breakpoint { pc }
+        } else if self.class_breakpoints.contains(&class) {
+            StopReason::ClassBreakpoint { pc, class }
+        } else if self.break_on_new_address && !self.visited[pc as usize] {
+            StopReason::NewAddress { pc }
+        } else {
+            return None;
+        };
+
+        self.last_stop = Some(reason.clone());
+        Some(reason)
+    }
+
+    // Counts the hit and logs for logpoints, true when execution should stop
+    fn hit_breakpoint(&mut self, pc: u16, context: &Context) -> bool {
+        let Some(breakpoint) = self.breakpoints.get_mut(&pc) else {
+            return false;
+        };
+        breakpoint.hits += 1;
+
+        let context = Context {
+            hits: breakpoint.hits,
+            ..*context
+        };
+        let holds = match &breakpoint.condition {
+            Some(condition) => condition.eval(&context) != 0,
+            None => true,
+        };
+        match &breakpoint.log_message {
+            Some(message) if holds => {
+                if self.logs.len() == MAX_LOGS {
+                    self.logs.pop_front();
+                }
+                self.logs.push_back(message.format(&context));
+                false
+            }
+            _ => holds,
+        }
+    }
+
+    pub(crate) fn executed(&mut self, pc: u16) {
+        self.visited[pc as usize] = true;
+        self.resuming = None;
+        self.watch_hit = None;
+    }
+
+    /// Records an access by the instruction at `pc`, `old` and `new` are equal unless writing.
+    pub(crate) fn memory_access(
+        &mut self,
+        pc: u16,
+        address: usize,
+        access: Access,
+        old: u8,
+        new: u8,
+    ) {
+        if self.watchpoints.is_empty() || self.watch_hit.is_some() {
+            return;
+        }
+
+        let address = address as u16;
+        let hit = self.watchpoints.iter().any(|watchpoint| {
+            watchpoint.range.contains(&address) && watchpoint.kind.matches(access, old, new)
+        });
+        if hit {
+            self.watch_hit = Some(StopReason::Watchpoint {
+                pc,
+                address,
+                access,
+                old,
+                new,
+            });
+        }
+    }
+
+    /// The watchpoint hit by the last instruction, now the reason the emulator stopped.
+    pub(crate) fn take_watch_hit(&mut self) -> Option<StopReason> {
+        let reason = self.watch_hit.take()?;
+        self.last_stop = Some(reason.clone());
+        Some(reason)
+    }
+
+    /// Running on from `pc`, where the emulator was paused.
+    pub(crate) fn resumed(&mut self, pc: u16) {
+        self.last_stop = None;
+        self.resuming = Some(pc);
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.breakpoints
+            .values_mut()
+            .for_each(|breakpoint| breakpoint.hits = 0);
+        self.visited.fill(false);
+        self.resuming = None;
+        self.watch_hit = None;
+        self.last_stop = None;
+    }
+}
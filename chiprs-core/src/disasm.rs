@@ -0,0 +1,371 @@
+//! Recursive-descent disassembler producing Octo assembly.
+//!
+//! Code is found by following every path from the entry points through jumps,
+//! calls, skips and returns, so sprites and other data between routines are
+//! never decoded as instructions. Whatever no path reaches is kept as bytes.
+//! A symbol map can name addresses and mark data regions code can't be in.
+
+use crate::constants::START_ADDR;
+use crate::instruction::Instruction;
+use crate::symbols::SymbolMap;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::mem;
+use std::ops::{Range, RangeBounds};
+
+// Data lines hold at most this many bytes
+const DATA_LINE_SIZE: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Instruction(Instruction),
+    /// Bytes no code path reaches, e.g. sprites.
+    Data(Vec<u8>),
+}
+
+impl Line {
+    pub fn size(&self) -> u16 {
+        match self {
+            Self::Instruction(instruction) => instruction.size(),
+            Self::Data(bytes) => bytes.len() as u16,
+        }
+    }
+}
+
+// What a label was generated for, the first kind wins its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Reference {
+    Entry,
+    Call,
+    Jump,
+    Data,
+}
+
+/// A memory range split into instructions and data, with generated labels.
+#[derive(Debug, Clone, Default)]
+pub struct Disassembly {
+    lines: BTreeMap<u16, Line>,
+    labels: BTreeMap<u16, String>,
+}
+
+impl Disassembly {
+    /// Disassembles a rom as loaded at `START_ADDR`.
+    pub fn of_rom(rom: &[u8], symbols: &SymbolMap) -> Self {
+        Self::of_rom_from(rom, &[START_ADDR], symbols)
+    }
+
+    /// Disassembles a rom following code from more addresses than its start,
+    /// e.g. jump table targets seen running it.
+    pub fn of_rom_from(rom: &[u8], entry_points: &[u16], symbols: &SymbolMap) -> Self {
+        let mut memory = vec![0; START_ADDR as usize];
+        memory.extend_from_slice(rom);
+        let range = START_ADDR..memory.len() as u16;
+        Self::new(&memory, range, entry_points, symbols)
+    }
+
+    /// Disassembles `memory[range]`, `memory` being indexed by address. Code
+    /// is followed from `entry_points`, the first one is labelled `main`.
+    pub fn new(
+        memory: &[u8],
+        range: Range<u16>,
+        entry_points: &[u16],
+        symbols: &SymbolMap,
+    ) -> Self {
+        let mut code = BTreeMap::new();
+        let mut references = BTreeMap::new();
+        let mut pending = entry_points.to_vec();
+        if let Some(&main) = entry_points.first() {
+            references.insert(main, Reference::Entry);
+        }
+
+        let decode = |address: u16| {
+            let word = |at: usize| {
+                let byte = |at: usize| memory.get(at).copied().unwrap_or(0) as u16;
+                (byte(at) << 8) | byte(at + 1)
+            };
+            let at = address as usize;
+            let instruction = Instruction::decode(word(at), word(at + 2));
+            let last = address.wrapping_add(instruction.size() - 1);
+            let fits = at + instruction.size() as usize <= range.end as usize
+                && !symbols.is_data(address)
+                && !symbols.is_data(last);
+            fits.then_some(instruction)
+        };
+        let mut refer = |address: u16, reference: Reference| {
+            let entry = references.entry(address).or_insert(reference);
+            *entry = (*entry).min(reference);
+        };
+
+        while let Some(address) = pending.pop() {
+            if !range.contains(&address) || code.contains_key(&address) {
+                continue;
+            }
+            let Some(instruction) = decode(address) else {
+                continue;
+            };
+            code.insert(address, instruction);
+
+            let next = address.wrapping_add(instruction.size());
+            match instruction {
+                Instruction::Ret | Instruction::Exit | Instruction::Unknown { .. } => {}
+                Instruction::Jump { nnn } => {
+                    refer(nnn, Reference::Jump);
+                    pending.push(nnn);
+                }
+                // The target depends on V0, only the table itself is known
+                Instruction::JumpPlusV0 { nnn } => refer(nnn, Reference::Jump),
+                Instruction::Call { nnn } => {
+                    refer(nnn, Reference::Call);
+                    pending.extend([next, nnn]);
+                }
+                Instruction::SkipVxEqNN { .. }
+                | Instruction::SkipVxNeqNN { .. }
+                | Instruction::SkipVxEqVy { .. }
+                | Instruction::SkipVxNeqVy { .. }
+                | Instruction::SkipVxDown { .. }
+                | Instruction::SkipVxUp { .. } => {
+                    pending.push(next);
+                    if let Some(skipped) = decode(next) {
+                        pending.push(next.wrapping_add(skipped.size()));
+                    }
+                }
+                Instruction::SetI { nnn } => {
+                    refer(nnn, Reference::Data);
+                    pending.push(next);
+                }
+                Instruction::LongSetI { nnnn } => {
+                    refer(nnnn, Reference::Data);
+                    pending.push(next);
+                }
+                _ => pending.push(next),
+            }
+        }
+
+        // Jumps into the middle of an instruction can't both be kept
+        let mut lines = BTreeMap::new();
+        let mut end = range.start as usize;
+        for (&address, &instruction) in code.iter() {
+            if (address as usize) < end {
+                continue;
+            }
+            lines.insert(address, Line::Instruction(instruction));
+            end = address as usize + instruction.size() as usize;
+        }
+
+        // A skip needs an instruction after it in Octo, without one it is kept
+        // as bytes. Going backwards handles skips that skip these.
+        let skips = lines
+            .iter()
+            .rev()
+            .filter(|(_, line)| matches!(line, Line::Instruction(i) if i.is_skip()))
+            .map(|(&address, line)| (address, address as usize + line.size() as usize))
+            .collect::<Vec<_>>();
+        for (address, next) in skips {
+            let followed = u16::try_from(next)
+                .is_ok_and(|next| matches!(lines.get(&next), Some(Line::Instruction(_))));
+            if !followed {
+                lines.remove(&address);
+            }
+        }
+
+        // Start of the kept instruction covering an address, if any
+        let covering = |lines: &BTreeMap<u16, Line>, address: u16| {
+            lines
+                .range(..=address)
+                .next_back()
+                .filter(|(start, line)| {
+                    (address as usize) < **start as usize + line.size() as usize
+                })
+                .map(|(start, _)| *start)
+        };
+
+        // Labels can go before any instruction kept and any data byte. Symbol
+        // names win over generated ones, as long as they stay unique.
+        let mut labels = BTreeMap::new();
+        let mut used = HashSet::from(["main"]);
+        let labelled = references
+            .keys()
+            .copied()
+            .chain(symbols.labels().map(|(address, _)| address))
+            .collect::<BTreeSet<_>>();
+        for address in labelled {
+            if !range.contains(&address)
+                || covering(&lines, address).is_some_and(|start| start != address)
+            {
+                continue;
+            }
+            let reference = references.get(&address);
+            let name = match symbols.label(address) {
+                _ if reference == Some(&Reference::Entry) => "main".to_string(),
+                Some(name) if used.insert(name) => name.to_string(),
+                _ => match reference {
+                    Some(Reference::Call) => format!("sub_{:04X}", address),
+                    Some(Reference::Jump) => format!("label_{:04X}", address),
+                    Some(Reference::Data) => format!("data_{:04X}", address),
+                    _ => continue,
+                },
+            };
+            labels.insert(address, name);
+        }
+
+        // Everything left is data, split at labels so they can be placed
+        let mut data_lines = Vec::new();
+        let mut data = Vec::new();
+        let mut data_start = range.start;
+        for address in range {
+            let is_code = covering(&lines, address).is_some();
+            if !data.is_empty()
+                && (is_code || data.len() == DATA_LINE_SIZE || labels.contains_key(&address))
+            {
+                data_lines.push((data_start, Line::Data(mem::take(&mut data))));
+            }
+            if !is_code {
+                if data.is_empty() {
+                    data_start = address;
+                }
+                data.push(memory[address as usize]);
+            }
+        }
+        if !data.is_empty() {
+            data_lines.push((data_start, Line::Data(data)));
+        }
+        lines.extend(data_lines);
+
+        Self { lines, labels }
+    }
+
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = (u16, &Line)> {
+        self.lines.iter().map(|(address, line)| (*address, line))
+    }
+
+    /// Lines starting in `range`.
+    pub fn lines_in(
+        &self,
+        range: impl RangeBounds<u16>,
+    ) -> impl DoubleEndedIterator<Item = (u16, &Line)> {
+        self.lines
+            .range(range)
+            .map(|(address, line)| (*address, line))
+    }
+
+    /// The line `address` is part of.
+    pub fn line_containing(&self, address: u16) -> Option<(u16, &Line)> {
+        self.lines
+            .range(..=address)
+            .next_back()
+            .filter(|(start, line)| (address as usize) < **start as usize + line.size() as usize)
+            .map(|(start, line)| (*start, line))
+    }
+
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// A line as Octo source, addresses are replaced by their labels.
+    pub fn format_line(&self, line: &Line) -> String {
+        match line {
+            Line::Instruction(instruction) => self.format_instruction(instruction),
+            Line::Data(bytes) => bytes
+                .iter()
+                .map(|byte| format!("0x{:02X}", byte))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    fn format_instruction(&self, instruction: &Instruction) -> String {
+        let target = |address: u16| match self.label(address) {
+            Some(label) => label.to_string(),
+            None => format!("0x{:03X}", address),
+        };
+        let v = |x: usize| format!("v{:x}", x);
+
+        match *instruction {
+            Instruction::ClearScreen => "clear".to_string(),
+            Instruction::Ret => "return".to_string(),
+            Instruction::Exit => "exit".to_string(),
+
+            Instruction::ScrollDown { n } => format!("scroll-down {}", n),
+            Instruction::ScrollUp { n } => format!("scroll-up {}", n),
+            Instruction::ScrollRight => "scroll-right".to_string(),
+            Instruction::ScrollLeft => "scroll-left".to_string(),
+            Instruction::LowRes => "lores".to_string(),
+            Instruction::HighRes => "hires".to_string(),
+            Instruction::SelectPlanes { n } => format!("plane {}", n),
+
+            Instruction::Jump { nnn } => format!("jump {}", target(nnn)),
+            Instruction::JumpPlusV0 { nnn } => format!("jump0 {}", target(nnn)),
+            Instruction::Call { nnn } => match self.label(nnn) {
+                Some(label) => label.to_string(),
+                None => format!(":call 0x{:03X}", nnn),
+            },
+
+            // Octo conditions say when the next instruction runs, the
+            // opposite of when it is skipped
+            Instruction::SkipVxEqNN { x, nn } => format!("if {} != 0x{:02X} then", v(x), nn),
+            Instruction::SkipVxNeqNN { x, nn } => format!("if {} == 0x{:02X} then", v(x), nn),
+            Instruction::SkipVxEqVy { x, y } => format!("if {} != {} then", v(x), v(y)),
+            Instruction::SkipVxNeqVy { x, y } => format!("if {} == {} then", v(x), v(y)),
+            Instruction::SkipVxDown { x } => format!("if {} -key then", v(x)),
+            Instruction::SkipVxUp { x } => format!("if {} key then", v(x)),
+
+            Instruction::SetVxNN { x, nn } => format!("{} := 0x{:02X}", v(x), nn),
+            Instruction::SetVxVy { x, y } => format!("{} := {}", v(x), v(y)),
+            Instruction::SetVxDt { x } => format!("{} := delay", v(x)),
+            Instruction::SetVxKey { x } => format!("{} := key", v(x)),
+            Instruction::SetVxRnd { x, nn } => format!("{} := random 0x{:02X}", v(x), nn),
+            Instruction::SetI { nnn } => format!("i := {}", target(nnn)),
+            Instruction::LongSetI { nnnn } => match self.label(nnnn) {
+                Some(label) => format!("i := long {}", label),
+                None => format!("i := long 0x{:04X}", nnnn),
+            },
+            Instruction::SetVxFontToI { x } => format!("i := hex {}", v(x)),
+            Instruction::SetVxBigFontToI { x } => format!("i := bighex {}", v(x)),
+            Instruction::SetVxBcdToI { x } => format!("bcd {}", v(x)),
+            Instruction::SetDtVx { x } => format!("delay := {}", v(x)),
+            Instruction::SetStVx { x } => format!("buzzer := {}", v(x)),
+            Instruction::LoadAudioPattern => "audio".to_string(),
+            Instruction::SetPitchVx { x } => format!("pitch := {}", v(x)),
+
+            Instruction::AddVxNN { x, nn } => format!("{} += 0x{:02X}", v(x), nn),
+            Instruction::AddVxVy { x, y } => format!("{} += {}", v(x), v(y)),
+            Instruction::SubVxVy { x, y } => format!("{} -= {}", v(x), v(y)),
+            Instruction::SubVyVx { x, y } => format!("{} =- {}", v(x), v(y)),
+            Instruction::AddVxToI { x } => format!("i += {}", v(x)),
+
+            Instruction::OrVxVy { x, y } => format!("{} |= {}", v(x), v(y)),
+            Instruction::AndVxVy { x, y } => format!("{} &= {}", v(x), v(y)),
+            Instruction::XorVxVy { x, y } => format!("{} ^= {}", v(x), v(y)),
+
+            Instruction::RShiftVx { x, y } => format!("{} >>= {}", v(x), v(y)),
+            Instruction::LShiftVx { x, y } => format!("{} <<= {}", v(x), v(y)),
+
+            Instruction::Draw { x, y, n } => format!("sprite {} {} {}", v(x), v(y), n),
+            Instruction::SaveVx { x } => format!("save {}", v(x)),
+            Instruction::LoadVx { x } => format!("load {}", v(x)),
+            Instruction::SaveVxVy { x, y } => format!("save {} - {}", v(x), v(y)),
+            Instruction::LoadVxVy { x, y } => format!("load {} - {}", v(x), v(y)),
+            Instruction::SaveFlags { x } => format!("saveflags {}", v(x)),
+            Instruction::LoadFlags { x } => format!("loadflags {}", v(x)),
+
+            // Octo has no mnemonic for these, they are written as bytes
+            Instruction::Nop => "0x00 0x00".to_string(),
+            Instruction::Unknown { opcode } => {
+                format!("0x{:02X} 0x{:02X}", opcode >> 8, opcode & 0xFF)
+            }
+        }
+    }
+}
+
+/// The whole disassembly as an Octo program.
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (address, line) in self.lines() {
+            if let Some(label) = self.label(address) {
+                writeln!(f, ": {}", label)?;
+            }
+            writeln!(f, "\t{}", self.format_line(line))?;
+        }
+        Ok(())
+    }
+}
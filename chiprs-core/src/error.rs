@@ -0,0 +1,36 @@
+use std::error::Error;
+use std::fmt;
+
+/// Everything that stops the interpreter, `pc` is the address of the faulting instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmulatorError {
+    UnknownOpcode { pc: u16, opcode: u16 },
+    StackOverflow { pc: u16 },
+    StackUnderflow { pc: u16 },
+    MemoryOutOfBounds { pc: u16, address: usize },
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:04X} at {:04X}", opcode, pc)
+            }
+            Self::StackOverflow { pc } => write!(f, "stack overflow at {:04X}", pc),
+            Self::StackUnderflow { pc } => write!(f, "stack underflow at {:04X}", pc),
+            Self::MemoryOutOfBounds { pc, address } => {
+                write!(
+                    f,
+                    "memory access out of bounds ({:X}) at {:04X}",
+                    address, pc
+                )
+            }
+            Self::RomTooLarge { size, max } => {
+                write!(f, "rom is {} bytes, at most {} fit in memory", size, max)
+            }
+        }
+    }
+}
+
+impl Error for EmulatorError {}
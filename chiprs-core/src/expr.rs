@@ -0,0 +1,393 @@
+// Breakpoint conditions and logpoint messages, e.g. `v3 == 0x10 && mem[i+2] != 0`.
+//
+// Values are signed 64-bit integers and any non-zero value is true. Operators
+// follow C precedence, from loosest to tightest:
+//
+//   ||   &&   |   ^   &   == !=   < <= > >=   << >>   + -   * / %   unary ! - ~
+//
+// Variables are v0..vf, i, pc, dt, st, sp and hits (times the breakpoint was
+// reached, including this one), `mem[addr]` reads a byte. Dividing by zero gives 0.
+
+use crate::constants::{RAM_SIZE, V_SIZE};
+use std::error::Error;
+use std::fmt;
+
+/// Emulator state an expression is evaluated against.
+#[derive(Clone, Copy)]
+pub struct Context<'a> {
+    pub pc: u16,
+    pub i: u16,
+    pub v: &'a [u8; V_SIZE],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub stack_ptr: u16,
+    pub ram: &'a [u8],
+    pub hits: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based position of the offending character.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+    V(usize),
+    I,
+    Pc,
+    Dt,
+    St,
+    Sp,
+    Hits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Not,
+    Neg,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+// Binary operators by their spelling, longest first so `<=` wins over `<`
+const BINARY_OPS: [(&str, BinaryOp, u8); 18] = [
+    ("||", BinaryOp::Or, 1),
+    ("&&", BinaryOp::And, 2),
+    ("==", BinaryOp::Eq, 6),
+    ("!=", BinaryOp::Ne, 6),
+    ("<=", BinaryOp::Le, 7),
+    (">=", BinaryOp::Ge, 7),
+    ("<<", BinaryOp::Shl, 8),
+    (">>", BinaryOp::Shr, 8),
+    ("|", BinaryOp::BitOr, 3),
+    ("^", BinaryOp::BitXor, 4),
+    ("&", BinaryOp::BitAnd, 5),
+    ("<", BinaryOp::Lt, 7),
+    (">", BinaryOp::Gt, 7),
+    ("+", BinaryOp::Add, 9),
+    ("-", BinaryOp::Sub, 9),
+    ("*", BinaryOp::Mul, 10),
+    ("/", BinaryOp::Div, 10),
+    ("%", BinaryOp::Rem, 10),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Var(Var),
+    Mem(Box<Node>),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+/// A parsed expression, see the module comment for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr(Node);
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser { source, pos: 0 };
+        let node = parser.binary(0)?;
+        parser.skip_whitespace();
+        if parser.pos < source.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(Self(node))
+    }
+
+    pub fn eval(&self, context: &Context) -> i64 {
+        self.0.eval(context)
+    }
+}
+
+impl Node {
+    fn eval(&self, context: &Context) -> i64 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Var(var) => match *var {
+                Var::V(x) => context.v[x] as i64,
+                Var::I => context.i as i64,
+                Var::Pc => context.pc as i64,
+                Var::Dt => context.delay_timer as i64,
+                Var::St => context.sound_timer as i64,
+                Var::Sp => context.stack_ptr as i64,
+                Var::Hits => context.hits as i64,
+            },
+            Self::Mem(address) => {
+                let address = address.eval(context).rem_euclid(RAM_SIZE as i64);
+                context.ram[address as usize] as i64
+            }
+            Self::Unary(op, operand) => {
+                let value = operand.eval(context);
+                match op {
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::Neg => value.wrapping_neg(),
+                    UnaryOp::BitNot => !value,
+                }
+            }
+            Self::Binary(BinaryOp::Or, lhs, rhs) => {
+                (lhs.eval(context) != 0 || rhs.eval(context) != 0) as i64
+            }
+            Self::Binary(BinaryOp::And, lhs, rhs) => {
+                (lhs.eval(context) != 0 && rhs.eval(context) != 0) as i64
+            }
+            Self::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(context), rhs.eval(context));
+                match op {
+                    BinaryOp::BitOr => lhs | rhs,
+                    BinaryOp::BitXor => lhs ^ rhs,
+                    BinaryOp::BitAnd => lhs & rhs,
+                    BinaryOp::Eq => (lhs == rhs) as i64,
+                    BinaryOp::Ne => (lhs != rhs) as i64,
+                    BinaryOp::Lt => (lhs < rhs) as i64,
+                    BinaryOp::Le => (lhs <= rhs) as i64,
+                    BinaryOp::Gt => (lhs > rhs) as i64,
+                    BinaryOp::Ge => (lhs >= rhs) as i64,
+                    BinaryOp::Shl => lhs.wrapping_shl(rhs as u32),
+                    BinaryOp::Shr => lhs.wrapping_shr(rhs as u32),
+                    BinaryOp::Add => lhs.wrapping_add(rhs),
+                    BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                    BinaryOp::Mul => lhs.wrapping_mul(rhs),
+                    BinaryOp::Div => lhs.checked_div(rhs).unwrap_or(0),
+                    BinaryOp::Rem => lhs.checked_rem(rhs).unwrap_or(0),
+                    BinaryOp::Or | BinaryOp::And => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            column: self.pos + 1,
+            message: message.to_string(),
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.source[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.source.len() - trimmed.len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if !self.eat(token) {
+            return Err(self.error(&format!("expected '{}'", token)));
+        }
+        Ok(())
+    }
+
+    // Precedence climbing over `BINARY_OPS`, only operators binding tighter
+    // than `min_precedence` are consumed
+    fn binary(&mut self, min_precedence: u8) -> Result<Node, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            let Some(&(token, op, precedence)) = BINARY_OPS
+                .iter()
+                .find(|(token, _, _)| self.rest().starts_with(token))
+            else {
+                return Ok(lhs);
+            };
+            if precedence <= min_precedence {
+                return Ok(lhs);
+            }
+            self.pos += token.len();
+            let rhs = self.binary(precedence)?;
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ParseError> {
+        let op = if self.eat("!") {
+            UnaryOp::Not
+        } else if self.eat("-") {
+            UnaryOp::Neg
+        } else if self.eat("~") {
+            UnaryOp::BitNot
+        } else {
+            return self.primary();
+        };
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseError> {
+        self.skip_whitespace();
+        if self.eat("(") {
+            let expr = self.binary(0)?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+
+        let start = self.pos;
+        let len = self
+            .rest()
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("expected a number, variable or '('"));
+        }
+        let word = &self.source[start..start + len];
+        self.pos += len;
+
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            return parse_number(word).map(Node::Number).ok_or(ParseError {
+                column: start + 1,
+                message: format!("invalid number '{}'", word),
+            });
+        }
+
+        let var = match word.to_ascii_lowercase().as_str() {
+            "mem" => {
+                self.expect("[")?;
+                let address = self.binary(0)?;
+                self.expect("]")?;
+                return Ok(Node::Mem(Box::new(address)));
+            }
+            "i" => Var::I,
+            "pc" => Var::Pc,
+            "dt" => Var::Dt,
+            "st" => Var::St,
+            "sp" => Var::Sp,
+            "hits" => Var::Hits,
+            name => match name.strip_prefix('v').map(|x| usize::from_str_radix(x, 16)) {
+                Some(Ok(x)) if name.len() == 2 => Var::V(x),
+                _ => {
+                    return Err(ParseError {
+                        column: start + 1,
+                        message: format!("unknown variable '{}'", word),
+                    });
+                }
+            },
+        };
+        Ok(Node::Var(var))
+    }
+}
+
+fn parse_number(word: &str) -> Option<i64> {
+    let lower = word.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Value { expr: Expr, hex: bool },
+}
+
+/// Logpoint text with `{expr}` placeholders, `{expr:x}` prints in hex and
+/// `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogMessage {
+    parts: Vec<Part>,
+}
+
+impl LogMessage {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = source;
+        while let Some(c) = rest.chars().next() {
+            let offset = source.len() - rest.len();
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                text.push(c);
+                rest = &rest[2..];
+            } else if c == '{' {
+                let end = rest.find('}').ok_or(ParseError {
+                    column: offset + 1,
+                    message: "unclosed '{'".to_string(),
+                })?;
+                let inner = &rest[1..end];
+                let (inner, hex) = match inner.rsplit_once(':') {
+                    Some((inner, spec)) if spec.eq_ignore_ascii_case("x") => (inner, true),
+                    _ => (inner, false),
+                };
+                let expr = Expr::parse(inner).map_err(|e| ParseError {
+                    column: offset + 1 + e.column,
+                    message: e.message,
+                })?;
+
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(Part::Value { expr, hex });
+                rest = &rest[end + 1..];
+            } else if c == '}' {
+                return Err(ParseError {
+                    column: offset + 1,
+                    message: "unmatched '}'".to_string(),
+                });
+            } else {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        parts.push(Part::Text(text));
+
+        Ok(Self { parts })
+    }
+
+    pub fn format(&self, context: &Context) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Value { expr, hex: true } => format!("{:X}", expr.eval(context)),
+                Part::Value { expr, hex: false } => expr.eval(context).to_string(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,389 @@
+//! Server for the GDB remote serial protocol, so ROMs can be debugged from
+//! GDB and other frontends speaking it over TCP.
+//!
+//! Only one client is served at a time. Registers are numbered V0-VF, I, PC,
+//! SP, DT and ST, I and PC are 16 bits little endian, the others a byte each.
+//! The supported packets are `?`, `g`, `p`, `m`, `M`, `Z0`/`z0`, `s`, `c`, `D`
+//! and `k`, plus the queries GDB needs to connect and the target description
+//! naming the registers.
+//!
+//! Only built with the `gdb` feature.
+
+use crate::constants::{RAM_SIZE, V_SIZE};
+use crate::Emulator;
+use std::collections::BTreeSet;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+// Signals reported in stop replies
+const SIGTRAP: u8 = 5;
+const SIGILL: u8 = 4;
+
+const REGISTER_COUNT: usize = V_SIZE + 5;
+const PACKET_SIZE: usize = 0x1000;
+// Interrupt request, sent outside of any packet
+const INTERRUPT: u8 = 0x03;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chiprs.chip8">
+    <reg name="v0" bitsize="8" regnum="0"/>
+    <reg name="v1" bitsize="8"/>
+    <reg name="v2" bitsize="8"/>
+    <reg name="v3" bitsize="8"/>
+    <reg name="v4" bitsize="8"/>
+    <reg name="v5" bitsize="8"/>
+    <reg name="v6" bitsize="8"/>
+    <reg name="v7" bitsize="8"/>
+    <reg name="v8" bitsize="8"/>
+    <reg name="v9" bitsize="8"/>
+    <reg name="va" bitsize="8"/>
+    <reg name="vb" bitsize="8"/>
+    <reg name="vc" bitsize="8"/>
+    <reg name="vd" bitsize="8"/>
+    <reg name="ve" bitsize="8"/>
+    <reg name="vf" bitsize="8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8"/>
+    <reg name="dt" bitsize="8"/>
+    <reg name="st" bitsize="8"/>
+  </feature>
+</target>
+"#;
+
+/// Accepts a GDB connection and answers its packets, without ever blocking.
+///
+/// `poll` has to be called regularly, typically once per frame. Stepping and
+/// continuing go through `Emulator::next` and `Emulator::pause_or_resume`, the
+/// frontend keeps running frames as usual.
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<Client>,
+}
+
+struct Client {
+    stream: TcpStream,
+    received: Vec<u8>,
+    // Continuing, a stop reply is owed once the emulator pauses
+    running: bool,
+    // Breakpoints the client inserted, others belong to the frontend
+    breakpoints: BTreeSet<u16>,
+}
+
+impl GdbServer {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            client: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// Accepts a client, answers its pending packets and reports stops.
+    ///
+    /// A new client pauses the emulator. On an I/O error the client is
+    /// dropped and the error returned, the next client can connect afterwards.
+    /// Breakpoints a client leaves behind are removed when it goes.
+    pub fn poll(&mut self, emu: &mut Emulator) -> io::Result<()> {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    stream.set_nodelay(true)?;
+                    self.client = Some(Client {
+                        stream,
+                        received: Vec::new(),
+                        running: false,
+                        breakpoints: BTreeSet::new(),
+                    });
+                    if !emu.is_paused() {
+                        emu.pause_or_resume();
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let Some(client) = &mut self.client else {
+            return Ok(());
+        };
+        let result = client.poll(emu);
+        if !matches!(result, Ok(true)) {
+            for address in &client.breakpoints {
+                emu.debugger_mut().remove_breakpoint(*address);
+            }
+            self.client = None;
+        }
+        result.map(|_| ())
+    }
+}
+
+impl Client {
+    // False once the connection is closed
+    fn poll(&mut self, emu: &mut Emulator) -> io::Result<bool> {
+        let mut buffer = [0; PACKET_SIZE];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.received.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        while let Some(packet) = self.next_packet()? {
+            match packet {
+                Packet::Interrupt => {
+                    if self.running && !emu.is_paused() {
+                        emu.pause_or_resume();
+                    }
+                }
+                Packet::Command(command) => {
+                    self.stream_write(b"+")?;
+                    match self.handle(&command, emu) {
+                        Reply::Now(reply) => self.send(&reply)?,
+                        Reply::Later => {}
+                        Reply::Close(reply) => {
+                            if !reply.is_empty() {
+                                self.send(&reply)?;
+                            }
+                            return Ok(false);
+                        }
+                    }
+                }
+            }
+        }
+
+        let stopped = emu.is_paused() || emu.has_exited() || emu.fault().is_some();
+        if self.running && stopped {
+            self.running = false;
+            self.send(&stop_reply(emu))?;
+        }
+        Ok(true)
+    }
+
+    // Drops acknowledgements and garbage before the next packet, a packet
+    // with a bad checksum is answered with `-` for GDB to send it again
+    fn next_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            let Some(&first) = self.received.first() else {
+                return Ok(None);
+            };
+            if first == INTERRUPT {
+                self.received.remove(0);
+                return Ok(Some(Packet::Interrupt));
+            }
+            if first != b'$' {
+                self.received.remove(0);
+                continue;
+            }
+
+            let Some(end) = self.received.iter().position(|&b| b == b'#') else {
+                return Ok(None);
+            };
+            if self.received.len() < end + 3 {
+                return Ok(None);
+            }
+            let packet = self.received.drain(..end + 3).collect::<Vec<_>>();
+            let data = &packet[1..end];
+            let checksum = std::str::from_utf8(&packet[end + 1..])
+                .ok()
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+            if checksum != Some(checksum_of(data)) {
+                self.stream_write(b"-")?;
+                continue;
+            }
+            return Ok(Some(Packet::Command(
+                String::from_utf8_lossy(data).into_owned(),
+            )));
+        }
+    }
+
+    fn handle(&mut self, command: &str, emu: &mut Emulator) -> Reply {
+        let (kind, args) = command.split_at_checked(1).unwrap_or(("", ""));
+        let reply = match kind {
+            "?" => stop_reply(emu),
+            "g" => registers(emu).concat(),
+            "p" => parse_hex(args)
+                .and_then(|n| registers(emu).into_iter().nth(n))
+                .unwrap_or_else(|| "E01".to_string()),
+            "m" => read_memory(emu, args).unwrap_or_else(|| "E01".to_string()),
+            "M" => write_memory(emu, args).unwrap_or_else(|| "E01".to_string()),
+            "Z" | "z" => self.breakpoint(emu, kind == "Z", args),
+            "s" => {
+                // A fault is reported by the stop reply
+                let _ = emu.next();
+                stop_reply(emu)
+            }
+            "c" => {
+                if emu.is_paused() {
+                    emu.pause_or_resume();
+                }
+                self.running = true;
+                return Reply::Later;
+            }
+            "D" => {
+                if emu.is_paused() {
+                    emu.pause_or_resume();
+                }
+                return Reply::Close("OK".to_string());
+            }
+            "k" => return Reply::Close(String::new()),
+            "H" => "OK".to_string(),
+            "q" => query(args),
+            _ => String::new(),
+        };
+        Reply::Now(reply)
+    }
+
+    // `type,addr,kind`, only software and hardware breakpoints are supported.
+    // Inserting over a frontend breakpoint leaves it to the frontend.
+    fn breakpoint(&mut self, emu: &mut Emulator, insert: bool, args: &str) -> String {
+        let mut fields = args.split(',');
+        let (Some(kind), Some(address)) = (fields.next(), fields.next().and_then(parse_hex)) else {
+            return "E01".to_string();
+        };
+        if kind != "0" && kind != "1" {
+            return String::new();
+        }
+        let Ok(address) = u16::try_from(address) else {
+            return "E01".to_string();
+        };
+
+        let debugger = emu.debugger_mut();
+        if insert {
+            if !debugger.has_breakpoint(address) {
+                debugger.add_breakpoint(address);
+                self.breakpoints.insert(address);
+            }
+        } else if self.breakpoints.remove(&address) {
+            debugger.remove_breakpoint(address);
+        }
+        "OK".to_string()
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.stream_write(packet.as_bytes())
+    }
+
+    // Blocks while writing, replies are small and the client is waiting for them
+    fn stream_write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream.set_nonblocking(false)?;
+        let result = self.stream.write_all(data);
+        self.stream.set_nonblocking(true)?;
+        result
+    }
+}
+
+enum Packet {
+    Interrupt,
+    Command(String),
+}
+
+enum Reply {
+    Now(String),
+    // Continuing, the stop reply is sent by `poll` once the emulator pauses
+    Later,
+    // Sent before closing the connection, unless empty
+    Close(String),
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+}
+
+fn parse_hex(digits: &str) -> Option<usize> {
+    usize::from_str_radix(digits, 16).ok()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn stop_reply(emu: &Emulator) -> String {
+    if emu.has_exited() {
+        "W00".to_string()
+    } else if emu.fault().is_some() {
+        format!("S{:02x}", SIGILL)
+    } else {
+        format!("S{:02x}", SIGTRAP)
+    }
+}
+
+// Hex encoded values, in register number order
+fn registers(emu: &Emulator) -> Vec<String> {
+    let mut registers = Vec::with_capacity(REGISTER_COUNT);
+    registers.extend(emu.v_reg.iter().map(|v| hex(&[*v])));
+    registers.push(hex(&emu.i_reg.to_le_bytes()));
+    registers.push(hex(&emu.counter.to_le_bytes()));
+    registers.push(hex(&[emu.stack_ptr as u8]));
+    registers.push(hex(&[emu.delay_timer]));
+    registers.push(hex(&[emu.sound_timer]));
+    registers
+}
+
+// `addr,length` for `m`, the range has to be within RAM
+fn memory_range(args: &str) -> Option<std::ops::Range<usize>> {
+    let (address, length) = args.split_once(',')?;
+    let (address, length) = (parse_hex(address)?, parse_hex(length)?);
+    let end = address.checked_add(length)?;
+    (end <= RAM_SIZE).then_some(address..end)
+}
+
+fn read_memory(emu: &Emulator, args: &str) -> Option<String> {
+    Some(hex(&emu.ram[memory_range(args)?]))
+}
+
+// `addr,length:bytes`, poked without triggering watchpoints
+fn write_memory(emu: &mut Emulator, args: &str) -> Option<String> {
+    let (range, data) = args.split_once(':')?;
+    let range = memory_range(range)?;
+    if data.len() != range.len() * 2 {
+        return None;
+    }
+    let bytes = (0..data.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(data.get(at..at + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    for (address, byte) in range.zip(bytes) {
+        emu.poke(address as u16, byte);
+    }
+    Some("OK".to_string())
+}
+
+fn query(args: &str) -> String {
+    if args.starts_with("Supported") {
+        return format!("PacketSize={:x};qXfer:features:read+", PACKET_SIZE);
+    }
+    if args == "Attached" {
+        return "1".to_string();
+    }
+    if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+        return target_xml(range).unwrap_or_else(|| "E01".to_string());
+    }
+    String::new()
+}
+
+// `offset,length` of the target description, prefixed with `l` for the last part
+fn target_xml(range: &str) -> Option<String> {
+    let (offset, length) = range.split_once(',')?;
+    let (offset, length) = (parse_hex(offset)?, parse_hex(length)?);
+    let start = offset.min(TARGET_XML.len());
+    let end = offset.saturating_add(length).min(TARGET_XML.len());
+    let marker = if end == TARGET_XML.len() { 'l' } else { 'm' };
+    Some(format!("{}{}", marker, &TARGET_XML[start..end]))
+}
@@ -0,0 +1,476 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Nop,
+    ClearScreen,
+    Ret,
+    Exit,
+
+    ScrollDown { n: u8 },
+    ScrollUp { n: u8 },
+    ScrollRight,
+    ScrollLeft,
+    LowRes,
+    HighRes,
+    SelectPlanes { n: u8 },
+
+    Jump { nnn: u16 },
+    JumpPlusV0 { nnn: u16 },
+    Call { nnn: u16 },
+
+    SkipVxEqNN { x: usize, nn: u8 },
+    SkipVxNeqNN { x: usize, nn: u8 },
+    SkipVxEqVy { x: usize, y: usize },
+    SkipVxNeqVy { x: usize, y: usize },
+
+    SetVxNN { x: usize, nn: u8 },
+    SetVxVy { x: usize, y: usize },
+    SetVxDt { x: usize },
+    SetVxKey { x: usize },
+    SetVxRnd { x: usize, nn: u8 },
+    SetI { nnn: u16 },
+    LongSetI { nnnn: u16 },
+    SetVxFontToI { x: usize },
+    SetVxBigFontToI { x: usize },
+    SetVxBcdToI { x: usize },
+    SetDtVx { x: usize },
+    SetStVx { x: usize },
+    LoadAudioPattern,
+    SetPitchVx { x: usize },
+
+    AddVxNN { x: usize, nn: u8 },
+    AddVxVy { x: usize, y: usize },
+    SubVxVy { x: usize, y: usize },
+    SubVyVx { x: usize, y: usize },
+    AddVxToI { x: usize },
+
+    OrVxVy { x: usize, y: usize },
+    AndVxVy { x: usize, y: usize },
+    XorVxVy { x: usize, y: usize },
+
+    RShiftVx { x: usize, y: usize },
+    LShiftVx { x: usize, y: usize },
+
+    SkipVxDown { x: usize },
+    SkipVxUp { x: usize },
+
+    Draw { x: usize, y: usize, n: u8 },
+    SaveVx { x: usize },
+    LoadVx { x: usize },
+    SaveVxVy { x: usize, y: usize },
+    LoadVxVy { x: usize, y: usize },
+    SaveFlags { x: usize },
+    LoadFlags { x: usize },
+
+    Unknown { opcode: u16 },
+}
+
+impl Instruction {
+    /// Decodes the instruction starting with `opcode`, `next` is the word after it
+    /// and is only used by the four byte `F000 NNNN`.
+    pub fn decode(opcode: u16, next: u16) -> Self {
+        match opcode {
+            // F000 NNNN: set I to a 16-bit address
+            0xF000 => Self::LongSetI { nnnn: next },
+            _ => Self::from_opcode(opcode),
+        }
+    }
+
+    /// Decodes a single two byte opcode, see `decode` for `F000 NNNN`.
+    pub fn from_opcode(opcode: u16) -> Self {
+        let d1 = (opcode >> 12) as u8;
+        let d2 = ((opcode & 0x0F00) >> 8) as u8;
+        let d3 = ((opcode & 0x00F0) >> 4) as u8;
+        let d4 = (opcode & 0x000F) as u8;
+
+        let nnn = opcode & 0x0FFF;
+        let nn = (opcode & 0x00FF) as u8;
+        let x = d2 as usize;
+        let y = d3 as usize;
+        let n = d4;
+
+        match (d1, d2, d3, d4) {
+            // 0000: NOP
+            (0, 0, 0, 0) => Self::Nop,
+            // 00CN: scroll down N pixels
+            (0, 0, 0xC, _) => Self::ScrollDown { n },
+            // 00DN: scroll up N pixels
+            (0, 0, 0xD, _) => Self::ScrollUp { n },
+            // 00E0: Clear screen
+            (0, 0, 0xE, 0) => Self::ClearScreen,
+            // 00EE: return from a subroutine
+            (0, 0, 0xE, 0xE) => Self::Ret,
+            // 00FB: scroll right 4 pixels
+            (0, 0, 0xF, 0xB) => Self::ScrollRight,
+            // 00FC: scroll left 4 pixels
+            (0, 0, 0xF, 0xC) => Self::ScrollLeft,
+            // 00FD: exit the interpreter
+            (0, 0, 0xF, 0xD) => Self::Exit,
+            // 00FE: switch to low-res mode
+            (0, 0, 0xF, 0xE) => Self::LowRes,
+            // 00FF: switch to high-res mode
+            (0, 0, 0xF, 0xF) => Self::HighRes,
+            // 1NNN: jump
+            (1, _, _, _) => Self::Jump { nnn },
+            // 2NNN: execute subroutine
+            (2, _, _, _) => Self::Call { nnn },
+            // 3XNN: skip if vx == nn
+            (3, _, _, _) => Self::SkipVxEqNN { x, nn },
+            // 4XNN: skip if vx != nn
+            (4, _, _, _) => Self::SkipVxNeqNN { x, nn },
+            // 5XY0: skip if vx == vy
+            (5, _, _, 0) => Self::SkipVxEqVy { x, y },
+            // 5XY2: save VX..VY into I
+            (5, _, _, 2) => Self::SaveVxVy { x, y },
+            // 5XY3: load I into VX..VY
+            (5, _, _, 3) => Self::LoadVxVy { x, y },
+            // 6XNN: set register VX
+            (6, _, _, _) => Self::SetVxNN { x, nn },
+            // 7XNN: add value to register VX
+            (7, _, _, _) => Self::AddVxNN { x, nn },
+            // 8XY0: store the value of VY in VX
+            (8, _, _, 0) => Self::SetVxVy { x, y },
+            // 8XY1: Set VX to VX or VY
+            (8, _, _, 1) => Self::OrVxVy { x, y },
+            // 8XY2: Set VX to VX AND VY
+            (8, _, _, 2) => Self::AndVxVy { x, y },
+            // 8XY3: Set VX to VX XOR VY
+            (8, _, _, 3) => Self::XorVxVy { x, y },
+            // 8XY4: Add VY to VX
+            (8, _, _, 4) => Self::AddVxVy { x, y },
+            // 8XY5: Sub VY from VX
+            (8, _, _, 5) => Self::SubVxVy { x, y },
+            // 8XY6: right shift VX
+            (8, _, _, 6) => Self::RShiftVx { x, y },
+            // 8XY7: set VX to VY - VX
+            (8, _, _, 7) => Self::SubVyVx { x, y },
+            // 8XYE: left shift VX
+            (8, _, _, 0xE) => Self::LShiftVx { x, y },
+            // 9XY0: skip if vx == vy
+            (9, _, _, 0) => Self::SkipVxNeqVy { x, y },
+            // ANNN: set index register I
+            (0xA, _, _, _) => Self::SetI { nnn },
+            // BNNN: jump to NNN + V0
+            (0xB, _, _, _) => Self::JumpPlusV0 { nnn },
+            // CXNN: VX = rand & NN
+            (0xC, _, _, _) => Self::SetVxRnd { x, nn },
+            // DXYN: display/draw (DXY0 draws a 16x16 sprite)
+            (0xD, _, _, _) => Self::Draw { x, y, n },
+            // EX9E: skip if VX key is pressed
+            (0xE, _, 9, 0xE) => Self::SkipVxDown { x },
+            // EXA1: skip if VX key is not pressed
+            (0xE, _, 0xA, 1) => Self::SkipVxUp { x },
+            // FN01: select drawing planes
+            (0xF, _, 0, 1) => Self::SelectPlanes { n: d2 },
+            // FX07: store delay timer in VX
+            (0xF, _, 0, 7) => Self::SetVxDt { x },
+            // FX0A: get key
+            (0xF, _, 0, 0xA) => Self::SetVxKey { x },
+            // FX15: delay timer = VX
+            (0xF, _, 1, 5) => Self::SetDtVx { x },
+            // FX18: sound timer = VX
+            (0xF, _, 1, 8) => Self::SetStVx { x },
+            // F002: load the 16 byte audio pattern at I
+            (0xF, 0, 0, 2) => Self::LoadAudioPattern,
+            // FX3A: audio pattern pitch = VX
+            (0xF, _, 3, 0xA) => Self::SetPitchVx { x },
+            // FX1E: add VX to I
+            (0xF, _, 1, 0xE) => Self::AddVxToI { x },
+            // FX29: font character
+            (0xF, _, 2, 9) => Self::SetVxFontToI { x },
+            // FX30: big font character
+            (0xF, _, 3, 0) => Self::SetVxBigFontToI { x },
+            // FX33: binary-coded decimal conversion
+            (0xF, _, 3, 3) => Self::SetVxBcdToI { x },
+            // FX55: save V0..VX into I
+            (0xF, _, 5, 5) => Self::SaveVx { x },
+            // FX65: load I into V0..VX
+            (0xF, _, 6, 5) => Self::LoadVx { x },
+            // FX75: save V0..VX into the RPL user flags
+            (0xF, _, 7, 5) => Self::SaveFlags { x },
+            // FX85: load the RPL user flags into V0..VX
+            (0xF, _, 8, 5) => Self::LoadFlags { x },
+            _ => Self::Unknown { opcode },
+        }
+    }
+
+    /// The bytes `decode` reads the instruction from, 2 or 4 of them.
+    pub fn encode(&self) -> Vec<u8> {
+        let xy = |x: usize, y: usize| (x as u16) << 8 | (y as u16) << 4;
+        let opcode = match *self {
+            Self::Nop => 0x0000,
+            Self::ClearScreen => 0x00E0,
+            Self::Ret => 0x00EE,
+            Self::Exit => 0x00FD,
+
+            Self::ScrollDown { n } => 0x00C0 | n as u16,
+            Self::ScrollUp { n } => 0x00D0 | n as u16,
+            Self::ScrollRight => 0x00FB,
+            Self::ScrollLeft => 0x00FC,
+            Self::LowRes => 0x00FE,
+            Self::HighRes => 0x00FF,
+            Self::SelectPlanes { n } => 0xF001 | (n as u16) << 8,
+
+            Self::Jump { nnn } => 0x1000 | nnn,
+            Self::JumpPlusV0 { nnn } => 0xB000 | nnn,
+            Self::Call { nnn } => 0x2000 | nnn,
+
+            Self::SkipVxEqNN { x, nn } => 0x3000 | xy(x, 0) | nn as u16,
+            Self::SkipVxNeqNN { x, nn } => 0x4000 | xy(x, 0) | nn as u16,
+            Self::SkipVxEqVy { x, y } => 0x5000 | xy(x, y),
+            Self::SkipVxNeqVy { x, y } => 0x9000 | xy(x, y),
+
+            Self::SetVxNN { x, nn } => 0x6000 | xy(x, 0) | nn as u16,
+            Self::SetVxVy { x, y } => 0x8000 | xy(x, y),
+            Self::SetVxDt { x } => 0xF007 | xy(x, 0),
+            Self::SetVxKey { x } => 0xF00A | xy(x, 0),
+            Self::SetVxRnd { x, nn } => 0xC000 | xy(x, 0) | nn as u16,
+            Self::SetI { nnn } => 0xA000 | nnn,
+            Self::LongSetI { nnnn } => return vec![0xF0, 0x00, (nnnn >> 8) as u8, nnnn as u8],
+            Self::SetVxFontToI { x } => 0xF029 | xy(x, 0),
+            Self::SetVxBigFontToI { x } => 0xF030 | xy(x, 0),
+            Self::SetVxBcdToI { x } => 0xF033 | xy(x, 0),
+            Self::SetDtVx { x } => 0xF015 | xy(x, 0),
+            Self::SetStVx { x } => 0xF018 | xy(x, 0),
+            Self::LoadAudioPattern => 0xF002,
+            Self::SetPitchVx { x } => 0xF03A | xy(x, 0),
+
+            Self::AddVxNN { x, nn } => 0x7000 | xy(x, 0) | nn as u16,
+            Self::AddVxVy { x, y } => 0x8004 | xy(x, y),
+            Self::SubVxVy { x, y } => 0x8005 | xy(x, y),
+            Self::SubVyVx { x, y } => 0x8007 | xy(x, y),
+            Self::AddVxToI { x } => 0xF01E | xy(x, 0),
+
+            Self::OrVxVy { x, y } => 0x8001 | xy(x, y),
+            Self::AndVxVy { x, y } => 0x8002 | xy(x, y),
+            Self::XorVxVy { x, y } => 0x8003 | xy(x, y),
+
+            Self::RShiftVx { x, y } => 0x8006 | xy(x, y),
+            Self::LShiftVx { x, y } => 0x800E | xy(x, y),
+
+            Self::SkipVxDown { x } => 0xE09E | xy(x, 0),
+            Self::SkipVxUp { x } => 0xE0A1 | xy(x, 0),
+
+            Self::Draw { x, y, n } => 0xD000 | xy(x, y) | n as u16,
+            Self::SaveVx { x } => 0xF055 | xy(x, 0),
+            Self::LoadVx { x } => 0xF065 | xy(x, 0),
+            Self::SaveVxVy { x, y } => 0x5002 | xy(x, y),
+            Self::LoadVxVy { x, y } => 0x5003 | xy(x, y),
+            Self::SaveFlags { x } => 0xF075 | xy(x, 0),
+            Self::LoadFlags { x } => 0xF085 | xy(x, 0),
+
+            Self::Unknown { opcode } => opcode,
+        };
+        opcode.to_be_bytes().to_vec()
+    }
+
+    /// Length of the instruction in bytes.
+    pub fn size(&self) -> u16 {
+        match self {
+            Self::LongSetI { .. } => 4,
+            _ => 2,
+        }
+    }
+
+    /// Whether the instruction may skip the one after it.
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Self::SkipVxEqNN { .. }
+                | Self::SkipVxNeqNN { .. }
+                | Self::SkipVxEqVy { .. }
+                | Self::SkipVxNeqVy { .. }
+                | Self::SkipVxDown { .. }
+                | Self::SkipVxUp { .. }
+        )
+    }
+
+    pub fn class(&self) -> InstructionClass {
+        match self {
+            Self::Draw { .. } => InstructionClass::Draw,
+            Self::ClearScreen
+            | Self::ScrollDown { .. }
+            | Self::ScrollUp { .. }
+            | Self::ScrollRight
+            | Self::ScrollLeft
+            | Self::LowRes
+            | Self::HighRes
+            | Self::SelectPlanes { .. } => InstructionClass::Screen,
+            Self::Nop
+            | Self::Ret
+            | Self::Exit
+            | Self::Jump { .. }
+            | Self::JumpPlusV0 { .. }
+            | Self::Call { .. } => InstructionClass::Flow,
+            Self::SkipVxEqNN { .. }
+            | Self::SkipVxNeqNN { .. }
+            | Self::SkipVxEqVy { .. }
+            | Self::SkipVxNeqVy { .. } => InstructionClass::Skip,
+            Self::SkipVxDown { .. } | Self::SkipVxUp { .. } | Self::SetVxKey { .. } => {
+                InstructionClass::Keys
+            }
+            Self::SetVxDt { .. } | Self::SetDtVx { .. } | Self::SetStVx { .. } => {
+                InstructionClass::Timers
+            }
+            Self::LoadAudioPattern | Self::SetPitchVx { .. } => InstructionClass::Sound,
+            Self::SetI { .. }
+            | Self::LongSetI { .. }
+            | Self::SetVxFontToI { .. }
+            | Self::SetVxBigFontToI { .. }
+            | Self::SetVxBcdToI { .. }
+            | Self::AddVxToI { .. }
+            | Self::SaveVx { .. }
+            | Self::LoadVx { .. }
+            | Self::SaveVxVy { .. }
+            | Self::LoadVxVy { .. }
+            | Self::SaveFlags { .. }
+            | Self::LoadFlags { .. } => InstructionClass::Memory,
+            Self::SetVxNN { .. }
+            | Self::SetVxVy { .. }
+            | Self::SetVxRnd { .. }
+            | Self::AddVxNN { .. }
+            | Self::AddVxVy { .. }
+            | Self::SubVxVy { .. }
+            | Self::SubVyVx { .. }
+            | Self::OrVxVy { .. }
+            | Self::AndVxVy { .. }
+            | Self::XorVxVy { .. }
+            | Self::RShiftVx { .. }
+            | Self::LShiftVx { .. } => InstructionClass::Arithmetic,
+            Self::Unknown { .. } => InstructionClass::Unknown,
+        }
+    }
+}
+
+/// Coarse groups of instructions, to break on or filter a whole kind at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstructionClass {
+    Draw,
+    /// Clearing, scrolling, resolution and plane selection.
+    Screen,
+    /// Jumps, calls, returns and exit.
+    Flow,
+    /// Skips comparing registers.
+    Skip,
+    /// Key skips and waiting for a key.
+    Keys,
+    Timers,
+    Sound,
+    /// Everything touching I or reading and writing memory.
+    Memory,
+    /// Register loads, math, logic, shifts and random numbers.
+    Arithmetic,
+    Unknown,
+}
+
+impl InstructionClass {
+    pub const ALL: [(&'static str, InstructionClass); 10] = [
+        ("draw", InstructionClass::Draw),
+        ("screen", InstructionClass::Screen),
+        ("flow", InstructionClass::Flow),
+        ("skip", InstructionClass::Skip),
+        ("keys", InstructionClass::Keys),
+        ("timers", InstructionClass::Timers),
+        ("sound", InstructionClass::Sound),
+        ("memory", InstructionClass::Memory),
+        ("arithmetic", InstructionClass::Arithmetic),
+        ("unknown", InstructionClass::Unknown),
+    ];
+}
+
+impl FromStr for InstructionClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, class)| *class)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(|(name, _)| name).join(", ");
+                format!(
+                    "unknown instruction class '{}', expected one of: {}",
+                    s, names
+                )
+            })
+    }
+}
+
+impl fmt::Display for InstructionClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = Self::ALL.iter().find(|(_, class)| class == self).unwrap();
+        write!(f, "{}", name)
+    }
+}
+
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Nop => write!(f, "0000: Nop"),
+            Self::ClearScreen => write!(f, "00E0: ClearScreen"),
+            Self::Ret => write!(f, "00EE: Ret"),
+            Self::Exit => write!(f, "00FD: Exit"),
+
+            Self::ScrollDown { n } => write!(f, "{:04X}: ScrollDown {{ n: {:X} }}", 0x00C0 | n as u16, n),
+            Self::ScrollUp { n } => write!(f, "{:04X}: ScrollUp {{ n: {:X} }}", 0x00D0 | n as u16, n),
+            Self::ScrollRight => write!(f, "00FB: ScrollRight"),
+            Self::ScrollLeft => write!(f, "00FC: ScrollLeft"),
+            Self::LowRes => write!(f, "00FE: LowRes"),
+            Self::HighRes => write!(f, "00FF: HighRes"),
+            Self::SelectPlanes { n } => write!(f, "{:04X}: SelectPlanes {{ n: {:X} }}", 0xF001 | (n as u16) << 8, n),
+
+            Self::Jump { nnn } => write!(f, "{:04X}: Jump {{ nnn: {:03X} }}", 0x1000 | nnn, nnn),
+            Self::JumpPlusV0 { nnn } => write!(f, "{:04X}: JumpPlusV0 {{ nnn: {:03X} }}", 0xB000 | nnn, nnn),
+            Self::Call { nnn } => write!(f, "{:04X}: Call {{ nnn: {:03X} }}", 0x2000 | nnn, nnn),
+
+            Self::SkipVxEqNN { x, nn } => write!(f, "{:04X}: SkipVxEqNN {{ x: {:X}, nn: {:02X} }}", 0x3000 | (x as u16) << 8 | nn as u16, x, nn),
+            Self::SkipVxNeqNN { x, nn } => write!(f, "{:04X}: SkipVxNeqNN {{ x: {:X}, nn: {:02X} }}", 0x4000 | (x as u16) << 8 | nn as u16, x, nn),
+            Self::SkipVxEqVy { x, y } => write!(f, "{:04X}: SkipVxEqVy {{ x: {:X}, y: {:X} }}", 0x5000 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::SkipVxNeqVy { x, y } => write!(f, "{:04X}: SkipVxNeqVy {{ x: {:X}, y: {:X} }}", 0x9000 | (x as u16) << 8 | (y as u16) << 4, x, y),
+
+            Self::SetVxNN { x, nn } => write!(f, "{:04X}: SetVxNN {{ x: {:X}, nn: {:02X} }}", 0x6000 | (x as u16) << 8 | nn as u16, x, nn),
+            Self::SetVxVy { x, y } => write!(f, "{:04X}: SetVxVy {{ x: {:X}, y: {:X} }}", 0x8000 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::SetVxDt { x } => write!(f, "{:04X}: SetVxDt {{ x: {:X} }}", 0xF007 | (x as u16) << 8, x),
+            Self::SetVxKey { x } => write!(f, "{:04X}: SetVxKey {{ x: {:X} }}", 0xF00A | (x as u16) << 8, x),
+            Self::SetVxRnd { x, nn } => write!(f, "{:04X}: SetVxRnd {{ x: {:X}, nn: {:02X} }}", 0xC000 | (x as u16) << 8 | nn as u16, x, nn),
+            Self::SetI { nnn } => write!(f, "{:04X}: SetI {{ nnn: {:03X} }}", 0xA000 | nnn, nnn),
+            Self::LongSetI { nnnn } => write!(f, "F000 {:04X}: LongSetI {{ nnnn: {:04X} }}", nnnn, nnnn),
+            Self::SetVxFontToI { x } => write!(f, "{:04X}: SetVxFontToI {{ x: {:X} }}", 0xF029 | (x as u16) << 8, x),
+            Self::SetVxBigFontToI { x } => write!(f, "{:04X}: SetVxBigFontToI {{ x: {:X} }}", 0xF030 | (x as u16) << 8, x),
+            Self::SetVxBcdToI { x } => write!(f, "{:04X}: SetVxBcdToI {{ x: {:X} }}", 0xF033 | (x as u16) << 8, x),
+            Self::SetDtVx { x } => write!(f, "{:04X}: SetDtVx {{ x: {:X} }}", 0xF015 | (x as u16) << 8, x),
+            Self::SetStVx { x } => write!(f, "{:04X}: SetStVx {{ x: {:X} }}", 0xF018 | (x as u16) << 8, x),
+            Self::LoadAudioPattern => write!(f, "F002: LoadAudioPattern"),
+            Self::SetPitchVx { x } => write!(f, "{:04X}: SetPitchVx {{ x: {:X} }}", 0xF03A | (x as u16) << 8, x),
+
+            Self::AddVxNN { x, nn } => write!(f, "{:04X}: AddVxNN {{ x: {:X}, nn: {:02X} }}", 0x7000 | (x as u16) << 8 | nn as u16, x, nn),
+            Self::AddVxVy { x, y } => write!(f, "{:04X}: AddVxVy {{ x: {:X}, y: {:X} }}", 0x8004 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::SubVxVy { x, y } => write!(f, "{:04X}: SubVxVy {{ x: {:X}, y: {:X} }}", 0x8005 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::SubVyVx { x, y } => write!(f, "{:04X}: SubVyVx {{ x: {:X}, y: {:X} }}", 0x8007 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::AddVxToI { x } => write!(f, "{:04X}: AddVxToI {{ x: {:X} }}", 0xF01E | (x as u16) << 8, x),
+
+            Self::OrVxVy { x, y } => write!(f, "{:04X}: OrVxVy {{ x: {:X}, y: {:X} }}", 0x8001 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::AndVxVy { x, y } => write!(f, "{:04X}: AndVxVy {{ x: {:X}, y: {:X} }}", 0x8002 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::XorVxVy { x, y } => write!(f, "{:04X}: XorVxVy {{ x: {:X}, y: {:X} }}", 0x8003 | (x as u16) << 8 | (y as u16) << 4, x, y),
+
+            Self::RShiftVx { x, y } => write!(f, "{:04X}: RShiftVx {{ x: {:X}, y: {:X} }}", 0x8006 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::LShiftVx { x, y } => write!(f, "{:04X}: LShiftVx {{ x: {:X}, y: {:X} }}", 0x800E | (x as u16) << 8 | (y as u16) << 4, x, y),
+
+            Self::SkipVxDown { x } => write!(f, "{:04X}: SkipVxDown {{ x: {:X} }}", 0xE09E | (x as u16) << 8, x),
+            Self::SkipVxUp { x } => write!(f, "{:04X}: SkipVxUp {{ x: {:X} }}", 0xE0A1 | (x as u16) << 8, x),
+
+            Self::Draw { x, y, n } => write!(f, "{:04X}: Draw {{ x: {:X}, y: {:X}, n: {:X} }}", 0xD000 | (x as u16) << 8 | (y as u16) << 4 | n as u16, x, y, n),
+            Self::SaveVx { x } => write!(f, "{:04X}: SaveVx {{ x: {:X} }}", 0xF055 | (x as u16) << 8, x),
+            Self::LoadVx { x } => write!(f, "{:04X}: LoadVx {{ x: {:X} }}", 0xF065 | (x as u16) << 8, x),
+            Self::SaveVxVy { x, y } => write!(f, "{:04X}: SaveVxVy {{ x: {:X}, y: {:X} }}", 0x5002 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::LoadVxVy { x, y } => write!(f, "{:04X}: LoadVxVy {{ x: {:X}, y: {:X} }}", 0x5003 | (x as u16) << 8 | (y as u16) << 4, x, y),
+            Self::SaveFlags { x } => write!(f, "{:04X}: SaveFlags {{ x: {:X} }}", 0xF075 | (x as u16) << 8, x),
+            Self::LoadFlags { x } => write!(f, "{:04X}: LoadFlags {{ x: {:X} }}", 0xF085 | (x as u16) << 8, x),
+
+            Self::Unknown { opcode } => write!(f, "{:04X}: Unknown", opcode),
+        }
+    }
+}
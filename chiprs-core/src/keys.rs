@@ -21,7 +21,7 @@ pub enum ChipKey {
 }
 
 impl ChipKey {
-    pub fn to_hex(&self) -> u8 {
+    pub fn to_hex(self) -> u8 {
         match self {
             ChipKey::Num0 => 0x0,
             ChipKey::Num1 => 0x1,
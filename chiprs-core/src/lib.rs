@@ -0,0 +1,796 @@
+//! CHIP-8, SUPER-CHIP and XO-CHIP interpreter, independent of any frontend.
+
+pub mod asm;
+pub mod audio;
+pub mod constants;
+pub mod coverage;
+pub mod cpu;
+#[cfg(feature = "dap")]
+pub mod dap;
+pub mod debugger;
+pub mod disasm;
+pub mod error;
+pub mod expr;
+pub mod fontset;
+#[cfg(feature = "gdb")]
+pub mod gdb;
+pub mod instruction;
+pub mod keys;
+pub mod profile;
+pub mod quirks;
+pub mod rewind;
+pub mod screen;
+pub mod state;
+pub mod symbols;
+pub mod trace;
+mod undo;
+
+use crate::audio::{Audio, DEFAULT_PATTERN_PITCH};
+use crate::constants::{
+    AUDIO_PATTERN_SIZE, KEYPAD_SIZE, RAM_SIZE, RPL_FLAGS_SIZE, STACK_SIZE, START_ADDR, V_SIZE,
+};
+use crate::coverage::Coverage;
+use crate::debugger::{Access, Debugger, StopReason};
+use crate::disasm::Disassembly;
+use crate::error::EmulatorError;
+use crate::expr::Context;
+use crate::fontset::{BIG_FONTSET, BIG_FONTSET_SIZE, FONTSET, FONTSET_SIZE};
+pub use crate::instruction::Instruction;
+pub use crate::keys::ChipKey;
+use crate::profile::Profile;
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::screen::Screen;
+use crate::symbols::SymbolMap;
+use crate::trace::{Registers, Tracer};
+use crate::undo::{UndoHistory, UndoRecord};
+use rand::random;
+use std::collections::HashMap;
+use std::io;
+use std::ops::RangeInclusive;
+
+pub struct Emulator {
+    pub counter: u16,
+    // Address of the instruction being executed, reported in errors
+    instruction_pc: u16,
+    ram: [u8; RAM_SIZE],
+    rom: Vec<u8>,
+    screen: Screen,
+    v_reg: [u8; V_SIZE],
+    i_reg: u16,
+    stack: [u16; STACK_SIZE],
+    stack_ptr: u16,
+    keys: [bool; KEYPAD_SIZE],
+    waiting_for_key_reg: Option<u8>,
+    delay_timer: u8,
+    sound_timer: u8,
+    // XO-CHIP buzzer sample loaded by F002, the plain beep plays until then
+    audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    audio_pitch: u8,
+    waiting_for_vblank: bool,
+    // Survives `reset`, like the HP-48 user flags SUPER-CHIP stores them in
+    rpl_flags: [u8; RPL_FLAGS_SIZE],
+    exited: bool,
+
+    quirks: Quirks,
+    is_paused: bool,
+    fault: Option<EmulatorError>,
+    audio: Option<Audio>,
+    tracer: Option<Tracer>,
+    profile: Option<Profile>,
+    coverage: Option<Coverage>,
+    history: UndoHistory,
+    // Being recorded for the instruction running
+    undo: Option<UndoRecord>,
+    debugger: Debugger,
+}
+
+impl Emulator {
+    pub fn new(quirks: Quirks) -> Self {
+        let mut emu = Self {
+            counter: START_ADDR,
+            instruction_pc: START_ADDR,
+            ram: [0; RAM_SIZE],
+            rom: Vec::new(),
+            screen: Screen::new(),
+            v_reg: [0; V_SIZE],
+            i_reg: 0,
+            stack: [0; STACK_SIZE],
+            stack_ptr: 0,
+            keys: [false; KEYPAD_SIZE],
+            waiting_for_key_reg: None,
+            delay_timer: 0,
+            sound_timer: 0,
+            audio_pattern: None,
+            audio_pitch: DEFAULT_PATTERN_PITCH,
+            waiting_for_vblank: false,
+            rpl_flags: [0; RPL_FLAGS_SIZE],
+            exited: false,
+            quirks,
+            is_paused: false,
+            fault: None,
+            audio: None,
+            tracer: None,
+            profile: None,
+            coverage: None,
+            history: UndoHistory::default(),
+            undo: None,
+            debugger: Debugger::new(),
+        };
+        emu.load_fonts();
+
+        emu
+    }
+
+    pub fn reset(&mut self) {
+        self.counter = START_ADDR;
+        self.instruction_pc = START_ADDR;
+        self.ram = [0; RAM_SIZE];
+        self.screen = Screen::new();
+        self.v_reg = [0; V_SIZE];
+        self.i_reg = 0;
+        self.stack = [0; STACK_SIZE];
+        self.stack_ptr = 0;
+        self.keys = [false; KEYPAD_SIZE];
+        self.waiting_for_key_reg = None;
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.audio_pattern = None;
+        self.audio_pitch = DEFAULT_PATTERN_PITCH;
+        self.waiting_for_vblank = false;
+        self.exited = false;
+        self.fault = None;
+        self.history.clear();
+        if self.profile.is_some() {
+            self.profile = Some(Profile::new());
+        }
+        self.debugger.reset();
+
+        self.load_fonts();
+        self.load_rom()
+    }
+
+    pub fn load(&mut self, rom: &[u8]) -> Result<(), EmulatorError> {
+        let max = RAM_SIZE - START_ADDR as usize;
+        if rom.len() > max {
+            return Err(EmulatorError::RomTooLarge {
+                size: rom.len(),
+                max,
+            });
+        }
+
+        self.rom = rom.to_vec();
+        self.load_rom();
+        Ok(())
+    }
+
+    fn load_fonts(&mut self) {
+        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.ram[FONTSET_SIZE..FONTSET_SIZE + BIG_FONTSET_SIZE].copy_from_slice(&BIG_FONTSET);
+    }
+
+    fn load_rom(&mut self) {
+        let start = START_ADDR as usize;
+        self.ram[start..start + self.rom.len()].copy_from_slice(&self.rom);
+    }
+
+    pub fn get_screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// Output for the sound timer, without one the emulator stays silent.
+    pub fn set_audio(&mut self, audio: Audio) {
+        self.audio = Some(audio);
+    }
+
+    /// Completes the sound output at exit, reporting any error writing it.
+    pub fn finish_audio(&mut self) -> io::Result<()> {
+        match &mut self.audio {
+            Some(audio) => audio.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Logs the instructions executed from now on.
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    /// Completes the trace at exit, returning the first error writing it.
+    pub fn finish_trace(&mut self) -> Option<&io::Error> {
+        let tracer = self.tracer.as_mut()?;
+        tracer.finish();
+        tracer.error()
+    }
+
+    /// Counts the instructions executed from now on, `reset` starts over.
+    pub fn start_profiling(&mut self) {
+        if self.profile.is_none() {
+            self.profile = Some(Profile::new());
+        }
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Notes the instructions run and the way skips go from now on, across
+    /// resets.
+    pub fn start_coverage(&mut self) {
+        if self.coverage.is_none() {
+            self.coverage = Some(Coverage::new());
+        }
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn pause_or_resume(&mut self) {
+        self.is_paused = !self.is_paused;
+        if !self.is_paused {
+            self.debugger.resumed(self.counter);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    /// The error that froze the emulator, cleared by `reset`.
+    pub fn fault(&self) -> Option<&EmulatorError> {
+        self.fault.as_ref()
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    /// Runs the next instruction unless paused, a breakpoint pauses before it.
+    pub fn tick(&mut self) -> Result<(), StopReason> {
+        if self.is_paused || self.fault.is_some() {
+            return Ok(());
+        }
+        if self.debugger.is_active() && !self.is_blocked() {
+            let instruction = self.fetch(self.counter as usize);
+            let context = Context {
+                pc: self.counter,
+                i: self.i_reg,
+                v: &self.v_reg,
+                delay_timer: self.delay_timer,
+                sound_timer: self.sound_timer,
+                stack_ptr: self.stack_ptr,
+                ram: &self.ram,
+                hits: 0,
+            };
+            if let Some(reason) = self.debugger.check(self.counter, &instruction, &context) {
+                self.is_paused = true;
+                return Err(reason);
+            }
+        }
+        self.step()
+    }
+
+    /// Runs one 60Hz frame, `ticks` instructions followed by the timers.
+    ///
+    /// The timers still run when an instruction stops the frame early, the
+    /// reason is returned afterwards. They keep running while paused, unless
+    /// there is undo history: then they hold, so stepping back and forth sees
+    /// the same values, and the buzzer is silent.
+    pub fn run_frame(&mut self, ticks: usize) -> Result<(), StopReason> {
+        if self.is_paused && !self.history.is_empty() {
+            if let Some(audio) = &mut self.audio {
+                audio.render_frame(false, None, self.audio_pitch);
+            }
+            return Ok(());
+        }
+        let result = (0..ticks).try_for_each(|_| self.tick());
+        self.tick_timers();
+        result
+    }
+
+    /// Runs the next instruction even when paused, breakpoints are ignored.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<(), EmulatorError> {
+        match self.step() {
+            Err(StopReason::Fault(fault)) => Err(fault),
+            _ => Ok(()),
+        }
+    }
+
+    // Runs one instruction, a watchpoint it hits pauses the emulator afterwards
+    fn step(&mut self) -> Result<(), StopReason> {
+        if let Some(fault) = &self.fault {
+            return Err(fault.clone().into());
+        }
+        if self.is_blocked() {
+            return Ok(());
+        }
+        self.instruction_pc = self.counter;
+        let instruction = self.fetch(self.counter as usize);
+        self.counter = self.counter.wrapping_add(instruction.size());
+        self.debugger.executed(self.instruction_pc);
+        for offset in 0..instruction.size() as usize {
+            let address = (self.instruction_pc as usize + offset) % RAM_SIZE;
+            let byte = self.ram[address];
+            self.debugger
+                .memory_access(self.instruction_pc, address, Access::Fetch, byte, byte);
+        }
+
+        let before = self.tracer.is_some().then(|| self.registers());
+        self.begin_undo(&instruction);
+        let result = self.execute(instruction);
+        self.end_undo(result.is_ok());
+        if let Some(before) = before {
+            let after = self.registers();
+            if let Some(tracer) = &mut self.tracer {
+                tracer.record(self.instruction_pc, &instruction, &before, &after);
+                if result.is_err() {
+                    tracer.dump();
+                }
+            }
+        }
+
+        if result.is_ok() {
+            if let Some(profile) = &mut self.profile {
+                profile.record(self.instruction_pc, &instruction);
+            }
+            if let Some(coverage) = &mut self.coverage {
+                coverage.record(self.instruction_pc, &instruction, self.counter);
+            }
+        }
+
+        if let Err(fault) = result {
+            // Stay on the faulting instruction so it shows up in the instruction list
+            self.counter = self.instruction_pc;
+            self.fault = Some(fault.clone());
+            return Err(fault.into());
+        }
+        if let Some(reason) = self.debugger.take_watch_hit() {
+            self.is_paused = true;
+            return Err(reason);
+        }
+        Ok(())
+    }
+
+    fn registers(&self) -> Registers {
+        Registers {
+            v: self.v_reg,
+            i: self.i_reg,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            stack_ptr: self.stack_ptr,
+        }
+    }
+
+    // Nothing runs until the rom exits, a key is released or the next frame starts
+    fn is_blocked(&self) -> bool {
+        self.exited || self.waiting_for_key_reg.is_some() || self.waiting_for_vblank
+    }
+
+    pub fn tick_timers(&mut self) {
+        if self.fault.is_some() {
+            return;
+        }
+        if let Some(audio) = &mut self.audio {
+            audio.render_frame(
+                self.sound_timer > 0,
+                self.audio_pattern.as_ref(),
+                self.audio_pitch,
+            );
+        }
+        self.decrement_timers();
+    }
+
+    // The state a timer tick changes, without the sound it plays
+    fn decrement_timers(&mut self) {
+        self.waiting_for_vblank = false;
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+        self.history.ticked();
+    }
+
+    pub fn key_pressed(&mut self, key: ChipKey) {
+        self.keys[key.to_hex() as usize] = true;
+    }
+
+    pub fn key_released(&mut self, key: ChipKey) {
+        let idx = key.to_hex();
+        self.keys[idx as usize] = false;
+        if let Some(x) = self.waiting_for_key_reg {
+            self.v_reg[x as usize] = idx;
+            self.waiting_for_key_reg = None;
+        }
+    }
+
+    pub fn key_states(&self) -> HashMap<ChipKey, bool> {
+        (0..16)
+            .map(|i| {
+                let key = ChipKey::from_hex(i as u8).unwrap();
+                (key, self.keys[i])
+            })
+            .collect()
+    }
+
+    /// The byte at `address`, without triggering watchpoints.
+    pub fn peek(&self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+
+    /// Overwrites the byte at `address`, without triggering watchpoints.
+    ///
+    /// `reset` restores the rom over any changes.
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.ram[address as usize] = value;
+        self.history.clear();
+    }
+
+    /// Disassembles the rom as it is now in memory, following code from the
+    /// start and from the program counter.
+    pub fn disassemble(&self, symbols: &SymbolMap) -> Disassembly {
+        let start = START_ADDR.min(self.counter);
+        let end = (START_ADDR as usize + self.rom.len())
+            .max(self.counter as usize + 4)
+            .min(RAM_SIZE - 1);
+        Disassembly::new(
+            &self.ram,
+            start..end as u16,
+            &[START_ADDR, self.counter],
+            symbols,
+        )
+    }
+
+    pub fn fetch(&self, at: usize) -> Instruction {
+        let word = |at: usize| {
+            let higher = self.ram[at % RAM_SIZE] as u16;
+            let lower = self.ram[(at + 1) % RAM_SIZE] as u16;
+            (higher << 8) | lower
+        };
+
+        Instruction::decode(word(at), word(at + 2))
+    }
+
+    fn execute(&mut self, instruction: Instruction) -> Result<(), EmulatorError> {
+        match instruction {
+            Instruction::Nop => (),
+            Instruction::ClearScreen => self.screen.clear(),
+            Instruction::Ret => self.counter = self.pop()?,
+            Instruction::Exit => self.exited = true,
+            Instruction::ScrollDown { n } => self.screen.scroll_down(n as usize),
+            Instruction::ScrollUp { n } => self.screen.scroll_up(n as usize),
+            Instruction::ScrollRight => self.screen.scroll_right(4),
+            Instruction::ScrollLeft => self.screen.scroll_left(4),
+            Instruction::LowRes => self.screen.set_hires(false),
+            Instruction::HighRes => self.screen.set_hires(true),
+            Instruction::SelectPlanes { n } => self.screen.select_planes(n),
+            Instruction::Jump { nnn } => self.counter = nnn,
+            Instruction::JumpPlusV0 { nnn } => {
+                let x = if self.quirks.jump_uses_vx {
+                    (nnn >> 8) as usize
+                } else {
+                    0
+                };
+                self.counter = nnn + self.v_reg[x] as u16
+            }
+            Instruction::Call { nnn } => {
+                self.push(self.counter)?;
+                self.counter = nnn
+            }
+            Instruction::SkipVxEqNN { x, nn } => {
+                if self.v_reg[x] == nn {
+                    self.skip_next();
+                }
+            }
+            Instruction::SkipVxNeqNN { x, nn } => {
+                if self.v_reg[x] != nn {
+                    self.skip_next();
+                }
+            }
+            Instruction::SkipVxEqVy { x, y } => {
+                if self.v_reg[x] == self.v_reg[y] {
+                    self.skip_next();
+                }
+            }
+            Instruction::SkipVxNeqVy { x, y } => {
+                if self.v_reg[x] != self.v_reg[y] {
+                    self.skip_next();
+                }
+            }
+            Instruction::SetVxNN { x, nn } => {
+                self.v_reg[x] = nn;
+            }
+            Instruction::SetVxVy { x, y } => {
+                self.v_reg[x] = self.v_reg[y];
+            }
+            Instruction::SetVxDt { x } => {
+                self.v_reg[x] = self.delay_timer;
+            }
+            Instruction::SetVxKey { x } => {
+                self.waiting_for_key_reg = Some(x as u8);
+            }
+            Instruction::SetVxRnd { x, nn } => {
+                // Stepping back and forth draws the same value again
+                let rnd = match &mut self.undo {
+                    Some(record) => *record.redo.random.get_or_insert_with(random),
+                    None => random(),
+                };
+                self.v_reg[x] = rnd & nn;
+            }
+            Instruction::SetI { nnn } => {
+                self.i_reg = nnn;
+            }
+            Instruction::LongSetI { nnnn } => {
+                self.i_reg = nnnn;
+            }
+            Instruction::SetVxFontToI { x } => self.i_reg = (self.v_reg[x] & 0xF) as u16 * 5,
+            Instruction::SetVxBigFontToI { x } => {
+                self.i_reg = FONTSET_SIZE as u16 + (self.v_reg[x] & 0xF) as u16 * 10
+            }
+            Instruction::SetVxBcdToI { x } => {
+                let vx = self.v_reg[x];
+                let i = self.i_reg as usize;
+                self.write_ram(i, vx / 100)?;
+                self.write_ram(i + 1, (vx / 10) % 10)?;
+                self.write_ram(i + 2, vx % 10)?;
+            }
+            Instruction::SetDtVx { x } => {
+                self.delay_timer = self.v_reg[x];
+            }
+            Instruction::SetStVx { x } => {
+                self.sound_timer = self.v_reg[x];
+            }
+            Instruction::LoadAudioPattern => {
+                let mut pattern = [0; AUDIO_PATTERN_SIZE];
+                for (idx, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.read_ram(self.i_reg as usize + idx)?;
+                }
+                self.audio_pattern = Some(pattern);
+            }
+            Instruction::SetPitchVx { x } => {
+                self.audio_pitch = self.v_reg[x];
+            }
+            Instruction::AddVxNN { x, nn } => {
+                self.v_reg[x] = self.v_reg[x].wrapping_add(nn);
+            }
+            Instruction::AddVxVy { x, y } => {
+                let (vx, carry) = self.v_reg[x].overflowing_add(self.v_reg[y]);
+                self.v_reg[x] = vx;
+                self.v_reg[0xF] = if carry { 1 } else { 0 };
+            }
+            Instruction::SubVxVy { x, y } => {
+                let (vx, borrow) = self.v_reg[x].overflowing_sub(self.v_reg[y]);
+                self.v_reg[x] = vx;
+                self.v_reg[0xF] = if borrow { 0 } else { 1 };
+            }
+            Instruction::SubVyVx { x, y } => {
+                let (vx, borrow) = self.v_reg[y].overflowing_sub(self.v_reg[x]);
+                self.v_reg[x] = vx;
+                self.v_reg[0xF] = if borrow { 0 } else { 1 };
+            }
+            Instruction::AddVxToI { x } => {
+                self.i_reg = self.i_reg.wrapping_add(self.v_reg[x] as u16)
+            }
+            Instruction::OrVxVy { x, y } => {
+                self.v_reg[x] |= self.v_reg[y];
+                if self.quirks.logic_resets_vf {
+                    self.v_reg[0xF] = 0;
+                }
+            }
+            Instruction::AndVxVy { x, y } => {
+                self.v_reg[x] &= self.v_reg[y];
+                if self.quirks.logic_resets_vf {
+                    self.v_reg[0xF] = 0;
+                }
+            }
+            Instruction::XorVxVy { x, y } => {
+                self.v_reg[x] ^= self.v_reg[y];
+                if self.quirks.logic_resets_vf {
+                    self.v_reg[0xF] = 0;
+                }
+            }
+            Instruction::RShiftVx { x, y } => {
+                let vx = self.shift_source(x, y);
+                self.v_reg[x] = vx >> 1;
+                self.v_reg[0xF] = vx & 1;
+            }
+            Instruction::LShiftVx { x, y } => {
+                let vx = self.shift_source(x, y);
+                self.v_reg[x] = vx << 1;
+                self.v_reg[0xF] = (vx >> 7) & 1;
+            }
+            Instruction::SkipVxDown { x } => {
+                let vx = self.v_reg[x];
+                let key = self.keys[(vx & 0xF) as usize];
+                if key {
+                    self.skip_next();
+                }
+            }
+            Instruction::SkipVxUp { x } => {
+                let vx = self.v_reg[x];
+                let key = self.keys[(vx & 0xF) as usize];
+                if !key {
+                    self.skip_next();
+                }
+            }
+            Instruction::Draw { x, y, n } => {
+                let position = (self.v_reg[x] as usize, self.v_reg[y] as usize);
+                // DXY0 draws a 16x16 sprite made of two bytes per row
+                let (width, height) = if n == 0 { (16, 16) } else { (8, n as usize) };
+                let bytes_per_row = width / 8;
+
+                // Each selected plane reads its own sprite right after the previous one
+                let mut i = self.i_reg as usize;
+                let mut any_flipped = false;
+                for plane in [0b01, 0b10] {
+                    if self.screen.selected_planes() & plane == 0 {
+                        continue;
+                    }
+
+                    let mut rows = Vec::with_capacity(height);
+                    for row in 0..height {
+                        let at = i + row * bytes_per_row;
+                        rows.push(if bytes_per_row == 2 {
+                            u16::from_be_bytes([self.read_ram(at)?, self.read_ram(at + 1)?])
+                        } else {
+                            self.read_ram(at)? as u16
+                        });
+                    }
+                    any_flipped |= self.screen.draw_sprite(
+                        position,
+                        plane,
+                        &rows,
+                        width,
+                        self.quirks.clip_sprites,
+                    );
+                    i += height * bytes_per_row;
+                }
+
+                self.v_reg[0xF] = if any_flipped { 1 } else { 0 };
+                self.waiting_for_vblank = self.quirks.display_wait;
+            }
+            Instruction::SaveVx { x } => {
+                for idx in 0..=x {
+                    self.write_ram(self.i_reg as usize + idx, self.v_reg[idx])?;
+                }
+                self.increment_i_after_load_store(x);
+            }
+            Instruction::LoadVx { x } => {
+                for idx in 0..=x {
+                    self.v_reg[idx] = self.read_ram(self.i_reg as usize + idx)?;
+                }
+                self.increment_i_after_load_store(x);
+            }
+            Instruction::SaveVxVy { x, y } => {
+                for idx in register_range(x, y) {
+                    let address = self.i_reg as usize + idx.abs_diff(x);
+                    self.write_ram(address, self.v_reg[idx])?;
+                }
+            }
+            Instruction::LoadVxVy { x, y } => {
+                for idx in register_range(x, y) {
+                    let address = self.i_reg as usize + idx.abs_diff(x);
+                    self.v_reg[idx] = self.read_ram(address)?;
+                }
+            }
+            Instruction::SaveFlags { x } => {
+                self.rpl_flags[..=x].copy_from_slice(&self.v_reg[..=x]);
+            }
+            Instruction::LoadFlags { x } => {
+                self.v_reg[..=x].copy_from_slice(&self.rpl_flags[..=x]);
+            }
+            Instruction::Unknown { opcode } => {
+                return Err(EmulatorError::UnknownOpcode {
+                    pc: self.instruction_pc,
+                    opcode,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Steps over the next instruction, which may be the four byte `F000 NNNN`.
+    fn skip_next(&mut self) {
+        let next = self.fetch(self.counter as usize);
+        self.counter = self.counter.wrapping_add(next.size());
+    }
+
+    fn shift_source(&self, x: usize, y: usize) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.v_reg[y]
+        } else {
+            self.v_reg[x]
+        }
+    }
+
+    fn increment_i_after_load_store(&mut self, x: usize) {
+        let increment = match self.quirks.load_store_increment {
+            IndexIncrement::None => 0,
+            IndexIncrement::X => x as u16,
+            IndexIncrement::XPlusOne => x as u16 + 1,
+        };
+        self.i_reg = self.i_reg.wrapping_add(increment);
+    }
+
+    fn read_ram(&mut self, address: usize) -> Result<u8, EmulatorError> {
+        let value = *self
+            .ram
+            .get(address)
+            .ok_or(EmulatorError::MemoryOutOfBounds {
+                pc: self.instruction_pc,
+                address,
+            })?;
+        self.debugger
+            .memory_access(self.instruction_pc, address, Access::Read, value, value);
+        Ok(value)
+    }
+
+    fn write_ram(&mut self, address: usize, value: u8) -> Result<(), EmulatorError> {
+        match self.ram.get_mut(address) {
+            Some(byte) => {
+                let old = *byte;
+                *byte = value;
+                self.record_write(address, old);
+                self.debugger.memory_access(
+                    self.instruction_pc,
+                    address,
+                    Access::Write,
+                    old,
+                    value,
+                );
+                Ok(())
+            }
+            None => Err(EmulatorError::MemoryOutOfBounds {
+                pc: self.instruction_pc,
+                address,
+            }),
+        }
+    }
+
+    fn push(&mut self, v: u16) -> Result<(), EmulatorError> {
+        if self.stack_ptr as usize >= STACK_SIZE {
+            return Err(EmulatorError::StackOverflow {
+                pc: self.instruction_pc,
+            });
+        }
+        self.stack[self.stack_ptr as usize] = v;
+        self.stack_ptr += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<u16, EmulatorError> {
+        if self.stack_ptr == 0 {
+            return Err(EmulatorError::StackUnderflow {
+                pc: self.instruction_pc,
+            });
+        }
+        self.stack_ptr -= 1;
+        Ok(self.stack[self.stack_ptr as usize])
+    }
+}
+
+/// The registers 5XY2/5XY3 touch, lowest first whichever of X and Y it is.
+/// In memory VX is at I and each register is as far from it as from VX, so
+/// they are stored backwards when X > Y.
+fn register_range(x: usize, y: usize) -> RangeInclusive<usize> {
+    x.min(y)..=x.max(y)
+}
@@ -0,0 +1,200 @@
+//! Where a rom spends its cycles, one cycle per instruction executed.
+//!
+//! Subroutines are found from `Call` and `Ret`: a subroutine's inclusive
+//! cycles are the ones spent until it returns, its exclusive cycles leave out
+//! the subroutines it calls. The code the rom starts at counts as a
+//! subroutine of its own, entered once.
+
+use crate::constants::{RAM_SIZE, START_ADDR};
+use crate::instruction::Instruction;
+use crate::symbols::SymbolMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+// Addresses listed by `report`
+const HOTTEST_ADDRESSES: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Subroutine {
+    pub calls: u64,
+    pub inclusive: u64,
+    pub exclusive: u64,
+}
+
+#[derive(Clone)]
+pub struct Profile {
+    counts: Vec<u64>,
+    total: u64,
+    subroutines: BTreeMap<u16, Subroutine>,
+    // Entry address and total cycles when it was entered, outermost first
+    frames: Vec<(u16, u64)>,
+    // Cycles spent in each call stack, keyed by entry addresses outermost first
+    stacks: HashMap<Vec<u16>, u64>,
+    // Cycles in the current stack not added to `stacks` yet
+    pending: u64,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        let mut subroutines = BTreeMap::new();
+        subroutines.insert(
+            START_ADDR,
+            Subroutine {
+                calls: 1,
+                ..Subroutine::default()
+            },
+        );
+        Self {
+            counts: vec![0; RAM_SIZE],
+            total: 0,
+            subroutines,
+            frames: vec![(START_ADDR, 0)],
+            stacks: HashMap::new(),
+            pending: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, pc: u16, instruction: &Instruction) {
+        self.counts[pc as usize] += 1;
+        self.total += 1;
+        self.pending += 1;
+
+        match instruction {
+            Instruction::Call { nnn } => {
+                self.flush();
+                self.frames.push((*nnn, self.total));
+                self.subroutines.entry(*nnn).or_default().calls += 1;
+            }
+            // Returning from the code the rom started at underflows the stack
+            Instruction::Ret if self.frames.len() > 1 => {
+                self.flush();
+                let (entry, start) = self.frames.pop().unwrap();
+                self.end_frame(entry, start);
+            }
+            _ => {}
+        }
+    }
+
+    // Hands the pending cycles to the subroutine running and its call stack
+    fn flush(&mut self) {
+        if self.pending == 0 {
+            return;
+        }
+        let (entry, _) = *self.frames.last().unwrap();
+        self.subroutines.entry(entry).or_default().exclusive += self.pending;
+        let stack = self.frames.iter().map(|(entry, _)| *entry).collect();
+        *self.stacks.entry(stack).or_default() += self.pending;
+        self.pending = 0;
+    }
+
+    // A recursive subroutine's cycles only count once, for its outermost call
+    fn end_frame(&mut self, entry: u16, start: u64) {
+        if !self.frames.iter().any(|(outer, _)| *outer == entry) {
+            self.subroutines.entry(entry).or_default().inclusive += self.total - start;
+        }
+    }
+
+    // The profile as if every subroutine running returned now
+    fn finished(&self) -> Self {
+        let mut profile = self.clone();
+        profile.flush();
+        while let Some((entry, start)) = profile.frames.pop() {
+            profile.end_frame(entry, start);
+        }
+        profile
+    }
+
+    /// Instructions executed at `address`.
+    pub fn count(&self, address: u16) -> u64 {
+        self.counts[address as usize]
+    }
+
+    /// The highest count of any address.
+    pub fn max_count(&self) -> u64 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Instructions executed in all.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Cycles of every subroutine called so far, by entry address.
+    pub fn subroutines(&self) -> BTreeMap<u16, Subroutine> {
+        self.finished().subroutines
+    }
+
+    /// The subroutines by inclusive cycles and the hottest addresses, as a table.
+    pub fn report(&self, symbols: &SymbolMap) -> String {
+        let percent = |cycles: u64| 100.0 * cycles as f64 / self.total.max(1) as f64;
+        let mut subroutines = self.subroutines().into_iter().collect::<Vec<_>>();
+        subroutines.sort_by_key(|(address, stats)| (Reverse(stats.inclusive), *address));
+
+        let mut out = format!("cycles {}\n\n", self.total);
+        let _ = writeln!(
+            out,
+            "{:<24} {:>8} {:>12} {:>7} {:>12} {:>7}",
+            "subroutine", "calls", "inclusive", "%", "exclusive", "%"
+        );
+        for (address, stats) in subroutines {
+            let _ = writeln!(
+                out,
+                "{:<24} {:>8} {:>12} {:>6.1}% {:>12} {:>6.1}%",
+                symbols.describe(address),
+                stats.calls,
+                stats.inclusive,
+                percent(stats.inclusive),
+                stats.exclusive,
+                percent(stats.exclusive)
+            );
+        }
+
+        let mut addresses = (0..RAM_SIZE)
+            .filter(|&address| self.counts[address] > 0)
+            .map(|address| (address as u16, self.counts[address]))
+            .collect::<Vec<_>>();
+        addresses.sort_by_key(|(address, count)| (Reverse(*count), *address));
+        let _ = writeln!(
+            out,
+            "\n{:<8} {:<24} {:>12} {:>7}",
+            "address", "location", "count", "%"
+        );
+        for (address, count) in addresses.into_iter().take(HOTTEST_ADDRESSES) {
+            let _ = writeln!(
+                out,
+                "{:04X}     {:<24} {:>12} {:>6.1}%",
+                address,
+                symbols.describe(address),
+                count,
+                percent(count)
+            );
+        }
+        out
+    }
+
+    /// Folded stacks, one `outer;inner cycles` line per call stack, as read
+    /// by flamegraph tools.
+    pub fn folded(&self, symbols: &SymbolMap) -> String {
+        let profile = self.finished();
+        let mut lines = profile
+            .stacks
+            .iter()
+            .map(|(stack, cycles)| {
+                let names = stack
+                    .iter()
+                    .map(|&entry| symbols.describe(entry))
+                    .collect::<Vec<_>>();
+                format!("{} {}", names.join(";"), cycles)
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
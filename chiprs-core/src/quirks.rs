@@ -0,0 +1,125 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Amount added to I by FX55/FX65.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    /// I is left untouched (SUPER-CHIP).
+    None,
+    /// I += X (CHIP-48).
+    X,
+    /// I += X + 1 (COSMAC VIP, XO-CHIP).
+    XPlusOne,
+}
+
+/// Behaviour switches for the opcodes that CHIP-8 platforms disagree on.
+///
+/// Use one of the presets (`Quirks::CHIP8`, `Quirks::CHIP48`, `Quirks::SUPER_CHIP`,
+/// `Quirks::XO_CHIP`) unless a ROM needs a specific combination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6/8XYE copy VY into VX before shifting (COSMAC VIP).
+    /// When off, VX is shifted in place and VY is ignored (CHIP-48 and later).
+    pub shift_uses_vy: bool,
+    /// How far FX55/FX65 advance I after touching V0..VX.
+    pub load_store_increment: IndexIncrement,
+    /// 8XY1/8XY2/8XY3 reset VF to 0.
+    pub logic_resets_vf: bool,
+    /// Sprites are cut off at the screen edges instead of wrapping around.
+    pub clip_sprites: bool,
+    /// BNNN is read as BXNN and jumps to XNN + VX instead of NNN + V0.
+    pub jump_uses_vx: bool,
+    /// DXYN waits for the next frame before the interpreter continues.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub const CHIP8: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increment: IndexIncrement::XPlusOne,
+        logic_resets_vf: true,
+        clip_sprites: true,
+        jump_uses_vx: false,
+        display_wait: true,
+    };
+
+    /// CHIP-48 on the HP-48 calculators.
+    ///
+    /// Its FX55/FX65 leave I pointing at VX's byte rather than past it, as
+    /// listed in the CHIP-48 column of Gulrak's opcode table
+    /// (chip8.gulrak.net). `roms/5-quirks.ch8` has no CHIP-48 entry, run as
+    /// SUPER-CHIP it reports that difference as a failed memory test.
+    pub const CHIP48: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increment: IndexIncrement::X,
+        logic_resets_vf: false,
+        clip_sprites: true,
+        jump_uses_vx: true,
+        display_wait: false,
+    };
+
+    /// SUPER-CHIP 1.1, as implemented by modern interpreters.
+    pub const SUPER_CHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increment: IndexIncrement::None,
+        logic_resets_vf: false,
+        clip_sprites: true,
+        jump_uses_vx: true,
+        display_wait: false,
+    };
+
+    /// XO-CHIP, as defined by Octo.
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increment: IndexIncrement::XPlusOne,
+        logic_resets_vf: false,
+        clip_sprites: false,
+        jump_uses_vx: false,
+        display_wait: false,
+    };
+
+    pub const PRESETS: [(&'static str, Quirks); 4] = [
+        ("chip8", Quirks::CHIP8),
+        ("chip48", Quirks::CHIP48),
+        ("schip", Quirks::SUPER_CHIP),
+        ("xochip", Quirks::XO_CHIP),
+    ];
+
+    pub fn preset_name(&self) -> Option<&'static str> {
+        Self::PRESETS
+            .iter()
+            .find(|(_, quirks)| quirks == self)
+            .map(|(name, _)| *name)
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::CHIP8
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::PRESETS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, quirks)| *quirks)
+            .ok_or_else(|| {
+                let names = Self::PRESETS.map(|(name, _)| name).join(", ");
+                format!("unknown platform '{}', expected one of: {}", s, names)
+            })
+    }
+}
+
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.preset_name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "custom"),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::state::StateError;
+use crate::Emulator;
+use std::collections::VecDeque;
+
+/// Rewind history made of save states taken every `interval` frames.
+///
+/// Only the newest snapshot is kept whole, older ones are stored as
+/// run-length encoded XOR deltas against the snapshot that followed them,
+/// so memory use depends on how much the machine changes between snapshots.
+pub struct RewindBuffer {
+    interval: usize,
+    max_bytes: usize,
+    frames: usize,
+    latest: Option<Vec<u8>>,
+    // deltas.back() turns `latest` into the snapshot taken before it
+    deltas: VecDeque<Vec<u8>>,
+    delta_bytes: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(interval: usize, max_bytes: usize) -> Self {
+        Self {
+            interval: interval.max(1),
+            max_bytes,
+            frames: 0,
+            latest: None,
+            deltas: VecDeque::new(),
+            delta_bytes: 0,
+        }
+    }
+
+    /// Memory held by the history, in bytes.
+    pub fn size(&self) -> usize {
+        self.delta_bytes + self.latest.as_ref().map_or(0, Vec::len)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames = 0;
+        self.latest = None;
+        self.deltas.clear();
+        self.delta_bytes = 0;
+    }
+
+    /// Call once per emulated frame, takes a snapshot every `interval` frames.
+    pub fn record(&mut self, emu: &Emulator) {
+        self.frames += 1;
+        if self.frames < self.interval {
+            return;
+        }
+        self.frames = 0;
+
+        let snapshot = emu.save_state();
+        match self.latest.replace(snapshot) {
+            Some(previous) => {
+                let latest = self.latest.as_ref().unwrap();
+                if previous == *latest {
+                    return;
+                }
+                let delta = encode_delta(latest, &previous);
+                self.delta_bytes += delta.len();
+                self.deltas.push_back(delta);
+            }
+            None => return,
+        }
+
+        while self.size() > self.max_bytes {
+            match self.deltas.pop_front() {
+                Some(oldest) => self.delta_bytes -= oldest.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Call once per frame while rewinding, steps back one snapshot every
+    /// `interval` frames so history plays backwards in real time.
+    ///
+    /// Returns false once the oldest snapshot has been reached.
+    pub fn rewind(&mut self, emu: &mut Emulator) -> Result<bool, StateError> {
+        self.frames += 1;
+        if self.frames < self.interval {
+            return Ok(!self.deltas.is_empty());
+        }
+        self.frames = 0;
+
+        self.step_back(emu)
+    }
+
+    /// Restores the snapshot taken before the newest one.
+    ///
+    /// Fails when a different rom was loaded since recording, the history is
+    /// dropped then.
+    pub fn step_back(&mut self, emu: &mut Emulator) -> Result<bool, StateError> {
+        let (Some(latest), Some(delta)) = (self.latest.as_ref(), self.deltas.pop_back()) else {
+            return Ok(false);
+        };
+        self.delta_bytes -= delta.len();
+
+        let previous = decode_delta(latest, &delta);
+        if let Err(e) = emu.load_state(&previous) {
+            self.clear();
+            return Err(e);
+        }
+        self.latest = Some(previous);
+        Ok(true)
+    }
+}
+
+// Delta layout: target length u32, then pairs of (zero run, literal length)
+// varints, each followed by that many literal XOR bytes.
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let len = from.len().max(to.len());
+    let xor = |idx: usize| from.get(idx).unwrap_or(&0) ^ to.get(idx).unwrap_or(&0);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&(to.len() as u32).to_le_bytes());
+
+    let mut idx = 0;
+    while idx < len {
+        let run_start = idx;
+        while idx < len && xor(idx) == 0 {
+            idx += 1;
+        }
+        let literal_start = idx;
+        while idx < len && xor(idx) != 0 {
+            idx += 1;
+        }
+
+        write_varint(&mut out, literal_start - run_start);
+        write_varint(&mut out, idx - literal_start);
+        out.extend((literal_start..idx).map(xor));
+    }
+
+    out
+}
+
+fn decode_delta(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    let len = u32::from_le_bytes(delta[..4].try_into().unwrap()) as usize;
+    let mut out = from.to_vec();
+    out.resize(len.max(from.len()), 0);
+
+    let mut pos = 4;
+    let mut idx = 0;
+    while pos < delta.len() {
+        idx += read_varint(delta, &mut pos);
+        let literals = read_varint(delta, &mut pos);
+        for byte in &delta[pos..pos + literals] {
+            out[idx] ^= byte;
+            idx += 1;
+        }
+        pos += literals;
+    }
+
+    out.truncate(len);
+    out
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
@@ -0,0 +1,185 @@
+use crate::constants::{
+    EMU_SCREEN_HEIGHT, EMU_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH,
+};
+
+// Characters `to_ascii` uses for each combination of plane bits
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@'];
+
+/// Framebuffer that follows the active SUPER-CHIP resolution,
+/// 64x32 in low-res mode and 128x64 in high-res mode.
+///
+/// Every pixel holds one bit per XO-CHIP bitplane, drawing, clearing and
+/// scrolling only touch the planes picked with `select_planes`.
+#[derive(Clone)]
+pub struct Screen {
+    hires: bool,
+    planes: u8,
+    pixels: Vec<u8>,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self {
+            hires: false,
+            planes: 0b01,
+            pixels: vec![0; EMU_SCREEN_WIDTH * EMU_SCREEN_HEIGHT],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_WIDTH
+        } else {
+            EMU_SCREEN_WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_HEIGHT
+        } else {
+            EMU_SCREEN_HEIGHT
+        }
+    }
+
+    /// Switches resolution, which also clears the screen.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.pixels = vec![0; self.width() * self.height()];
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.planes
+    }
+
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & 0b11;
+    }
+
+    /// Plane bits of every pixel, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Overwrites every pixel, `pixels` must match the current resolution.
+    pub fn restore_pixels(&mut self, pixels: &[u8]) {
+        self.pixels.copy_from_slice(pixels);
+    }
+
+    /// XORs plane bits into pixels, given as (index, bits) pairs.
+    pub(crate) fn flip_pixels(&mut self, flips: &[(usize, u8)]) {
+        for &(idx, bits) in flips {
+            self.pixels[idx] ^= bits;
+        }
+    }
+
+    /// Renders the pixels as text, one newline terminated line per row.
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::with_capacity((self.width() + 1) * self.height());
+        for row in self.pixels.chunks(self.width()) {
+            out.extend(row.iter().map(|&pixel| ASCII_PIXELS[pixel as usize & 0b11]));
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn clear(&mut self) {
+        let planes = self.planes;
+        self.pixels.iter_mut().for_each(|pixel| *pixel &= !planes);
+    }
+
+    /// XORs a sprite onto one plane and reports whether any lit pixel was turned off.
+    ///
+    /// Each row is `width` pixels wide (8 or 16) and is read MSB first from `rows`.
+    pub fn draw_sprite(
+        &mut self,
+        (x, y): (usize, usize),
+        plane: u8,
+        rows: &[u16],
+        width: usize,
+        clip: bool,
+    ) -> bool {
+        let (screen_width, screen_height) = (self.width(), self.height());
+        let x_cord = x % screen_width;
+        let y_cord = y % screen_height;
+
+        let mut any_flipped = false;
+        for (y_line, row) in rows.iter().enumerate() {
+            let mut y = y_cord + y_line;
+            if y >= screen_height {
+                if clip {
+                    break;
+                }
+                y %= screen_height;
+            }
+
+            for x_line in 0..width {
+                // Only flip if current pixel's bit is 1
+                if row & (1 << (width - 1 - x_line)) == 0 {
+                    continue;
+                }
+
+                let mut x = x_cord + x_line;
+                if x >= screen_width {
+                    if clip {
+                        continue;
+                    }
+                    x %= screen_width;
+                }
+                let idx = x + screen_width * y;
+
+                any_flipped |= self.pixels[idx] & plane != 0;
+                self.pixels[idx] ^= plane;
+            }
+        }
+
+        any_flipped
+    }
+
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll(0, n as isize)
+    }
+
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll(0, -(n as isize))
+    }
+
+    pub fn scroll_right(&mut self, n: usize) {
+        self.scroll(n as isize, 0)
+    }
+
+    pub fn scroll_left(&mut self, n: usize) {
+        self.scroll(-(n as isize), 0)
+    }
+
+    /// Moves the selected planes by (dx, dy), pixels scrolled in from the edges are off.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let planes = self.planes;
+        let source = self.pixels.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (from_x, from_y) = (x - dx, y - dy);
+                let scrolled = if (0..width).contains(&from_x) && (0..height).contains(&from_y) {
+                    source[(from_x + from_y * width) as usize] & planes
+                } else {
+                    0
+                };
+
+                let idx = (x + y * width) as usize;
+                self.pixels[idx] = (source[idx] & !planes) | scrolled;
+            }
+        }
+    }
+}
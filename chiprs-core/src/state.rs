@@ -0,0 +1,209 @@
+use crate::constants::{
+    AUDIO_PATTERN_SIZE, KEYPAD_SIZE, RAM_SIZE, RPL_FLAGS_SIZE, STACK_SIZE, V_SIZE,
+};
+use crate::screen::Screen;
+use crate::Emulator;
+use std::error::Error;
+use std::fmt;
+
+// Layout of a save state, all numbers are little endian:
+//
+//   magic "CH8S", version u16, rom hash u64,
+//   pc u16, ram [u8; RAM_SIZE], V0..VF, I u16, stack [u16; STACK_SIZE], stack pointer u8,
+//   keys u16 bitmask, waiting for key register u8 (0xFF when not waiting),
+//   delay timer u8, sound timer u8, audio pattern loaded u8, audio pattern [u8; 16],
+//   audio pitch u8, waiting for vblank u8, rpl flags, exited u8,
+//   hires u8, selected planes u8, pixels [u8; width * height]
+const MAGIC: &[u8; 4] = b"CH8S";
+const VERSION: u16 = 2;
+const NOT_WAITING: u8 = 0xFF;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    BadMagic,
+    UnsupportedVersion { version: u16 },
+    RomMismatch { expected: u64, found: u64 },
+    Truncated,
+    Corrupted,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::BadMagic => write!(f, "not a chiprs save state"),
+            Self::UnsupportedVersion { version } => write!(
+                f,
+                "save state version {} is not supported (expected {})",
+                version, VERSION
+            ),
+            Self::RomMismatch { expected, found } => write!(
+                f,
+                "save state was made for a different rom (rom hash {:016X}, state has {:016X})",
+                expected, found
+            ),
+            Self::Truncated => write!(f, "save state is truncated"),
+            Self::Corrupted => write!(f, "save state is corrupted"),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+/// FNV-1a hash identifying the rom a save state belongs to.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Emulator {
+    /// Snapshots the whole machine, the pause and fault state are not part of it.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(RAM_SIZE + 0x2000);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&rom_hash(&self.rom).to_le_bytes());
+
+        out.extend_from_slice(&self.counter.to_le_bytes());
+        out.extend_from_slice(&self.ram);
+        out.extend_from_slice(&self.v_reg);
+        out.extend_from_slice(&self.i_reg.to_le_bytes());
+        for entry in self.stack {
+            out.extend_from_slice(&entry.to_le_bytes());
+        }
+        out.push(self.stack_ptr as u8);
+
+        let keys = (0..KEYPAD_SIZE)
+            .filter(|&idx| self.keys[idx])
+            .fold(0u16, |mask, idx| mask | 1 << idx);
+        out.extend_from_slice(&keys.to_le_bytes());
+        out.push(self.waiting_for_key_reg.unwrap_or(NOT_WAITING));
+
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.push(self.audio_pattern.is_some() as u8);
+        out.extend_from_slice(&self.audio_pattern.unwrap_or([0; AUDIO_PATTERN_SIZE]));
+        out.push(self.audio_pitch);
+        out.push(self.waiting_for_vblank as u8);
+        out.extend_from_slice(&self.rpl_flags);
+        out.push(self.exited as u8);
+
+        out.push(self.screen.is_hires() as u8);
+        out.push(self.screen.selected_planes());
+        out.extend_from_slice(self.screen.pixels());
+
+        out
+    }
+
+    /// Restores a snapshot made by `save_state` for the currently loaded rom.
+    ///
+    /// The emulator is left untouched when the state is rejected.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut reader = StateReader { data, pos: 0 };
+
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion { version });
+        }
+        let expected = rom_hash(&self.rom);
+        let found = reader.u64()?;
+        if found != expected {
+            return Err(StateError::RomMismatch { expected, found });
+        }
+
+        let counter = reader.u16()?;
+        let ram = reader.bytes(RAM_SIZE)?;
+        let v_reg = reader.bytes(V_SIZE)?;
+        let i_reg = reader.u16()?;
+        let mut stack = [0; STACK_SIZE];
+        for entry in stack.iter_mut() {
+            *entry = reader.u16()?;
+        }
+        let stack_ptr = reader.u8()? as u16;
+        if stack_ptr as usize > STACK_SIZE {
+            return Err(StateError::Corrupted);
+        }
+
+        let keys = reader.u16()?;
+        let waiting_for_key_reg = match reader.u8()? {
+            NOT_WAITING => None,
+            x if (x as usize) < V_SIZE => Some(x),
+            _ => return Err(StateError::Corrupted),
+        };
+
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let has_pattern = reader.u8()? != 0;
+        let pattern = reader.bytes(AUDIO_PATTERN_SIZE)?;
+        let audio_pattern = has_pattern.then(|| pattern.try_into().unwrap());
+        let audio_pitch = reader.u8()?;
+        let waiting_for_vblank = reader.u8()? != 0;
+        let rpl_flags = reader.bytes(RPL_FLAGS_SIZE)?;
+        let exited = reader.u8()? != 0;
+
+        let mut screen = Screen::new();
+        screen.set_hires(reader.u8()? != 0);
+        screen.select_planes(reader.u8()?);
+        let pixels = reader.bytes(screen.width() * screen.height())?;
+        screen.restore_pixels(pixels);
+
+        if reader.pos != data.len() {
+            return Err(StateError::Corrupted);
+        }
+
+        self.counter = counter;
+        self.instruction_pc = counter;
+        self.ram.copy_from_slice(ram);
+        self.v_reg.copy_from_slice(v_reg);
+        self.i_reg = i_reg;
+        self.stack = stack;
+        self.stack_ptr = stack_ptr;
+        for (idx, key) in self.keys.iter_mut().enumerate() {
+            *key = keys & (1 << idx) != 0;
+        }
+        self.waiting_for_key_reg = waiting_for_key_reg;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.audio_pattern = audio_pattern;
+        self.audio_pitch = audio_pitch;
+        self.waiting_for_vblank = waiting_for_vblank;
+        self.rpl_flags.copy_from_slice(rpl_flags);
+        self.exited = exited;
+        self.screen = screen;
+        self.fault = None;
+        self.history.clear();
+
+        Ok(())
+    }
+}
+
+struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(StateError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,301 @@
+//! Symbol files mapping ROM addresses back to the source they were built from.
+//!
+//! A symbol file is plain text, one record per line, `#` starts a comment:
+//!
+//! ```text
+//! # address  file:line
+//! line 0200 game.8o:12
+//! line 0202 game.8o:13
+//! # address  name
+//! label 0200 main
+//! label 0230 draw_player
+//! # first-last byte of data, never decoded as instructions
+//! data 0250-025F
+//! data 0260
+//! ```
+//!
+//! Addresses are hex, file paths are relative to the symbol file. By default
+//! the symbol file of `game.ch8` is `game.sym` next to it.
+//!
+//! `chiprs asm` writes one next to the rom it assembles. Label lists with one
+//! `name address` pair per line, as exported by Octo tools, can be turned into
+//! symbol files with `import_labels`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: PathBuf,
+    /// 1-based.
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolMap {
+    lines: BTreeMap<u16, SourceLine>,
+    // The name shown for an address, the last one inserted
+    labels: BTreeMap<u16, String>,
+    // Every name, including aliases sharing an address
+    addresses: BTreeMap<String, u16>,
+    // Keyed by first address
+    data: BTreeMap<u16, u16>,
+}
+
+impl SymbolMap {
+    /// The symbol file next to a rom, `None` when there is none.
+    pub fn find_for_rom(rom_path: &Path) -> Option<PathBuf> {
+        let path = rom_path.with_extension("sym");
+        path.is_file().then_some(path)
+    }
+
+    /// Reads a symbol file, resolving source paths against its directory.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        let mut symbols = Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        // Absolute, so the paths compare equal to the ones editors use
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        for source in symbols.lines.values_mut() {
+            source.file = dir.join(&source.file);
+        }
+        Ok(symbols)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            symbols
+                .parse_record(line)
+                .map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        Ok(symbols)
+    }
+
+    /// Reads a label list of `name address` lines, `#` starts a comment.
+    pub fn import_labels(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [name, address] = fields.as_slice() else {
+                return Err(format!(
+                    "line {}: expected 'name address', got '{}'",
+                    number + 1,
+                    line
+                ));
+            };
+            let address =
+                parse_address(address).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            symbols.insert_label(address, name);
+        }
+        Ok(symbols)
+    }
+
+    fn parse_record(&mut self, record: &str) -> Result<(), String> {
+        let fields = record.split_whitespace().collect::<Vec<_>>();
+        match fields.as_slice() {
+            ["label", address, name] => {
+                self.insert_label(parse_address(address)?, name);
+                Ok(())
+            }
+            ["data", range] => {
+                let (first, last) = range.split_once('-').unwrap_or((range, range));
+                let (first, last) = (parse_address(first)?, parse_address(last)?);
+                if first > last {
+                    return Err(format!("invalid data range '{}'", range));
+                }
+                self.insert_data(first..=last);
+                Ok(())
+            }
+            ["line", address, location] => {
+                let address = parse_address(address)?;
+                let (file, line) = location
+                    .rsplit_once(':')
+                    .ok_or_else(|| format!("expected file:line, got '{}'", location))?;
+                let line = line
+                    .parse()
+                    .map_err(|_| format!("invalid line number '{}'", line))?;
+                self.lines.insert(
+                    address,
+                    SourceLine {
+                        file: PathBuf::from(file),
+                        line,
+                    },
+                );
+                Ok(())
+            }
+            _ => Err(format!("malformed record '{}'", record)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.labels.is_empty() && self.data.is_empty()
+    }
+
+    pub fn insert_line(&mut self, address: u16, source: SourceLine) {
+        self.lines.insert(address, source);
+    }
+
+    /// Names `address`, moving `name` there if it named another address.
+    pub fn insert_label(&mut self, address: u16, name: &str) {
+        if let Some(old) = self.addresses.insert(name.to_string(), address)
+            && old != address
+            && self.labels.get(&old).is_some_and(|shown| shown == name)
+        {
+            // Another name left at the old address is shown instead
+            let alias = self
+                .addresses
+                .iter()
+                .filter(|(_, at)| **at == old)
+                .map(|(alias, _)| alias.clone())
+                .next_back();
+            match alias {
+                Some(alias) => self.labels.insert(old, alias),
+                None => self.labels.remove(&old),
+            };
+        }
+        self.labels.insert(address, name.to_string());
+    }
+
+    /// Marks `range` as data, merged with the regions it overlaps.
+    pub fn insert_data(&mut self, range: RangeInclusive<u16>) {
+        let (mut first, mut last) = range.into_inner();
+        let overlapping = self
+            .data
+            .range(..=last)
+            .filter(|(_, end)| **end >= first)
+            .map(|(start, end)| (*start, *end))
+            .collect::<Vec<_>>();
+        for (start, end) in overlapping {
+            self.data.remove(&start);
+            first = first.min(start);
+            last = last.max(end);
+        }
+        self.data.insert(first, last);
+    }
+
+    /// Adds the records of `other`, which win over the existing ones.
+    pub fn merge(&mut self, other: SymbolMap) {
+        self.lines.extend(other.lines);
+        // The names shown go last so they stay shown
+        let aliases = other
+            .addresses
+            .iter()
+            .filter(|(name, address)| other.labels.get(address) != Some(name));
+        for (name, &address) in aliases {
+            self.insert_label(address, name);
+        }
+        for (address, name) in &other.labels {
+            self.insert_label(*address, name);
+        }
+        for (first, last) in other.data {
+            self.insert_data(first..=last);
+        }
+    }
+
+    /// The label shown for `address`, when several share it the last one
+    /// inserted.
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.labels
+            .iter()
+            .map(|(address, name)| (*address, name.as_str()))
+    }
+
+    pub fn address_of_label(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    /// `address` as the closest label before it plus an offset, e.g.
+    /// `draw_player+4`, or in hex without a label.
+    pub fn describe(&self, address: u16) -> String {
+        match self.labels.range(..=address).next_back() {
+            Some((&start, name)) if start == address => name.clone(),
+            Some((&start, name)) => format!("{}+{}", name, address - start),
+            None => format!("{:04X}", address),
+        }
+    }
+
+    /// Whether `address` is in a data region. Regions never overlap, only the
+    /// one starting closest before it can hold it.
+    pub fn is_data(&self, address: u16) -> bool {
+        self.data
+            .range(..=address)
+            .next_back()
+            .is_some_and(|(_, &last)| address <= last)
+    }
+
+    /// Whether the symbols map addresses back to source lines.
+    pub fn has_source_lines(&self) -> bool {
+        !self.lines.is_empty()
+    }
+
+    /// The source line an address was assembled from.
+    pub fn source_line(&self, address: u16) -> Option<&SourceLine> {
+        self.lines.get(&address)
+    }
+
+    /// The lowest address assembled from `line` of `file`, or from the first
+    /// line after it holding code. `file` may be longer than the recorded
+    /// path, e.g. absolute.
+    pub fn address_of(&self, file: &Path, line: u32) -> Option<(u16, u32)> {
+        self.lines
+            .iter()
+            .filter(|(_, source)| source.line >= line && file.ends_with(&source.file))
+            .min_by_key(|(address, source)| (source.line, **address))
+            .map(|(address, source)| (*address, source.line))
+    }
+}
+
+fn parse_address(address: &str) -> Result<u16, String> {
+    u16::from_str_radix(address.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid address '{}'", address))
+}
+
+/// The symbol file text, `parse` reads it back.
+impl fmt::Display for SymbolMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Aliases first, so reading the file back shows the same label
+        let mut labels = self
+            .addresses
+            .iter()
+            .map(|(name, &address)| (address, self.labels.get(&address) == Some(name), name))
+            .collect::<Vec<_>>();
+        labels.sort();
+        for (address, _, name) in labels {
+            writeln!(f, "label {:04X} {}", address, name)?;
+        }
+        for (first, last) in &self.data {
+            writeln!(f, "data {:04X}-{:04X}", first, last)?;
+        }
+        for (address, source) in &self.lines {
+            writeln!(
+                f,
+                "line {:04X} {}:{}",
+                address,
+                source.file.display(),
+                source.line
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+use crate::constants::V_SIZE;
+use crate::instruction::{Instruction, InstructionClass};
+use crate::symbols::SymbolMap;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraceFormat {
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "jsonl" => Ok(Self::Jsonl),
+            _ => Err(format!(
+                "unknown trace format '{}', expected text or jsonl",
+                s
+            )),
+        }
+    }
+}
+
+/// The registers an instruction can change, compared before and after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Registers {
+    pub v: [u8; V_SIZE],
+    pub i: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub stack_ptr: u16,
+}
+
+impl Registers {
+    // Name, old and new value of each register that differs
+    fn changes(&self, after: &Registers) -> Vec<(String, u16, u16)> {
+        let mut changes = Vec::new();
+        for (idx, (old, new)) in self.v.iter().zip(after.v).enumerate() {
+            if *old != new {
+                changes.push((format!("V{:X}", idx), *old as u16, new as u16));
+            }
+        }
+        let others = [
+            ("I", self.i, after.i),
+            ("DT", self.delay_timer as u16, after.delay_timer as u16),
+            ("ST", self.sound_timer as u16, after.sound_timer as u16),
+            ("SP", self.stack_ptr, after.stack_ptr),
+        ];
+        for (name, old, new) in others {
+            if old != new {
+                changes.push((name.to_string(), old, new));
+            }
+        }
+        changes
+    }
+}
+
+/// The `--trace` command line options shared by the frontends.
+#[derive(Debug, Clone, Default)]
+pub struct TraceOptions {
+    /// A file or `-` for stdout, nothing is traced without it.
+    pub path: Option<String>,
+    pub format: TraceFormat,
+    pub ranges: Vec<RangeInclusive<u16>>,
+    pub classes: Vec<InstructionClass>,
+    pub ring_size: Option<usize>,
+}
+
+impl TraceOptions {
+    /// Reads the value of a `--trace*` flag.
+    pub fn parse_flag(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        match flag {
+            "--trace" => self.path = Some(value.to_string()),
+            "--trace-format" => self.format = value.parse()?,
+            "--trace-range" => self.ranges.push(parse_range(value)?),
+            "--trace-class" => self.classes.push(value.parse()?),
+            "--trace-ring" => {
+                let size = value
+                    .parse()
+                    .map_err(|_| format!("invalid value '{}' for {}", value, flag))?;
+                self.ring_size = Some(size);
+            }
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+        Ok(())
+    }
+
+    /// The tracer the options describe, `None` without `--trace`.
+    pub fn tracer(&self, symbols: &SymbolMap) -> Result<Option<Tracer>, String> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+        let mut tracer = Tracer::create(path, self.format)
+            .map_err(|e| format!("unable to create {}: {}", path, e))?;
+        self.ranges
+            .iter()
+            .for_each(|range| tracer.add_range(range.clone()));
+        self.classes
+            .iter()
+            .for_each(|class| tracer.add_class(*class));
+        if let Some(size) = self.ring_size {
+            tracer.set_ring_size(size);
+        }
+        tracer.set_symbols(symbols.clone());
+        Ok(Some(tracer))
+    }
+}
+
+// `first-last` or a single address, in hex
+fn parse_range(text: &str) -> Result<RangeInclusive<u16>, String> {
+    let address = |text: &str| {
+        u16::from_str_radix(text.trim_start_matches("0x"), 16)
+            .map_err(|_| format!("invalid address '{}'", text))
+    };
+    let (first, last) = text.split_once('-').unwrap_or((text, text));
+    let (first, last) = (address(first)?, address(last)?);
+    if first > last {
+        return Err(format!("invalid address range '{}'", text));
+    }
+    Ok(first..=last)
+}
+
+/// Writes a line per executed instruction, see `Emulator::set_tracer`.
+///
+/// With a ring size only the last instructions are kept, and written when
+/// the emulator faults.
+pub struct Tracer {
+    output: Box<dyn Write>,
+    format: TraceFormat,
+    ranges: Vec<RangeInclusive<u16>>,
+    classes: Vec<InstructionClass>,
+    ring: Option<(usize, VecDeque<String>)>,
+    symbols: SymbolMap,
+    // Instructions executed since tracing started, traced or not
+    cycle: u64,
+    // The first write that failed, tracing stops there
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new(output: Box<dyn Write>, format: TraceFormat) -> Self {
+        Self {
+            output,
+            format,
+            ranges: Vec::new(),
+            classes: Vec::new(),
+            ring: None,
+            symbols: SymbolMap::default(),
+            cycle: 0,
+            error: None,
+        }
+    }
+
+    /// Traces into a file, `-` for stdout.
+    pub fn create(path: &str, format: TraceFormat) -> io::Result<Self> {
+        let output: Box<dyn Write> = if path == "-" {
+            Box::new(io::stdout())
+        } else {
+            Box::new(BufWriter::new(File::create(path)?))
+        };
+        Ok(Self::new(output, format))
+    }
+
+    /// Only traces instructions in the ranges added.
+    pub fn add_range(&mut self, range: RangeInclusive<u16>) {
+        self.ranges.push(range);
+    }
+
+    /// Only traces instructions of the classes added.
+    pub fn add_class(&mut self, class: InstructionClass) {
+        self.classes.push(class);
+    }
+
+    /// Keeps only the last `size` instructions until a fault.
+    pub fn set_ring_size(&mut self, size: usize) {
+        self.ring = Some((size, VecDeque::with_capacity(size)));
+    }
+
+    /// Labels are written next to the addresses.
+    pub fn set_symbols(&mut self, symbols: SymbolMap) {
+        self.symbols = symbols;
+    }
+
+    /// Why writing the trace failed, it stopped at the first error.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        pc: u16,
+        instruction: &Instruction,
+        before: &Registers,
+        after: &Registers,
+    ) {
+        let cycle = self.cycle;
+        self.cycle += 1;
+
+        let in_range = self.ranges.is_empty() || self.ranges.iter().any(|r| r.contains(&pc));
+        let in_class = self.classes.is_empty() || self.classes.contains(&instruction.class());
+        if !in_range || !in_class {
+            return;
+        }
+
+        let line = self.format_line(cycle, pc, instruction, &before.changes(after));
+        match &mut self.ring {
+            Some((size, lines)) => {
+                if lines.len() == *size {
+                    lines.pop_front();
+                }
+                if *size > 0 {
+                    lines.push_back(line);
+                }
+            }
+            None => self.write(&line),
+        }
+    }
+
+    /// Writes the instructions kept in ring mode, oldest first.
+    pub(crate) fn dump(&mut self) {
+        let Some((_, lines)) = &mut self.ring else {
+            return;
+        };
+        let lines = lines.drain(..).collect::<Vec<_>>();
+        lines.iter().for_each(|line| self.write(line));
+        self.flush();
+    }
+
+    /// Writes out what is still buffered, at exit.
+    pub(crate) fn finish(&mut self) {
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.output.flush() {
+            self.fail(e);
+        }
+    }
+
+    fn write(&mut self, line: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = writeln!(self.output, "{}", line) {
+            self.fail(e);
+        }
+    }
+
+    fn fail(&mut self, e: io::Error) {
+        self.error.get_or_insert(e);
+    }
+
+    fn format_line(
+        &self,
+        cycle: u64,
+        pc: u16,
+        instruction: &Instruction,
+        changes: &[(String, u16, u16)],
+    ) -> String {
+        let label = (!self.symbols.is_empty()).then(|| self.symbols.describe(pc));
+        match self.format {
+            TraceFormat::Text => {
+                let mut line = format!("{:>8} {:04X}", cycle, pc);
+                if let Some(label) = label {
+                    let _ = write!(line, " ({})", label);
+                }
+                let _ = write!(line, " {}", instruction);
+                for (name, old, new) in changes {
+                    let _ = write!(line, " {}={:X}->{:X}", name, old, new);
+                }
+                line
+            }
+            TraceFormat::Jsonl => {
+                let opcode = instruction
+                    .encode()
+                    .iter()
+                    .map(|byte| format!("{:02X}", byte))
+                    .collect::<String>();
+                // Display starts with the opcode
+                let text = instruction.to_string();
+                let name = text
+                    .split_once(": ")
+                    .map_or(text.as_str(), |(_, name)| name);
+
+                let mut line = format!("{{\"cycle\":{},\"pc\":{}", cycle, pc);
+                if let Some(label) = label {
+                    let _ = write!(line, ",\"label\":\"{}\"", json_escape(&label));
+                }
+                let _ = write!(
+                    line,
+                    ",\"opcode\":\"{}\",\"instruction\":\"{}\",\"changes\":{{",
+                    opcode,
+                    json_escape(name)
+                );
+                let changes = changes
+                    .iter()
+                    .map(|(name, old, new)| format!("\"{}\":[{},{}]", name, old, new))
+                    .collect::<Vec<_>>();
+                let _ = write!(line, "{}}}}}", changes.join(","));
+                line
+            }
+        }
+    }
+}
+
+fn json_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,234 @@
+//! Undo records that let the debugger step backwards.
+//!
+//! While debugging, every executed instruction leaves a record of the state
+//! it overwrote. Undoing it restores that state and keeps what is needed to
+//! run the instruction again exactly as before: the random value `CXNN`
+//! drew and the timer ticks that followed it.
+
+use crate::constants::{AUDIO_PATTERN_SIZE, RPL_FLAGS_SIZE, STACK_SIZE, V_SIZE};
+use crate::instruction::{Instruction, InstructionClass};
+use crate::screen::Screen;
+use crate::Emulator;
+use std::collections::VecDeque;
+
+// Records kept before the oldest are dropped
+const UNDO_LIMIT: usize = 10_000;
+
+pub(crate) struct UndoRecord {
+    counter: u16,
+    v_reg: [u8; V_SIZE],
+    i_reg: u16,
+    stack: [u16; STACK_SIZE],
+    stack_ptr: u16,
+    waiting_for_key_reg: Option<u8>,
+    delay_timer: u8,
+    sound_timer: u8,
+    audio_pattern: Option<[u8; AUDIO_PATTERN_SIZE]>,
+    audio_pitch: u8,
+    waiting_for_vblank: bool,
+    rpl_flags: [u8; RPL_FLAGS_SIZE],
+    exited: bool,
+    // Address and previous value of every byte written, in order
+    ram: Vec<(usize, u8)>,
+    screen: ScreenUndo,
+    pub(crate) redo: Redo,
+}
+
+/// What running an undone instruction again needs to repeat it exactly.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Redo {
+    pc: u16,
+    pub(crate) random: Option<u8>,
+    // Timer ticks before the next instruction
+    ticks: u32,
+}
+
+enum ScreenUndo {
+    Unchanged,
+    /// The screen before a screen instruction, until it's compared to the one after.
+    Before(Screen),
+    /// Plane bits to flip back and the planes selected before.
+    Xor {
+        planes: u8,
+        pixels: Vec<(usize, u8)>,
+    },
+}
+
+impl ScreenUndo {
+    // Keeps the whole screen only when the resolution changed
+    fn shrink(self, after: &Screen) -> Self {
+        let ScreenUndo::Before(before) = self else {
+            return self;
+        };
+        if before.is_hires() != after.is_hires() {
+            return ScreenUndo::Before(before);
+        }
+        let pixels = before
+            .pixels()
+            .iter()
+            .zip(after.pixels())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(idx, (old, new))| (idx, old ^ new))
+            .collect::<Vec<_>>();
+        if pixels.is_empty() && before.selected_planes() == after.selected_planes() {
+            ScreenUndo::Unchanged
+        } else {
+            ScreenUndo::Xor {
+                planes: before.selected_planes(),
+                pixels,
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct UndoHistory {
+    records: VecDeque<UndoRecord>,
+    // Of the records undone, the last one undone at the end
+    redo: Vec<Redo>,
+}
+
+impl UndoHistory {
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+        self.redo.clear();
+    }
+
+    // Whether there is anything to step back over or run again
+    pub(crate) fn is_empty(&self) -> bool {
+        self.records.is_empty() && self.redo.is_empty()
+    }
+
+    // The redo for the instruction at `pc`, the others are forgotten once the
+    // program takes a different path than the one undone
+    fn take_redo(&mut self, pc: u16) -> Redo {
+        match self.redo.pop() {
+            Some(redo) if redo.pc == pc => redo,
+            _ => {
+                self.redo.clear();
+                Redo {
+                    pc,
+                    ..Redo::default()
+                }
+            }
+        }
+    }
+
+    /// Counts a timer tick towards the last instruction.
+    pub(crate) fn ticked(&mut self) {
+        if let Some(record) = self.records.back_mut() {
+            record.redo.ticks += 1;
+        }
+    }
+}
+
+impl Emulator {
+    /// Undoes the last instruction run while debugging, along with the timer
+    /// ticks that followed it, and pauses.
+    ///
+    /// Instructions are recorded while paused or while breakpoints are set,
+    /// running on without them forgets the history. Running the instruction
+    /// again restores the exact same state. Profile and coverage counts and
+    /// trace lines are not undone, running the instruction again adds to
+    /// them once more. Returns whether there was anything to undo.
+    pub fn step_back(&mut self) -> bool {
+        let Some(record) = self.history.records.pop_back() else {
+            return false;
+        };
+        self.history.redo.push(record.redo);
+
+        for &(address, value) in record.ram.iter().rev() {
+            self.ram[address] = value;
+        }
+        match record.screen {
+            ScreenUndo::Unchanged => {}
+            ScreenUndo::Before(screen) => self.screen = screen,
+            ScreenUndo::Xor { planes, pixels } => {
+                self.screen.flip_pixels(&pixels);
+                self.screen.select_planes(planes);
+            }
+        }
+        self.counter = record.counter;
+        self.instruction_pc = record.counter;
+        self.v_reg = record.v_reg;
+        self.i_reg = record.i_reg;
+        self.stack = record.stack;
+        self.stack_ptr = record.stack_ptr;
+        self.waiting_for_key_reg = record.waiting_for_key_reg;
+        self.delay_timer = record.delay_timer;
+        self.sound_timer = record.sound_timer;
+        self.audio_pattern = record.audio_pattern;
+        self.audio_pitch = record.audio_pitch;
+        self.waiting_for_vblank = record.waiting_for_vblank;
+        self.rpl_flags = record.rpl_flags;
+        self.exited = record.exited;
+        self.fault = None;
+        self.is_paused = true;
+        true
+    }
+
+    pub fn can_step_back(&self) -> bool {
+        !self.history.records.is_empty()
+    }
+
+    fn is_recording_undo(&self) -> bool {
+        self.is_paused || self.debugger.is_active()
+    }
+
+    // Called before `instruction` at `instruction_pc` runs
+    pub(crate) fn begin_undo(&mut self, instruction: &Instruction) {
+        if !self.is_recording_undo() {
+            self.history.clear();
+            return;
+        }
+
+        let screen = match instruction.class() {
+            InstructionClass::Draw | InstructionClass::Screen => {
+                ScreenUndo::Before(self.screen.clone())
+            }
+            _ => ScreenUndo::Unchanged,
+        };
+        self.undo = Some(UndoRecord {
+            counter: self.instruction_pc,
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            stack: self.stack,
+            stack_ptr: self.stack_ptr,
+            waiting_for_key_reg: self.waiting_for_key_reg,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            audio_pattern: self.audio_pattern,
+            audio_pitch: self.audio_pitch,
+            waiting_for_vblank: self.waiting_for_vblank,
+            rpl_flags: self.rpl_flags,
+            exited: self.exited,
+            ram: Vec::new(),
+            screen,
+            redo: self.history.take_redo(self.instruction_pc),
+        });
+    }
+
+    // Called once the instruction ran, a redone one also repeats its timer ticks
+    pub(crate) fn end_undo(&mut self, succeeded: bool) {
+        let Some(mut record) = self.undo.take() else {
+            return;
+        };
+        record.screen = record.screen.shrink(&self.screen);
+        let ticks = if succeeded { record.redo.ticks } else { 0 };
+        record.redo.ticks = 0;
+
+        if self.history.records.len() == UNDO_LIMIT {
+            self.history.records.pop_front();
+        }
+        self.history.records.push_back(record);
+        (0..ticks).for_each(|_| self.decrement_timers());
+    }
+
+    // Notes the previous value of a byte an instruction writes
+    pub(crate) fn record_write(&mut self, address: usize, old: u8) {
+        if let Some(record) = &mut self.undo {
+            record.ram.push((address, old));
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Assembler tests, including disassembling the bundled roms and assembling
+//! the result back.
+
+use chiprs_core::asm::assemble;
+use chiprs_core::disasm::Disassembly;
+use chiprs_core::symbols::SymbolMap;
+use chiprs_core::Instruction;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn disassembled_roms_assemble_back() {
+    let roms_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../roms");
+    for entry in fs::read_dir(roms_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let rom = fs::read(&path).unwrap();
+
+        let source = Disassembly::of_rom(&rom, &SymbolMap::default()).to_string();
+        let program = assemble(&source)
+            .unwrap_or_else(|e| panic!("{} doesn't assemble: {}", path.display(), e));
+        assert!(program.rom == rom, "{} differs", path.display());
+    }
+}
+
+#[test]
+fn skips_without_an_instruction_after_assemble_back() {
+    let roms: [&[u8]; 4] = [
+        // At the end
+        &[0x60, 0x01, 0x30, 0x00],
+        // Skipping a skip at the end
+        &[0x30, 0x00, 0x40, 0x01],
+        // Before data no path reaches
+        &[0x30, 0x00, 0x00],
+        // Before a long instruction cut off by the end
+        &[0x60, 0x01, 0x30, 0x00, 0xF0, 0x00],
+    ];
+    for rom in roms {
+        let source = Disassembly::of_rom(rom, &SymbolMap::default()).to_string();
+        assert!(!source.trim_end().ends_with("then"), "{}", source);
+        let program = assemble(&source).unwrap_or_else(|e| panic!("{}\n{}", e, source));
+        assert_eq!(program.rom, rom, "{}", source);
+    }
+}
+
+#[test]
+fn every_opcode_encodes_back() {
+    for opcode in 0..=u16::MAX {
+        let instruction = Instruction::from_opcode(opcode);
+        assert_eq!(
+            instruction.encode(),
+            opcode.to_be_bytes(),
+            "{}",
+            instruction
+        );
+    }
+    let long = Instruction::decode(0xF000, 0x1234);
+    assert_eq!(long.encode(), [0xF0, 0x00, 0x12, 0x34]);
+}
+
+#[test]
+fn control_structures() {
+    let source = "
+        :alias counter v3
+        :const LIMIT 5
+        : main
+            counter := 0
+            loop
+                counter += 1
+                if counter == LIMIT begin
+                    jump done
+                else
+                    i := sprite
+                end
+            again
+        : done
+            if v0 key then exit
+        : sprite
+            0b11110000 0x90 -1
+    ";
+    let program = assemble(source).unwrap();
+    #[rustfmt::skip]
+    let expected = [
+        0x63, 0x00, // 200: counter := 0
+        0x73, 0x01, // 202: loop counter += 1
+        0x33, 0x05, // 204: if counter == LIMIT begin
+        0x12, 0x0C, // 206:   (jump to else)
+        0x12, 0x10, // 208:   jump done
+        0x12, 0x0E, // 20A: else (jump to end)
+        0xA2, 0x14, // 20C:   i := sprite
+        0x12, 0x02, // 20E: end again
+        0xE0, 0xA1, // 210: done, if v0 key then
+        0x00, 0xFD, // 212: exit
+        0xF0, 0x90, 0xFF, // 214: sprite
+    ];
+    assert_eq!(program.rom, expected);
+    assert_eq!(program.labels["done"], 0x210);
+    assert_eq!(program.lines[&0x204], 8);
+}
+
+#[test]
+fn jumps_to_main_when_it_is_not_first() {
+    let program = assemble(": helper return : main helper").unwrap();
+    assert_eq!(program.rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+}
+
+#[test]
+fn reports_the_line_of_errors() {
+    let error = assemble("clear\nv0 := 256").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert_eq!(error.message, "byte 256 out of range");
+
+    let error = assemble("loop\nclear").unwrap_err();
+    assert_eq!(error.line, 1);
+
+    let error = assemble(": main\n  if v0 == 1 then\n").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert_eq!(error.message, "expected statement after then");
+}
+
+#[test]
+fn sources_larger_than_memory_are_rejected() {
+    // Fills memory from 0x200 up to the last byte
+    let full = "0\n".repeat(0x10000 - 0x200);
+    assert_eq!(assemble(&full).unwrap().rom.len(), 0xFE00);
+
+    for (rest, line) in [("0", 1), (": end", 1), ("clear", 1)] {
+        let error = assemble(&format!("{}{}", full, rest)).unwrap_err();
+        assert_eq!(error.message, "out of memory");
+        assert_eq!(error.line, 0xFE00 + line);
+    }
+
+    // Running past the end halfway through an instruction
+    let almost = "0\n".repeat(0x10000 - 0x200 - 2);
+    let error = assemble(&format!("{}i := long 0x1234", almost)).unwrap_err();
+    assert_eq!(error.message, "out of memory");
+    assert_eq!(error.line, 0xFDFF);
+}
+
+#[test]
+fn symbols_name_the_disassembly() {
+    let source = "
+        : main
+            i := player
+            sprite v0 v1 2
+            draw_score
+        : halt
+            jump halt
+        : draw_score
+            return
+        : player
+            0x3C 0x18
+    ";
+    let program = assemble(source).unwrap();
+    let text = program.symbols(Path::new("game.8o")).to_string();
+    let symbols = SymbolMap::parse(&text).unwrap();
+    assert_eq!(symbols.address_of_label("player"), Some(0x20A));
+    assert!(symbols.is_data(0x20B));
+    assert_eq!(symbols.source_line(0x204).unwrap().line, 5);
+    assert_eq!(symbols.describe(0x202), "main+2");
+
+    let disassembly = Disassembly::of_rom(&program.rom, &symbols).to_string();
+    assert!(disassembly.contains("\tdraw_score\n"), "{}", disassembly);
+    assert!(
+        disassembly.contains(": player\n\t0x3C 0x18\n"),
+        "{}",
+        disassembly
+    );
+    assert_eq!(assemble(&disassembly).unwrap().rom, program.rom);
+}
+
+#[test]
+fn labels_at_the_same_address_all_resolve() {
+    let program = assemble(": main\n: loop\n  v0 += 1\n  jump loop").unwrap();
+    assert_eq!(program.rom, [0x70, 0x01, 0x12, 0x00]);
+    let symbols = program.symbols(Path::new("loop.8o"));
+    assert_eq!(symbols.address_of_label("main"), Some(0x200));
+    assert_eq!(symbols.address_of_label("loop"), Some(0x200));
+
+    let text = symbols.to_string();
+    let read_back = SymbolMap::parse(&text).unwrap();
+    assert_eq!(read_back.address_of_label("main"), Some(0x200));
+    assert_eq!(read_back.address_of_label("loop"), Some(0x200));
+    assert_eq!(read_back.label(0x200), symbols.label(0x200));
+    assert_eq!(read_back.to_string(), text);
+}
+
+#[test]
+fn labels_move_and_data_regions_merge() {
+    let text = "\
+label 0200 main
+label 0200 start
+label 0204 loop
+label 0206 loop
+label 0204 done
+label 0204 done_again
+data 0300-0310
+data 0302-0304
+data 0308-0320
+";
+    let symbols = SymbolMap::parse(text).unwrap();
+    // A name only stays where it was given last
+    assert_eq!(symbols.address_of_label("loop"), Some(0x206));
+    assert_eq!(symbols.label(0x206), Some("loop"));
+    assert_eq!(symbols.label(0x204), Some("done_again"));
+    assert_eq!(symbols.describe(0x205), "done_again+1");
+
+    // Moving the name shown leaves another one there
+    let mut moved = symbols.clone();
+    moved.insert_label(0x208, "start");
+    assert_eq!(moved.label(0x200), Some("main"));
+    moved.insert_label(0x208, "main");
+    assert_eq!(moved.label(0x200), None);
+    assert_eq!(moved.describe(0x202), "0202");
+
+    // Inside the first region, past the end of the second
+    assert!(symbols.is_data(0x306));
+    assert!(symbols.is_data(0x320));
+    assert!(!symbols.is_data(0x321));
+
+    let written = symbols.to_string();
+    assert_eq!(written.matches("loop").count(), 1);
+    assert_eq!(written.matches("data").count(), 1);
+    assert_eq!(SymbolMap::parse(&written).unwrap().to_string(), written);
+}
@@ -0,0 +1,160 @@
+//! Renders the buzzer and XO-CHIP audio patterns, checking the samples that
+//! come out and what ends up in WAV files.
+
+use chiprs_core::audio::{pattern_rate, Audio, AudioSink, NullSink, WavSink};
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const SAMPLE_RATE: u32 = 22050;
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("chiprs-{}-{}.wav", name, std::process::id()))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn wav_files_hold_every_frame() {
+    let path = temp_path("frames");
+    let sink = WavSink::create(&path, SAMPLE_RATE).unwrap();
+    let mut audio = Audio::new(Box::new(sink), 441.0, 0.5).unwrap();
+    // 22050 / 60 isn't whole, the remainder carries over between frames
+    for frame in 0..90 {
+        audio.render_frame(frame < 30, None, 64);
+    }
+    audio.finish().unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let samples = SAMPLE_RATE * 90 / 60;
+    assert_eq!(&bytes[..4], b"RIFF");
+    assert_eq!(u32_at(&bytes, 4), 36 + 2 * samples);
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(&bytes, 24), SAMPLE_RATE);
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(u32_at(&bytes, 40), 2 * samples);
+    assert_eq!(bytes.len() as u32, 44 + 2 * samples);
+
+    let pcm = bytes[44..]
+        .chunks(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    let level = (0.5 * i16::MAX as f32) as i16;
+    // Half a second of square wave, then a second of silence
+    let playing = SAMPLE_RATE as usize / 2;
+    assert!(pcm[..playing].iter().all(|&s| s == level || s == -level));
+    // 441Hz is 50 samples a period, the first half of each high
+    let high = pcm[..playing].iter().filter(|&&s| s == level).count();
+    assert_eq!(high, 220 * 25 + 25);
+    assert!(pcm[playing..].iter().all(|&s| s == 0));
+}
+
+#[test]
+fn dropping_the_sink_completes_the_header() {
+    let path = temp_path("drop");
+    let mut emu = Emulator::new(Quirks::default());
+    // V0 := 3C, sound timer := V0
+    emu.load(&[0x60, 0x3C, 0xF0, 0x18]).unwrap();
+    let sink = WavSink::create(&path, SAMPLE_RATE).unwrap();
+    emu.set_audio(Audio::new(Box::new(sink), 440.0, 0.25).unwrap());
+    for _ in 0..10 {
+        emu.run_frame(2).unwrap();
+    }
+    drop(emu);
+
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(u32_at(&bytes, 40), 2 * SAMPLE_RATE * 10 / 60);
+}
+
+#[test]
+fn bad_pitches_and_volumes_are_rejected() {
+    let audio = |pitch, volume| Audio::new(Box::new(NullSink), pitch, volume).is_ok();
+    assert!(audio(440.0, 0.0));
+    assert!(audio(0.5, 1.0));
+    assert!(!audio(f32::NAN, 0.25));
+    assert!(!audio(-440.0, 0.25));
+    assert!(!audio(0.0, 0.25));
+    assert!(!audio(f32::INFINITY, 0.25));
+    assert!(!audio(440.0, f32::NAN));
+    assert!(!audio(440.0, -0.1));
+    assert!(!audio(440.0, 1.5));
+}
+
+// Keeps what the buzzer renders where the test can read it
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<f32>>>);
+
+impl AudioSink for Capture {
+    fn sample_rate(&self) -> u32 {
+        // One sample per pattern bit at the default pitch
+        4000
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        self.0.lock().unwrap().extend_from_slice(samples);
+    }
+}
+
+// Loads `pattern` with F002, sets the pitch with FX3A and plays for `frames`
+fn play_pattern(pattern: [u8; 16], pitch: u8, frames: usize) -> Vec<f32> {
+    let mut rom = vec![
+        0xA2, 0x10, 0xF0, 0x02, 0x60, pitch, 0xF0, 0x3A, 0x61, 0x3C, 0xF1, 0x18, 0x12, 0x0C, 0x00,
+        0x00,
+    ];
+    rom.extend_from_slice(&pattern);
+
+    let capture = Capture::default();
+    let mut emu = Emulator::new(Quirks::XO_CHIP);
+    emu.load(&rom).unwrap();
+    emu.set_audio(Audio::new(Box::new(capture.clone()), 440.0, 1.0).unwrap());
+    for _ in 0..frames {
+        emu.run_frame(TICKS_PER_FRAME).unwrap();
+    }
+    capture.0.lock().unwrap().clone()
+}
+
+#[test]
+fn pattern_rates_double_every_48_steps() {
+    assert_eq!(pattern_rate(64), 4000.0);
+    assert_eq!(pattern_rate(112), 8000.0);
+    assert_eq!(pattern_rate(16), 2000.0);
+    assert!((pattern_rate(255) - 4000.0 * 2f64.powf(191.0 / 48.0)).abs() < 1e-6);
+}
+
+#[test]
+fn patterns_play_bit_by_bit_across_frames() {
+    let pattern: [u8; 16] = std::array::from_fn(|idx| (idx as u8).wrapping_mul(37) ^ 0x5A);
+    let samples = play_pattern(pattern, 64, 5);
+    assert!(samples.len() > 300);
+    for (n, &sample) in samples.iter().enumerate() {
+        let bit = n % 128;
+        let expected = if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+            1.0
+        } else {
+            -1.0
+        };
+        assert_eq!(sample, expected, "sample {}", n);
+    }
+}
+
+#[test]
+fn higher_pitches_average_the_bits() {
+    // Two bits a sample at 8000 bits per second, alternating bits cancel out
+    let samples = play_pattern([0xAA; 16], 112, 2);
+    assert!(samples.iter().all(|&sample| sample == 0.0));
+
+    let samples = play_pattern([0xAA; 16], 64, 2);
+    assert_eq!(samples[..4], [1.0, -1.0, 1.0, -1.0]);
+
+    // Four bits a sample, three of them set
+    let samples = play_pattern([0xEE; 16], 160, 2);
+    assert!(samples.iter().all(|&sample| sample == 0.5));
+}
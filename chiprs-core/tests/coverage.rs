@@ -0,0 +1,89 @@
+//! Checks the counts `Coverage` collects and the reports made from them.
+
+use chiprs_core::asm::assemble;
+use chiprs_core::coverage::Coverage;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::symbols::SymbolMap;
+use chiprs_core::Emulator;
+use std::path::Path;
+
+// Counts v0 up to 3, skipping the jump back once it gets there, then loops
+const SOURCE: &str = "\
+: main
+  v0 := 0
+: again
+  v0 += 1
+  if v0 != 3 then jump again
+  if v1 == 7 then v2 := 1
+: done
+  jump done
+";
+
+fn run(rom: &[u8], cycles: usize) -> Coverage {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(rom).unwrap();
+    emu.start_coverage();
+    for _ in 0..cycles {
+        emu.next().unwrap();
+    }
+    emu.coverage().unwrap().clone()
+}
+
+#[test]
+fn skips_count_both_directions() {
+    let program = assemble(SOURCE).unwrap();
+    let coverage = run(&program.rom, 12);
+    assert_eq!(coverage.count(0x200), 1);
+    assert_eq!(coverage.count(0x202), 3);
+    assert_eq!(coverage.count(0x20A), 0);
+    assert_eq!(coverage.count(0x20C), 2);
+    assert_eq!(coverage.skips(0x204), (1, 2));
+    assert_eq!(coverage.skips(0x208), (1, 0));
+}
+
+#[test]
+fn files_read_back_and_merge() {
+    let program = assemble(SOURCE).unwrap();
+    let coverage = run(&program.rom, 12);
+    let text = coverage.to_string();
+    assert!(text.contains("exec 0202 3\n"));
+    assert!(text.contains("skip 0204 1 2\n"));
+    assert_eq!(Coverage::parse(&text).unwrap(), coverage);
+
+    let mut merged = Coverage::parse("# earlier run\nexec 0202 1\nskip 0208 4 0\n").unwrap();
+    merged.merge(&coverage);
+    assert_eq!(merged.count(0x202), 4);
+    assert_eq!(merged.skips(0x208), (5, 0));
+
+    assert!(Coverage::parse("exec 0202").is_err());
+    assert!(Coverage::parse("skip 0208 x 1").is_err());
+}
+
+#[test]
+fn annotation_marks_what_never_ran() {
+    let program = assemble(SOURCE).unwrap();
+    let coverage = run(&program.rom, 7);
+    let text = coverage.annotate(&program.rom, &SymbolMap::default());
+    let lines = text.lines().collect::<Vec<_>>();
+    assert!(
+        lines.contains(&"         2  0204  \tif v0 != 0x03 then\t# skipped 0 of 2, never skipped")
+    );
+    assert!(lines.contains(&"     #####  0208  \tif v1 == 0x07 then\t# skipped 0 of 0"));
+    assert!(text.ends_with(
+        "# instructions run: 4 of 7 (57.1%)\n# skip directions taken: 1 of 4 (25.0%)\n"
+    ));
+}
+
+#[test]
+fn lcov_uses_source_lines() {
+    let program = assemble(SOURCE).unwrap();
+    let coverage = run(&program.rom, 12);
+    let symbols = program.symbols(Path::new("count.8o"));
+    let lcov = coverage.lcov(&program.rom, &symbols);
+    assert_eq!(
+        lcov,
+        "TN:\nSF:count.8o\n\
+         BRDA:5,0,0,1\nBRDA:5,0,1,2\nBRDA:6,1,0,1\nBRDA:6,1,1,0\nBRF:4\nBRH:3\n\
+         DA:2,1\nDA:4,3\nDA:5,3\nDA:6,1\nDA:8,2\nLF:5\nLH:5\nend_of_record\n"
+    );
+}
@@ -0,0 +1,286 @@
+//! Drives the DAP server from a scripted client over loopback, while the test
+//! thread runs frames like a frontend would.
+
+use chiprs_core::asm::assemble;
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::dap::DapServer;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+// Calls a subroutine over and over, line numbers are in the symbols
+const SOURCE: &str = "\
+: main
+  v0 := 0
+: loop
+  sub
+  v0 += 1
+  jump loop
+: sub
+  v1 += 1
+  return
+";
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    seq: u64,
+    // Events read while waiting for something else
+    events: Vec<Value>,
+}
+
+impl Client {
+    fn connect(address: SocketAddr) -> Self {
+        let stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        Self {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            writer: stream,
+            seq: 0,
+            events: Vec::new(),
+        }
+    }
+
+    fn read(&mut self) -> Value {
+        let mut header = String::new();
+        self.reader.read_line(&mut header).unwrap();
+        let length = header
+            .trim_end()
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let mut blank = String::new();
+        self.reader.read_line(&mut blank).unwrap();
+        assert_eq!(blank, "\r\n");
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    // The response body, failing on an error response
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let body = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        write!(
+            self.writer,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        loop {
+            let message = self.read();
+            if message["type"] == "event" {
+                self.events.push(message);
+                continue;
+            }
+            assert_eq!(message["request_seq"], self.seq);
+            assert_eq!(message["success"], true, "{} failed: {}", command, message);
+            return message["body"].clone();
+        }
+    }
+
+    fn event(&mut self, event: &str) -> Value {
+        if let Some(at) = self.events.iter().position(|e| e["event"] == event) {
+            return self.events.remove(at)["body"].clone();
+        }
+        loop {
+            let message = self.read();
+            if message["event"] == event {
+                return message["body"].clone();
+            }
+            self.events.push(message);
+        }
+    }
+
+    fn stopped(&mut self) -> String {
+        self.event("stopped")["reason"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    // Source line and instruction of the innermost frame
+    fn location(&mut self) -> (u64, String) {
+        let trace = self.request("stackTrace", json!({ "threadId": 1 }));
+        let frame = &trace["stackFrames"][0];
+        (
+            frame["line"].as_u64().unwrap(),
+            frame["instructionPointerReference"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        )
+    }
+}
+
+fn script(mut client: Client) {
+    // Skipped and reported to the frontend
+    client
+        .writer
+        .write_all(b"Content-Length: 3\r\n\r\n{x}")
+        .unwrap();
+    // Too large to read, skipped without losing track of the next message
+    write!(client.writer, "Content-Length: {}\r\n\r\n", 2 << 20).unwrap();
+    client.writer.write_all(&vec![b' '; 2 << 20]).unwrap();
+    let capabilities = client.request("initialize", json!({ "adapterID": "chiprs" }));
+    assert_eq!(capabilities["supportsStepBack"], true);
+    client.request(
+        "launch",
+        json!({ "program": "game.ch8", "stopOnEntry": true }),
+    );
+    client.event("initialized");
+
+    // Label lines move to the code after them, lines past the end have none
+    let breakpoints = client.request(
+        "setBreakpoints",
+        json!({
+            "source": { "path": "/home/someone/game.8o" },
+            "breakpoints": [{ "line": 3 }, { "line": 100 }],
+        }),
+    );
+    assert_eq!(
+        breakpoints["breakpoints"],
+        json!([
+            { "verified": true, "line": 4, "instructionReference": "0x0202" },
+            { "verified": false, "line": 100, "message": "no code at this line" },
+        ])
+    );
+
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.stopped(), "entry");
+    assert_eq!(client.location(), (2, "0x0200".to_string()));
+
+    // Ranges past either end of memory are clamped
+    let memory = |client: &mut Client, reference: &str, offset: i64, count: i64| {
+        let body = client.request(
+            "readMemory",
+            json!({ "memoryReference": reference, "offset": offset, "count": count }),
+        );
+        (
+            body["address"].as_str().unwrap().to_string(),
+            body["data"].as_str().unwrap().to_string(),
+            body["unreadableBytes"].as_i64().unwrap(),
+        )
+    };
+    assert_eq!(
+        memory(&mut client, "0x0200", 0, 2),
+        ("0x0200".to_string(), "YAA=".to_string(), 0)
+    );
+    assert_eq!(
+        memory(&mut client, "0x0200", -0x202, 4),
+        ("0x0000".to_string(), "8JA=".to_string(), 2)
+    );
+    assert_eq!(
+        memory(&mut client, "0xFFFF", i64::MAX, 4),
+        ("0x10000".to_string(), "".to_string(), 4)
+    );
+    assert_eq!(
+        memory(&mut client, "0xFFFE", 0, i64::MAX),
+        ("0xFFFE".to_string(), "AAA=".to_string(), i64::MAX - 2)
+    );
+
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.location(), (4, "0x0202".to_string()));
+
+    // Stepping over the call on the breakpoint's line runs it
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.location(), (5, "0x0204".to_string()));
+
+    // The breakpoint the step ran to is gone, the next stop is the call again
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "breakpoint");
+    assert_eq!(client.location(), (4, "0x0202".to_string()));
+
+    client.request("stepBack", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.location(), (6, "0x0206".to_string()));
+
+    client.request("disconnect", json!({}));
+}
+
+// Serves `script` a session debugging `source`, returns the adapter and the
+// emulator once the client disconnected
+fn serve(source: &'static str, script: fn(Client)) -> (DapServer, Emulator) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let client = thread::spawn(move || script(Client::connect(address)));
+    let (stream, _) = listener.accept().unwrap();
+    let mut dap = DapServer::new(stream.try_clone().unwrap(), stream);
+
+    let launch = dap.wait_for_launch().unwrap();
+    assert_eq!(launch.program, PathBuf::from("game.ch8"));
+    let program = assemble(source).unwrap();
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&program.rom).unwrap();
+    dap.launched(&mut emu, program.symbols(Path::new("game.8o")));
+
+    while dap.poll(&mut emu) {
+        let _ = emu.run_frame(TICKS_PER_FRAME);
+        thread::sleep(Duration::from_millis(1));
+    }
+    client.join().unwrap();
+    (dap, emu)
+}
+
+#[test]
+fn scripted_session() {
+    let (mut dap, emu) = serve(SOURCE, script);
+    assert_eq!(emu.debugger().breakpoints().collect::<Vec<_>>(), [0x202]);
+    let errors = dap.take_errors();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].starts_with("invalid debug adapter message"));
+    assert!(errors[1].ends_with("is too large"));
+}
+
+// Calls itself until the stack overflows
+const OVERFLOW_SOURCE: &str = "\
+: main
+  main
+";
+
+fn overflow_script(mut client: Client) {
+    client.request("initialize", json!({ "adapterID": "chiprs" }));
+    client.request(
+        "launch",
+        json!({ "program": "game.ch8", "stopOnEntry": true }),
+    );
+    client.event("initialized");
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.stopped(), "entry");
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "exception");
+
+    // Stepping over the faulted call reports the fault again
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "exception");
+    assert_eq!(client.location(), (2, "0x0200".to_string()));
+
+    client.request("disconnect", json!({}));
+}
+
+#[test]
+fn stepping_over_a_faulted_call_stops() {
+    let (mut dap, emu) = serve(OVERFLOW_SOURCE, overflow_script);
+    assert!(emu.fault().is_some());
+    assert_eq!(emu.debugger().breakpoints().count(), 0);
+    assert!(dap.take_errors().is_empty());
+}
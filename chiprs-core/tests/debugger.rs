@@ -0,0 +1,339 @@
+//! Checks where the debugger stops and that resuming runs on from there.
+
+use chiprs_core::asm::assemble;
+use chiprs_core::debugger::{Access, StopReason, WatchKind, Watchpoint};
+use chiprs_core::instruction::InstructionClass;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+
+// Counts v0 up forever, setting the delay timer on the way
+const SOURCE: &str = "\
+: main
+  v0 := 0
+: loop
+  v0 += 1
+  delay := v0
+  jump loop
+";
+
+// Stores v0 and v1 to data, then loads them back, over and over
+const WATCH_SOURCE: &str = "\
+: main
+  i := data
+  v0 := 5
+  v1 := 6
+  save v1
+  v2 := 7
+  i := data
+  load v1
+  jump main
+: data
+  0 0
+";
+
+// Where the data label ends up
+const DATA: u16 = 0x210;
+
+fn load() -> Emulator {
+    load_source(SOURCE)
+}
+
+fn load_source(source: &str) -> Emulator {
+    let program = assemble(source).unwrap();
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&program.rom).unwrap();
+    emu
+}
+
+/// Ticks until the debugger stops, failing after `limit` instructions.
+fn run_to_stop(emu: &mut Emulator, limit: usize) -> StopReason {
+    for _ in 0..limit {
+        if let Err(reason) = emu.tick() {
+            assert!(emu.is_paused());
+            assert_eq!(emu.debugger().last_stop(), Some(&reason));
+            return reason;
+        }
+    }
+    panic!("no stop within {} instructions", limit);
+}
+
+fn hits(emu: &Emulator, pc: u16) -> u32 {
+    emu.debugger().breakpoint(pc).unwrap().hits()
+}
+
+#[test]
+fn breakpoints_stop_before_the_instruction() {
+    let mut emu = load();
+    emu.debugger_mut().add_breakpoint(0x204);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Breakpoint { pc: 0x204 }
+    );
+    assert_eq!(emu.counter, 0x204);
+    assert_eq!(emu.cpu_state().v[0], 1);
+    assert_eq!(hits(&emu, 0x204), 1);
+
+    // Ticking while paused does nothing
+    emu.tick().unwrap();
+    assert_eq!(emu.counter, 0x204);
+}
+
+#[test]
+fn class_breakpoints_stop_on_their_class() {
+    let mut emu = load();
+    emu.debugger_mut()
+        .add_class_breakpoint(InstructionClass::Flow);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::ClassBreakpoint {
+            pc: 0x206,
+            class: InstructionClass::Flow
+        }
+    );
+    assert_eq!(emu.counter, 0x206);
+}
+
+#[test]
+fn new_addresses_stop_once_each() {
+    let mut emu = load();
+    emu.debugger_mut().set_break_on_new_address(true);
+    for pc in [0x200, 0x202, 0x204, 0x206] {
+        assert_eq!(run_to_stop(&mut emu, 10), StopReason::NewAddress { pc });
+        emu.pause_or_resume();
+    }
+    // The loop only revisits addresses already run
+    for _ in 0..20 {
+        emu.tick().unwrap();
+    }
+    assert!(!emu.is_paused());
+}
+
+#[test]
+fn resuming_runs_past_a_breakpoint_stop() {
+    let mut emu = load();
+    emu.debugger_mut().add_breakpoint(0x204);
+    run_to_stop(&mut emu, 10);
+    emu.pause_or_resume();
+    emu.tick().unwrap();
+    assert_eq!(emu.counter, 0x206);
+    assert_eq!(hits(&emu, 0x204), 1);
+
+    // Coming round again stops again
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Breakpoint { pc: 0x204 }
+    );
+    assert_eq!(emu.cpu_state().v[0], 2);
+    assert_eq!(hits(&emu, 0x204), 2);
+}
+
+#[test]
+fn resuming_runs_past_a_class_breakpoint_stop() {
+    let mut emu = load();
+    emu.debugger_mut()
+        .add_class_breakpoint(InstructionClass::Timers);
+    run_to_stop(&mut emu, 10);
+    emu.pause_or_resume();
+    emu.tick().unwrap();
+    assert_eq!(emu.counter, 0x206);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::ClassBreakpoint {
+            pc: 0x204,
+            class: InstructionClass::Timers
+        }
+    );
+    assert_eq!(emu.cpu_state().v[0], 2);
+}
+
+#[test]
+fn resuming_runs_past_a_breakpoint_stepped_onto() {
+    let mut emu = load();
+    emu.debugger_mut().add_breakpoint(0x202);
+    emu.pause_or_resume();
+    emu.next().unwrap();
+    assert_eq!(emu.counter, 0x202);
+    emu.pause_or_resume();
+    emu.tick().unwrap();
+    assert_eq!(emu.counter, 0x204);
+    assert_eq!(hits(&emu, 0x202), 0);
+}
+
+#[test]
+fn resuming_runs_past_a_class_breakpoint_stepped_onto() {
+    let mut emu = load();
+    emu.debugger_mut()
+        .add_class_breakpoint(InstructionClass::Arithmetic);
+    emu.pause_or_resume();
+    emu.next().unwrap();
+    emu.pause_or_resume();
+    emu.tick().unwrap();
+    assert_eq!(emu.counter, 0x204);
+}
+
+#[test]
+fn resuming_runs_past_a_breakpoint_stepped_back_onto() {
+    let mut emu = load();
+    emu.debugger_mut().add_breakpoint(0x202);
+    emu.pause_or_resume();
+    emu.next().unwrap();
+    emu.next().unwrap();
+    assert!(emu.step_back());
+    assert_eq!(emu.counter, 0x202);
+    emu.pause_or_resume();
+    emu.tick().unwrap();
+    assert_eq!(emu.counter, 0x204);
+    assert_eq!(hits(&emu, 0x202), 0);
+}
+
+#[test]
+fn resuming_only_skips_the_resumed_address() {
+    let mut emu = load();
+    emu.debugger_mut().add_breakpoint(0x204);
+    emu.pause_or_resume();
+    emu.next().unwrap();
+    emu.pause_or_resume();
+    // The next instruction is checked as usual
+    emu.tick().unwrap();
+    assert_eq!(
+        run_to_stop(&mut emu, 1),
+        StopReason::Breakpoint { pc: 0x204 }
+    );
+}
+
+fn watching(range: std::ops::RangeInclusive<u16>, kind: WatchKind) -> Emulator {
+    let mut emu = load_source(WATCH_SOURCE);
+    emu.debugger_mut()
+        .add_watchpoint(Watchpoint { range, kind });
+    emu
+}
+
+#[test]
+fn write_watchpoints_pause_after_the_instruction() {
+    let mut emu = watching(DATA..=DATA + 1, WatchKind::Write);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Watchpoint {
+            pc: 0x206,
+            address: DATA,
+            access: Access::Write,
+            old: 0,
+            new: 5
+        }
+    );
+    // The whole save ran, not only the write that hit
+    assert_eq!(emu.counter, 0x208);
+    assert_eq!(emu.peek(DATA + 1), 6);
+}
+
+#[test]
+fn saves_into_the_middle_of_a_range_stop() {
+    let mut emu = watching(DATA + 1..=DATA + 1, WatchKind::Write);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Watchpoint {
+            pc: 0x206,
+            address: DATA + 1,
+            access: Access::Write,
+            old: 0,
+            new: 6
+        }
+    );
+}
+
+#[test]
+fn change_watchpoints_ignore_unchanged_writes() {
+    let mut emu = watching(DATA..=DATA + 1, WatchKind::Change);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Watchpoint {
+            pc: 0x206,
+            address: DATA,
+            access: Access::Write,
+            old: 0,
+            new: 5
+        }
+    );
+    // Later passes store the same values again
+    emu.pause_or_resume();
+    for _ in 0..50 {
+        emu.tick().unwrap();
+    }
+    assert!(!emu.is_paused());
+
+    // While plain write watchpoints stop on them
+    emu.debugger_mut().add_watchpoint(Watchpoint {
+        range: DATA..=DATA,
+        kind: WatchKind::Write,
+    });
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Watchpoint {
+            pc: 0x206,
+            address: DATA,
+            access: Access::Write,
+            old: 5,
+            new: 5
+        }
+    );
+}
+
+#[test]
+fn read_watchpoints_stop_on_loads() {
+    let mut emu = watching(DATA..=DATA + 1, WatchKind::Read);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Watchpoint {
+            pc: 0x20C,
+            address: DATA,
+            access: Access::Read,
+            old: 5,
+            new: 5
+        }
+    );
+    assert_eq!(emu.counter, 0x20E);
+}
+
+#[test]
+fn read_watchpoints_stop_on_fetches_inside_the_range() {
+    // Only the second byte of `v2 := 7` is watched
+    let mut emu = watching(0x209..=0x20A, WatchKind::Read);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Watchpoint {
+            pc: 0x208,
+            address: 0x209,
+            access: Access::Fetch,
+            old: 0x07,
+            new: 0x07
+        }
+    );
+    assert_eq!(emu.counter, 0x20A);
+    assert_eq!(emu.cpu_state().v[2], 7);
+
+    // Write watchpoints leave fetches alone
+    let mut emu = watching(0x200..=0x20F, WatchKind::Write);
+    for _ in 0..50 {
+        emu.tick().unwrap();
+    }
+    assert!(!emu.is_paused());
+}
+
+#[test]
+fn resuming_after_a_watchpoint_runs_on() {
+    let mut emu = watching(DATA..=DATA, WatchKind::Read);
+    run_to_stop(&mut emu, 10);
+    emu.pause_or_resume();
+    emu.tick().unwrap();
+    assert_eq!(emu.counter, 0x200);
+    assert_eq!(
+        run_to_stop(&mut emu, 10),
+        StopReason::Watchpoint {
+            pc: 0x20C,
+            address: DATA,
+            access: Access::Read,
+            old: 5,
+            new: 5
+        }
+    );
+}
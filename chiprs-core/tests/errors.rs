@@ -0,0 +1,90 @@
+//! Checks every `EmulatorError` is raised where it should be and freezes the
+//! emulator on the faulting instruction.
+
+use chiprs_core::error::EmulatorError;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+
+// Runs until the first error
+fn fault(rom: &[u8]) -> (Emulator, EmulatorError) {
+    let mut emu = Emulator::new(Quirks::XO_CHIP);
+    emu.load(rom).unwrap();
+    for _ in 0..100 {
+        if let Err(e) = emu.next() {
+            return (emu, e);
+        }
+    }
+    panic!("no fault");
+}
+
+#[test]
+fn unknown_opcode() {
+    let (emu, e) = fault(&[0x60, 0x01, 0xFF, 0xFF]);
+    assert_eq!(
+        e,
+        EmulatorError::UnknownOpcode {
+            pc: 0x202,
+            opcode: 0xFFFF
+        }
+    );
+    assert_eq!(e.to_string(), "unknown opcode FFFF at 0202");
+    assert_eq!(emu.counter, 0x202);
+}
+
+#[test]
+fn stack_overflow() {
+    // Calls itself
+    let (emu, e) = fault(&[0x22, 0x00]);
+    assert_eq!(e, EmulatorError::StackOverflow { pc: 0x200 });
+    assert_eq!(emu.cpu_state().stack.len(), 16);
+}
+
+#[test]
+fn stack_underflow() {
+    let (_, e) = fault(&[0x00, 0xEE]);
+    assert_eq!(e, EmulatorError::StackUnderflow { pc: 0x200 });
+    assert_eq!(e.to_string(), "stack underflow at 0200");
+}
+
+#[test]
+fn memory_out_of_bounds() {
+    // I := long FFFF, save V0..V1
+    let (emu, e) = fault(&[0xF0, 0x00, 0xFF, 0xFF, 0xF1, 0x55]);
+    assert_eq!(
+        e,
+        EmulatorError::MemoryOutOfBounds {
+            pc: 0x204,
+            address: 0x10000
+        }
+    );
+    assert_eq!(emu.fault(), Some(&e));
+
+    // Reads fault the same way: I := long FFFF, draw two rows
+    let (_, e) = fault(&[0xF0, 0x00, 0xFF, 0xFF, 0xD0, 0x02]);
+    assert!(matches!(
+        e,
+        EmulatorError::MemoryOutOfBounds { pc: 0x204, .. }
+    ));
+}
+
+#[test]
+fn faults_stick_until_reset() {
+    let (mut emu, e) = fault(&[0x00, 0xEE]);
+    assert_eq!(emu.next(), Err(e.clone()));
+    // Running frames does nothing
+    assert!(emu.tick().is_ok());
+    assert_eq!((emu.counter, emu.fault()), (0x200, Some(&e)));
+    emu.reset();
+    assert_eq!(emu.fault(), None);
+}
+
+#[test]
+fn rom_too_large() {
+    let mut emu = Emulator::new(Quirks::default());
+    let max = 0x10000 - 0x200;
+    assert_eq!(
+        emu.load(&vec![0; max + 1]),
+        Err(EmulatorError::RomTooLarge { size: max + 1, max })
+    );
+    assert!(emu.load(&vec![0; max]).is_ok());
+}
@@ -0,0 +1,171 @@
+//! Checks parsing and evaluating breakpoint conditions and logpoint messages.
+
+use chiprs_core::asm::assemble;
+use chiprs_core::constants::{RAM_SIZE, V_SIZE};
+use chiprs_core::debugger::{Breakpoint, StopReason};
+use chiprs_core::expr::{Context, Expr, LogMessage, ParseError};
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+
+const V: [u8; V_SIZE] = [0, 1, 2, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF];
+
+fn eval(source: &str) -> i64 {
+    let mut ram = vec![0; RAM_SIZE];
+    ram[0x303] = 0xAB;
+    ram[RAM_SIZE - 1] = 0x42;
+    let context = Context {
+        pc: 0x208,
+        i: 0x301,
+        v: &V,
+        delay_timer: 0,
+        sound_timer: 3,
+        stack_ptr: 1,
+        ram: &ram,
+        hits: 6,
+    };
+    Expr::parse(source).unwrap().eval(&context)
+}
+
+fn parse_error(source: &str) -> (usize, String) {
+    let ParseError { column, message } = Expr::parse(source).unwrap_err();
+    (column, message)
+}
+
+#[test]
+fn examples_evaluate() {
+    assert_eq!(eval("v3 == 0x10 && i > 0x300"), 1);
+    assert_eq!(eval("dt == 0"), 1);
+    assert_eq!(eval("mem[i+2] != 0"), 1);
+    assert_eq!(eval("hits > 5"), 1);
+    assert_eq!(eval("v3 == 0x10 && i > 0x301"), 0);
+}
+
+#[test]
+fn variables_and_numbers_read() {
+    assert_eq!(eval("pc"), 0x208);
+    assert_eq!(eval("st + sp"), 4);
+    assert_eq!(eval("VF"), 0xFF);
+    assert_eq!(eval("0b101 + 0X1f + 10"), 46);
+    assert_eq!(eval("mem[i + 2]"), 0xAB);
+    // Addresses wrap around memory
+    assert_eq!(eval("mem[-1]"), 0x42);
+}
+
+#[test]
+fn operators_follow_c_precedence() {
+    assert_eq!(eval("1 + 2 * 3"), 7);
+    assert_eq!(eval("(1 + 2) * 3"), 9);
+    assert_eq!(eval("1 | 2 ^ 3 & 1"), 3);
+    assert_eq!(eval("1 << 2 + 1"), 8);
+    assert_eq!(eval("1 < 2 == 1"), 1);
+    assert_eq!(eval("0 && 1 || 1"), 1);
+    assert_eq!(eval("1 || 0 && 0"), 1);
+    assert_eq!(eval("!0 + 1"), 2);
+    assert_eq!(eval("-2 * 3"), -6);
+    assert_eq!(eval("~0"), -1);
+    assert_eq!(eval("!!5"), 1);
+}
+
+#[test]
+fn binary_operators_are_left_associative() {
+    assert_eq!(eval("10 - 3 - 2"), 5);
+    assert_eq!(eval("64 / 4 / 2"), 8);
+    assert_eq!(eval("100 % 7 % 3"), 2);
+    assert_eq!(eval("256 >> 2 >> 1"), 32);
+}
+
+#[test]
+fn dividing_by_zero_gives_zero() {
+    assert_eq!(eval("v1 / v0"), 0);
+    assert_eq!(eval("v1 % v0"), 0);
+}
+
+#[test]
+fn parse_errors_point_at_the_column() {
+    let expected_operand = "expected a number, variable or '('".to_string();
+    assert_eq!(parse_error("v3 =="), (6, expected_operand.clone()));
+    assert_eq!(parse_error(""), (1, expected_operand));
+    assert_eq!(
+        parse_error("v3 == 0x1g"),
+        (7, "invalid number '0x1g'".to_string())
+    );
+    assert_eq!(parse_error("vg"), (1, "unknown variable 'vg'".to_string()));
+    assert_eq!(parse_error("(1 + 2"), (7, "expected ')'".to_string()));
+    assert_eq!(parse_error("mem[1"), (6, "expected ']'".to_string()));
+    assert_eq!(parse_error("1 2"), (3, "unexpected input".to_string()));
+    assert_eq!(
+        Expr::parse("v0 == 1 == x").unwrap_err().to_string(),
+        "unknown variable 'x' at column 12"
+    );
+}
+
+#[test]
+fn log_messages_format_values() {
+    let ram = vec![0; RAM_SIZE];
+    let context = Context {
+        pc: 0x208,
+        i: 0x301,
+        v: &V,
+        delay_timer: 0,
+        sound_timer: 0,
+        stack_ptr: 0,
+        ram: &ram,
+        hits: 2,
+    };
+    let message = LogMessage::parse("v3={v3} i={i:x} {{hit {hits}}}").unwrap();
+    assert_eq!(message.format(&context), "v3=16 i=301 {hit 2}");
+
+    let error = |source| {
+        let ParseError { column, message } = LogMessage::parse(source).unwrap_err();
+        (column, message)
+    };
+    assert_eq!(error("a {v0"), (3, "unclosed '{'".to_string()));
+    assert_eq!(error("a }"), (3, "unmatched '}'".to_string()));
+    // Columns inside a placeholder count from the start of the message
+    assert_eq!(
+        error("x {v0 +}"),
+        (8, "expected a number, variable or '('".to_string())
+    );
+}
+
+// Counts v0 up forever
+const LOOP: &str = "\
+: main
+  v0 += 1
+  jump main
+";
+
+fn looping(breakpoint: Breakpoint) -> Emulator {
+    let program = assemble(LOOP).unwrap();
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&program.rom).unwrap();
+    emu.debugger_mut().set_breakpoint(0x200, breakpoint);
+    emu
+}
+
+#[test]
+fn hits_count_every_pass_including_this_one() {
+    let mut emu = looping(Breakpoint::new(Some("hits == 3"), None).unwrap());
+    let stop = (0..20).find_map(|_| emu.tick().err());
+    assert_eq!(stop, Some(StopReason::Breakpoint { pc: 0x200 }));
+    assert_eq!(emu.cpu_state().v[0], 2);
+    assert_eq!(emu.debugger().breakpoint(0x200).unwrap().hits(), 3);
+
+    // Failing passes count too, the one resumed from was counted already
+    emu.pause_or_resume();
+    for _ in 0..6 {
+        emu.tick().unwrap();
+    }
+    assert_eq!(emu.debugger().breakpoint(0x200).unwrap().hits(), 5);
+}
+
+#[test]
+fn logpoints_log_instead_of_stopping() {
+    let mut emu = looping(Breakpoint::new(Some("v0 % 2 == 0"), Some("v0={v0}")).unwrap());
+    for _ in 0..10 {
+        emu.tick().unwrap();
+    }
+    assert!(!emu.is_paused());
+    assert_eq!(emu.debugger_mut().take_logs(), ["v0=0", "v0=2", "v0=4"]);
+    assert!(emu.debugger_mut().take_logs().is_empty());
+}
@@ -0,0 +1,165 @@
+//! Drives the GDB server from a scripted client over loopback, while the test
+//! thread runs frames like a frontend would.
+
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::gdb::GdbServer;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+// 200: V0 = 5, 202: I = 300, 204: V0 += 1, 206: jump 204
+const ROM: [u8; 8] = [0x60, 0x05, 0xA3, 0x00, 0x70, 0x01, 0x12, 0x04];
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn connect(address: SocketAddr) -> Self {
+        let stream = TcpStream::connect(address).unwrap();
+        stream.set_nodelay(true).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        Self { stream }
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    fn send_raw(&mut self, data: &[u8]) {
+        self.stream.write_all(data).unwrap();
+    }
+
+    fn send(&mut self, data: &str) -> u8 {
+        let checksum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        self.send_raw(format!("${}#{:02x}", data, checksum).as_bytes());
+        self.read_byte()
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut data = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                byte => data.push(byte),
+            }
+        }
+        let digits = [self.read_byte(), self.read_byte()];
+        let checksum = u8::from_str_radix(std::str::from_utf8(&digits).unwrap(), 16).unwrap();
+        assert_eq!(
+            checksum,
+            data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+        );
+        self.send_raw(b"+");
+        String::from_utf8(data).unwrap()
+    }
+
+    fn command(&mut self, data: &str) -> String {
+        assert_eq!(self.send(data), b'+', "{} wasn't acknowledged", data);
+        self.reply()
+    }
+}
+
+fn script(mut client: Client) {
+    assert_eq!(client.command("?"), "S05");
+    assert!(client.command("qSupported:swbreak+").contains("PacketSize"));
+    assert!(client
+        .command("qXfer:features:read:target.xml:0,1000")
+        .starts_with("l<?xml"));
+
+    let registers = client.command("g");
+    assert_eq!(registers.len(), 2 * (16 + 2 + 2 + 3));
+    assert_eq!(&registers[32..40], "00000002");
+
+    assert_eq!(client.command("s"), "S05");
+    assert_eq!(client.command("p0"), "05");
+    assert_eq!(client.command("s"), "S05");
+    assert_eq!(client.command("p10"), "0003");
+    assert_eq!(client.command("p11"), "0402");
+    assert_eq!(client.command("p15"), "E01");
+
+    assert_eq!(client.command("M300,3:abcdef"), "OK");
+    assert_eq!(client.command("m300,3"), "abcdef");
+    assert_eq!(client.command("mffff,2"), "E01");
+
+    assert_eq!(client.command("Z0,206,2"), "OK");
+    assert_eq!(client.command("c"), "S05");
+    assert_eq!(client.command("p11"), "0602");
+    assert_eq!(client.command("p0"), "06");
+    assert_eq!(client.command("c"), "S05");
+    assert_eq!(client.command("p0"), "07");
+    assert_eq!(client.command("z0,206,2"), "OK");
+
+    // The frontend's breakpoint outlives inserting and removing it again
+    assert_eq!(client.command("Z0,300,2"), "OK");
+    assert_eq!(client.command("z0,300,2"), "OK");
+    // Left for detaching to remove
+    assert_eq!(client.command("Z0,208,2"), "OK");
+
+    assert_eq!(client.send("c"), b'+');
+    thread::sleep(Duration::from_millis(50));
+    client.send_raw(&[0x03]);
+    assert_eq!(client.reply(), "S05");
+
+    client.send_raw(b"$g#00");
+    assert_eq!(client.read_byte(), b'-', "bad checksums should be rejected");
+    assert_eq!(client.command("vMustReplyEmpty"), "");
+    assert_eq!(client.command("D"), "OK");
+}
+
+#[test]
+fn scripted_session() {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&ROM).unwrap();
+    emu.debugger_mut().add_breakpoint(0x300);
+    // Stay at the first instruction until the client is connected
+    emu.pause_or_resume();
+    serve(&mut emu, script);
+
+    assert!(!emu.is_paused(), "detaching should resume the emulator");
+    assert_eq!(emu.debugger().breakpoints().collect::<Vec<_>>(), [0x300]);
+}
+
+// Runs frames and polls the server until `script` is done with its client
+fn serve(emu: &mut Emulator, script: fn(Client)) {
+    let mut server = GdbServer::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+
+    let client = thread::spawn(move || script(Client::connect(address)));
+    while !client.is_finished() {
+        server.poll(emu).unwrap();
+        let _ = emu.run_frame(TICKS_PER_FRAME);
+        thread::sleep(Duration::from_millis(1));
+    }
+    client.join().unwrap();
+
+    server.poll(emu).unwrap();
+    assert!(!server.is_connected());
+}
+
+#[test]
+fn memory_writes_forget_the_undo_history() {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&ROM).unwrap();
+    emu.pause_or_resume();
+    emu.next().unwrap();
+    emu.next().unwrap();
+    assert!(emu.can_step_back());
+
+    serve(&mut emu, |mut client| {
+        assert_eq!(client.command("M200,2:6007"), "OK");
+        client.send_raw(b"$k#6b");
+    });
+    assert_eq!(emu.peek(0x200), 0x60);
+    assert_eq!(emu.peek(0x201), 0x07);
+    // Stepping back would restore registers set by the old instructions
+    assert!(!emu.can_step_back());
+}
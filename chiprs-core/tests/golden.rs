@@ -0,0 +1,151 @@
+//! Runs the bundled test roms under every quirk preset and compares the final
+//! screen against the ASCII images in `tests/golden`.
+//!
+//! Set `CHIPRS_BLESS=1` to rewrite the images from the current output.
+
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::{ChipKey, Emulator};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Frames a scripted key stays down, FX0A only completes on release
+const HOLD_FRAMES: u64 = 4;
+
+/// Key presses as (frame, key) pairs, each held for `HOLD_FRAMES`.
+type Presses = &'static [(u64, u8)];
+
+fn run(rom: &[u8], quirks: Quirks, frames: u64, presses: Presses) -> String {
+    let mut emu = Emulator::new(quirks);
+    emu.load(rom).unwrap();
+
+    for frame in 0..frames {
+        for &(at, key) in presses {
+            let key = ChipKey::from_hex(key).unwrap();
+            if frame == at + HOLD_FRAMES {
+                emu.key_released(key);
+            } else if frame == at {
+                emu.key_pressed(key);
+            }
+        }
+        if let Err(e) = emu.run_frame(TICKS_PER_FRAME) {
+            panic!("faulted under {}: {}", quirks, e);
+        }
+    }
+
+    emu.get_screen().to_ascii()
+}
+
+/// Expected and actual rows next to each other, differing rows marked with `*`.
+fn side_by_side(expected: &str, actual: &str) -> String {
+    let width = expected.lines().map(str::len).max().unwrap_or(0);
+    let mut expected_rows = expected.lines();
+    let mut actual_rows = actual.lines();
+    let mut out = format!("{:width$}   {}\n", "expected", "actual");
+    loop {
+        let (left, right) = match (expected_rows.next(), actual_rows.next()) {
+            (None, None) => break,
+            (left, right) => (left.unwrap_or(""), right.unwrap_or("")),
+        };
+        let marker = if left == right { '|' } else { '*' };
+        out.push_str(&format!("{:width$} {} {}\n", left, marker, right));
+    }
+    out
+}
+
+fn check(name: &str, frames: u64, presses_for: fn(&str) -> Presses) {
+    check_presets(name, frames, presses_for, &Quirks::PRESETS);
+}
+
+fn check_presets(
+    name: &str,
+    frames: u64,
+    presses_for: fn(&str) -> Presses,
+    presets: &[(&str, Quirks)],
+) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("../roms").join(format!("{}.ch8", name))).unwrap();
+    let bless = env::var_os("CHIPRS_BLESS").is_some();
+
+    let mut failures = Vec::new();
+    for &(preset, quirks) in presets {
+        let actual = run(&rom, quirks, frames, presses_for(preset));
+        let golden = root
+            .join("tests/golden")
+            .join(format!("{}-{}.txt", name, preset));
+        if bless {
+            fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&golden)
+            .unwrap_or_else(|e| panic!("unable to read {}: {}", golden.display(), e));
+        if expected != actual {
+            failures.push(format!(
+                "{} under {} doesn't match {}\n{}",
+                name,
+                preset,
+                golden.display(),
+                side_by_side(&expected, &actual)
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+fn no_presses(_: &str) -> Presses {
+    &[]
+}
+
+#[test]
+fn chip8_logo() {
+    check("1-chip8-logo", 60, no_presses);
+}
+
+#[test]
+fn ibm_logo() {
+    check("2-ibm-logo", 60, no_presses);
+}
+
+#[test]
+fn corax() {
+    check("3-corax+", 200, no_presses);
+}
+
+#[test]
+fn flags() {
+    check("4-flags", 200, no_presses);
+}
+
+#[test]
+fn quirks() {
+    // The rom's menu has no CHIP-48 entry and its SUPER-CHIP memory test
+    // expects I to stay put, which CHIP-48's I += X fails, see
+    // `Quirks::CHIP48`. The CHIP-48 quirks are covered by tests/quirks.rs.
+    let presets = Quirks::PRESETS
+        .iter()
+        .filter(|(preset, _)| *preset != "chip48")
+        .copied()
+        .collect::<Vec<_>>();
+
+    // Pick the platform matching the preset from the menu, SUPER-CHIP asks
+    // for modern or legacy behaviour next
+    check_presets(
+        "5-quirks",
+        1000,
+        |preset| match preset {
+            "chip8" => &[(100, 1)],
+            "xochip" => &[(100, 3)],
+            _ => &[(100, 2), (300, 1)],
+        },
+        &presets,
+    );
+}
+
+#[test]
+fn keypad() {
+    // FX0A test, answered with key A
+    check("6-keypad", 400, |_| &[(100, 3), (200, 0xA)]);
+}
@@ -0,0 +1,66 @@
+//! Checks reading and writing memory from outside the program.
+
+use chiprs_core::debugger::{WatchKind, Watchpoint};
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+
+// 200: V0 = 1, 202: jump 200
+const ROM: [u8; 4] = [0x60, 0x01, 0x12, 0x00];
+
+fn load() -> Emulator {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&ROM).unwrap();
+    emu
+}
+
+#[test]
+fn peek_reads_the_rom_and_fonts() {
+    let emu = load();
+    assert_eq!(emu.peek(0x200), 0x60);
+    assert_eq!(emu.peek(0x203), 0x00);
+    assert_eq!(emu.peek(0x204), 0x00);
+    // The small font starts with the 0, at the start of memory
+    assert_eq!(emu.peek(0x000), 0xF0);
+    assert_eq!(emu.peek(0xFFFF), 0x00);
+}
+
+#[test]
+fn poke_writes_until_reset() {
+    let mut emu = load();
+    emu.poke(0x201, 0x07);
+    emu.poke(0xFFFF, 0xAB);
+    assert_eq!(emu.peek(0x201), 0x07);
+    assert_eq!(emu.peek(0xFFFF), 0xAB);
+    emu.next().unwrap();
+    assert_eq!(emu.cpu_state().v[0], 0x07);
+
+    emu.reset();
+    assert_eq!(emu.peek(0x201), 0x01);
+    assert_eq!(emu.peek(0xFFFF), 0x00);
+}
+
+#[test]
+fn poke_triggers_no_watchpoints() {
+    let mut emu = load();
+    emu.debugger_mut().add_watchpoint(Watchpoint {
+        range: 0x300..=0x300,
+        kind: WatchKind::Write,
+    });
+    emu.poke(0x300, 1);
+    for _ in 0..10 {
+        emu.tick().unwrap();
+    }
+    assert!(!emu.is_paused());
+    assert!(emu.debugger().last_stop().is_none());
+}
+
+#[test]
+fn poke_forgets_the_undo_history() {
+    let mut emu = load();
+    emu.pause_or_resume();
+    emu.next().unwrap();
+    assert!(emu.can_step_back());
+    emu.poke(0x300, 1);
+    // Undoing would restore state from before the write
+    assert!(!emu.can_step_back());
+}
@@ -0,0 +1,68 @@
+//! Checks the cycle counts `Profile` derives from small hand-assembled programs.
+
+use chiprs_core::profile::Subroutine;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::symbols::SymbolMap;
+use chiprs_core::Emulator;
+
+fn profile(rom: &[u8], cycles: usize, symbols: &SymbolMap) -> (Emulator, String) {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(rom).unwrap();
+    emu.start_profiling();
+    for _ in 0..cycles {
+        emu.next().unwrap();
+    }
+    let folded = emu.profile().unwrap().folded(symbols);
+    (emu, folded)
+}
+
+// 0200: call 0206, call 020A, then loop
+// 0206: call 020A, ret
+// 020A: v0 += 1, ret
+const PROGRAM: [u8; 14] = [
+    0x22, 0x06, 0x22, 0x0A, 0x12, 0x04, 0x22, 0x0A, 0x00, 0xEE, 0x70, 0x01, 0x00, 0xEE,
+];
+
+#[test]
+fn subroutines_get_inclusive_and_exclusive_cycles() {
+    let (emu, _) = profile(&PROGRAM, 12, &SymbolMap::default());
+    let profile = emu.profile().unwrap();
+    assert_eq!(profile.total(), 12);
+    assert_eq!(profile.count(0x020A), 2);
+    assert_eq!(profile.count(0x0204), 4);
+
+    let subroutines = profile.subroutines();
+    let stats = |calls, inclusive, exclusive| Subroutine {
+        calls,
+        inclusive,
+        exclusive,
+    };
+    assert_eq!(subroutines[&0x0200], stats(1, 12, 6));
+    assert_eq!(subroutines[&0x0206], stats(1, 4, 2));
+    assert_eq!(subroutines[&0x020A], stats(2, 4, 4));
+}
+
+#[test]
+fn folded_stacks_use_labels() {
+    let mut symbols = SymbolMap::default();
+    symbols.insert_label(0x0200, "main");
+    symbols.insert_label(0x0206, "outer");
+    symbols.insert_label(0x020A, "inner");
+    let (_, folded) = profile(&PROGRAM, 12, &symbols);
+    assert_eq!(
+        folded,
+        "main 6\nmain;inner 2\nmain;outer 2\nmain;outer;inner 2\n"
+    );
+}
+
+#[test]
+fn recursion_counts_once() {
+    // 0200: call 0202, 0202: v0 += 1, if v0 != 3 then call 0202, ret
+    let rom = [0x22, 0x02, 0x70, 0x01, 0x30, 0x03, 0x22, 0x02, 0x00, 0xEE];
+    let (emu, folded) = profile(&rom, 11, &SymbolMap::default());
+    let subroutines = emu.profile().unwrap().subroutines();
+    assert_eq!(subroutines[&0x0202].calls, 3);
+    assert_eq!(subroutines[&0x0202].inclusive, 10);
+    assert_eq!(subroutines[&0x0202].exclusive, 10);
+    assert!(folded.contains("0200;0202;0202;0202 "));
+}
@@ -0,0 +1,94 @@
+//! Runs small hand-assembled programs under each preset, pinning what every
+//! quirk switch changes.
+
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+
+fn run(rom: &[u8], quirks: Quirks, cycles: usize) -> Emulator {
+    let mut emu = Emulator::new(quirks);
+    emu.load(rom).unwrap();
+    for _ in 0..cycles {
+        emu.next().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn shift_source() {
+    // V0 := 05, V1 := 03, V0 := V1 >> 1 or V0 >> 1
+    let rom = [0x60, 0x05, 0x61, 0x03, 0x80, 0x16];
+    let v = run(&rom, Quirks::CHIP8, 3).cpu_state().v;
+    assert_eq!((v[0], v[0xF]), (1, 1));
+    let v = run(&rom, Quirks::CHIP48, 3).cpu_state().v;
+    assert_eq!((v[0], v[0xF]), (2, 1));
+
+    // V0 := 05, V1 := 81, V0 := V1 << 1 or V0 << 1
+    let rom = [0x60, 0x05, 0x61, 0x81, 0x80, 0x1E];
+    let v = run(&rom, Quirks::XO_CHIP, 3).cpu_state().v;
+    assert_eq!((v[0], v[0xF]), (0x02, 1));
+    let v = run(&rom, Quirks::SUPER_CHIP, 3).cpu_state().v;
+    assert_eq!((v[0], v[0xF]), (0x0A, 0));
+}
+
+#[test]
+fn load_store_increment() {
+    // I := 300, save V0..V2, load V0..V2
+    let rom = [0xA3, 0x00, 0xF2, 0x55, 0xF2, 0x65];
+    let i = |quirks| run(&rom, quirks, 3).cpu_state().i;
+    assert_eq!(i(Quirks::CHIP8), 0x306);
+    assert_eq!(i(Quirks::CHIP48), 0x304);
+    assert_eq!(i(Quirks::SUPER_CHIP), 0x300);
+    assert_eq!(i(Quirks::XO_CHIP), 0x306);
+}
+
+#[test]
+fn logic_resets_vf() {
+    // VF := 05, V0 |= V1, then the same for &= and ^=
+    for opcode in [0x01, 0x02, 0x03] {
+        let rom = [0x6F, 0x05, 0x80, 0x10 | opcode];
+        assert_eq!(run(&rom, Quirks::CHIP8, 2).cpu_state().v[0xF], 0);
+        assert_eq!(run(&rom, Quirks::SUPER_CHIP, 2).cpu_state().v[0xF], 5);
+    }
+}
+
+#[test]
+fn clip_sprites() {
+    // V0 := 3E, I := font "0" (F0 top row), draw one row at V0,V1
+    let rom = [0x60, 0x3E, 0xA0, 0x00, 0xD0, 0x11];
+    let top_row = |quirks| {
+        let emu = run(&rom, quirks, 3);
+        emu.get_screen()
+            .to_ascii()
+            .lines()
+            .next()
+            .unwrap()
+            .to_string()
+    };
+    assert!(top_row(Quirks::CHIP8).starts_with("........"));
+    assert!(top_row(Quirks::CHIP8).ends_with("##"));
+    assert!(top_row(Quirks::XO_CHIP).starts_with("##......"));
+    assert!(top_row(Quirks::XO_CHIP).ends_with("##"));
+}
+
+#[test]
+fn jump_uses_vx() {
+    // V0 := 03, V2 := 05, jump to 210 + V0 or 210 + V2
+    let rom = [0x60, 0x03, 0x62, 0x05, 0xB2, 0x10];
+    assert_eq!(run(&rom, Quirks::CHIP8, 3).counter, 0x213);
+    assert_eq!(run(&rom, Quirks::CHIP48, 3).counter, 0x215);
+    assert_eq!(run(&rom, Quirks::SUPER_CHIP, 3).counter, 0x215);
+    assert_eq!(run(&rom, Quirks::XO_CHIP, 3).counter, 0x213);
+}
+
+#[test]
+fn display_wait() {
+    // Draw one row, V1 := 07
+    let rom = [0xD0, 0x01, 0x61, 0x07];
+    let mut emu = run(&rom, Quirks::CHIP8, 2);
+    assert_eq!(emu.cpu_state().v[1], 0);
+    emu.tick_timers();
+    emu.next().unwrap();
+    assert_eq!(emu.cpu_state().v[1], 7);
+
+    assert_eq!(run(&rom, Quirks::XO_CHIP, 2).cpu_state().v[1], 7);
+}
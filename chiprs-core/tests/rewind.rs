@@ -0,0 +1,88 @@
+//! Records a rom switching between resolutions, so the snapshots change
+//! length, and rewinds it checking every snapshot comes back bit for bit.
+
+use chiprs_core::quirks::Quirks;
+use chiprs_core::rewind::RewindBuffer;
+use chiprs_core::state::StateError;
+use chiprs_core::Emulator;
+
+// high, draw, V0 += 1, low, draw, V1 += 1, loop
+const ROM: [u8; 14] = [
+    0x00, 0xFF, 0xD0, 0x15, 0x70, 0x01, 0x00, 0xFE, 0xD0, 0x15, 0x71, 0x01, 0x12, 0x00,
+];
+
+// Snapshots every instruction, returns the states recorded oldest first
+fn record(emu: &mut Emulator, rewind: &mut RewindBuffer, steps: usize) -> Vec<Vec<u8>> {
+    let mut states = Vec::new();
+    for _ in 0..steps {
+        emu.next().unwrap();
+        rewind.record(emu);
+        states.push(emu.save_state());
+    }
+    states
+}
+
+#[test]
+fn snapshots_rewind_bit_for_bit() {
+    let mut emu = Emulator::new(Quirks::SUPER_CHIP);
+    emu.load(&ROM).unwrap();
+    let mut rewind = RewindBuffer::new(1, usize::MAX);
+    let states = record(&mut emu, &mut rewind, 50);
+
+    // Every other step goes from a 128x64 to a 64x32 screen, or back
+    for expected in states.iter().rev().skip(1) {
+        assert_eq!(rewind.step_back(&mut emu), Ok(true));
+        assert!(emu.save_state() == *expected);
+    }
+    assert_eq!(rewind.step_back(&mut emu), Ok(false));
+}
+
+#[test]
+fn history_fits_the_limit() {
+    let mut emu = Emulator::new(Quirks::SUPER_CHIP);
+    emu.load(&ROM).unwrap();
+    // The newest snapshot and a few small deltas
+    let limit = emu.save_state().len() + 1000;
+    let mut rewind = RewindBuffer::new(1, limit);
+    let states = record(&mut emu, &mut rewind, 200);
+    assert!(rewind.size() <= limit);
+
+    let mut steps = 0;
+    while rewind.step_back(&mut emu).unwrap() {
+        steps += 1;
+    }
+    assert!(steps > 0 && steps < states.len() - 1);
+    assert!(emu.save_state() == states[states.len() - 1 - steps]);
+}
+
+#[test]
+fn rewind_steps_back_every_interval() {
+    let mut emu = Emulator::new(Quirks::SUPER_CHIP);
+    emu.load(&ROM).unwrap();
+    let mut rewind = RewindBuffer::new(4, usize::MAX);
+    // Snapshots after the 4th and 8th instructions
+    let states = record(&mut emu, &mut rewind, 8);
+
+    for _ in 0..3 {
+        assert_eq!(rewind.rewind(&mut emu), Ok(true));
+        assert!(emu.save_state() == states[7]);
+    }
+    assert_eq!(rewind.rewind(&mut emu), Ok(true));
+    assert!(emu.save_state() == states[3]);
+}
+
+#[test]
+fn another_rom_drops_the_history() {
+    let mut emu = Emulator::new(Quirks::SUPER_CHIP);
+    emu.load(&ROM).unwrap();
+    let mut rewind = RewindBuffer::new(1, usize::MAX);
+    record(&mut emu, &mut rewind, 10);
+
+    emu.load(&[0x12, 0x00]).unwrap();
+    assert!(matches!(
+        rewind.step_back(&mut emu),
+        Err(StateError::RomMismatch { .. })
+    ));
+    assert_eq!(rewind.size(), 0);
+    assert_eq!(rewind.step_back(&mut emu), Ok(false));
+}
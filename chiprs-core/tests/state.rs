@@ -0,0 +1,133 @@
+//! Saves and restores the emulator, and checks every way a save state is
+//! rejected leaves it untouched.
+
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::state::{rom_hash, StateError};
+use chiprs_core::Emulator;
+use std::fs;
+use std::path::PathBuf;
+
+// Offset of the version after the magic, and of the stack pointer after the
+// header, pc, ram, registers, I and stack
+const VERSION_AT: usize = 4;
+const STACK_PTR_AT: usize = 14 + 2 + 0x10000 + 16 + 2 + 32;
+
+fn ibm_logo() -> Vec<u8> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    fs::read(root.join("../roms/2-ibm-logo.ch8")).unwrap()
+}
+
+fn running(rom: &[u8], frames: usize) -> Emulator {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(rom).unwrap();
+    for _ in 0..frames {
+        emu.run_frame(TICKS_PER_FRAME).unwrap();
+    }
+    emu
+}
+
+#[test]
+fn states_load_back() {
+    let mut emu = running(&ibm_logo(), 2);
+    let state = emu.save_state();
+    let screen = emu.get_screen().to_ascii();
+    let cpu = emu.cpu_state();
+
+    for _ in 0..20 {
+        emu.run_frame(TICKS_PER_FRAME).unwrap();
+    }
+    assert_ne!(emu.get_screen().to_ascii(), screen);
+
+    emu.load_state(&state).unwrap();
+    assert_eq!(emu.get_screen().to_ascii(), screen);
+    assert_eq!(emu.cpu_state(), cpu);
+    assert!(emu.save_state() == state);
+
+    // Into a fresh emulator with the same rom, which then runs on the same
+    let mut fresh = running(&ibm_logo(), 0);
+    fresh.load_state(&state).unwrap();
+    fresh.run_frame(TICKS_PER_FRAME).unwrap();
+    emu.run_frame(TICKS_PER_FRAME).unwrap();
+    assert!(fresh.save_state() == emu.save_state());
+}
+
+// Loads `state` expecting `error`, with the emulator as it was before
+fn check_rejected(emu: &mut Emulator, state: &[u8], error: StateError) {
+    let before = emu.save_state();
+    assert_eq!(emu.load_state(state), Err(error));
+    assert!(emu.save_state() == before);
+}
+
+#[test]
+fn bad_magic_is_rejected() {
+    let mut emu = running(&ibm_logo(), 1);
+    let mut state = emu.save_state();
+    state[0] = b'X';
+    check_rejected(&mut emu, &state, StateError::BadMagic);
+    check_rejected(&mut emu, b"", StateError::Truncated);
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let mut emu = running(&ibm_logo(), 1);
+    let mut state = emu.save_state();
+    state[VERSION_AT..VERSION_AT + 2].copy_from_slice(&99u16.to_le_bytes());
+    check_rejected(
+        &mut emu,
+        &state,
+        StateError::UnsupportedVersion { version: 99 },
+    );
+}
+
+#[test]
+fn other_roms_are_rejected() {
+    let state = running(&ibm_logo(), 1).save_state();
+    let other = [0x12, 0x00];
+    let mut emu = running(&other, 1);
+    check_rejected(
+        &mut emu,
+        &state,
+        StateError::RomMismatch {
+            expected: rom_hash(&other),
+            found: rom_hash(&ibm_logo()),
+        },
+    );
+}
+
+#[test]
+fn truncated_states_are_rejected() {
+    let mut emu = running(&ibm_logo(), 1);
+    let state = emu.save_state();
+    for len in [3, 10, STACK_PTR_AT, state.len() - 1] {
+        check_rejected(&mut emu, &state[..len], StateError::Truncated);
+    }
+}
+
+#[test]
+fn corrupted_states_are_rejected() {
+    let mut emu = running(&ibm_logo(), 1);
+    let state = emu.save_state();
+
+    let mut longer = state.clone();
+    longer.push(0);
+    check_rejected(&mut emu, &longer, StateError::Corrupted);
+
+    let mut deep_stack = state.clone();
+    deep_stack[STACK_PTR_AT] = 17;
+    check_rejected(&mut emu, &deep_stack, StateError::Corrupted);
+}
+
+#[test]
+fn version_1_states_are_rejected() {
+    // Version 1 predates the audio pattern and pitch, loading one would
+    // shift every field after the timers
+    let mut emu = running(&ibm_logo(), 1);
+    let mut state = emu.save_state();
+    state[VERSION_AT..VERSION_AT + 2].copy_from_slice(&1u16.to_le_bytes());
+    check_rejected(
+        &mut emu,
+        &state,
+        StateError::UnsupportedVersion { version: 1 },
+    );
+}
@@ -0,0 +1,116 @@
+//! Runs the SUPER-CHIP 1.1 instructions one at a time on small hand-assembled
+//! programs.
+
+use chiprs_core::fontset::{BIG_FONTSET, FONTSET_SIZE};
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+
+fn run(rom: &[u8], cycles: usize) -> Emulator {
+    let mut emu = Emulator::new(Quirks::SUPER_CHIP);
+    emu.load(rom).unwrap();
+    for _ in 0..cycles {
+        emu.next().unwrap();
+    }
+    emu
+}
+
+// (x, y) of every lit pixel
+fn lit(emu: &Emulator) -> Vec<(usize, usize)> {
+    let screen = emu.get_screen();
+    (0..screen.pixels().len())
+        .filter(|&idx| screen.pixels()[idx] != 0)
+        .map(|idx| (idx % screen.width(), idx / screen.width()))
+        .collect()
+}
+
+#[test]
+fn resolution_switches_and_clears() {
+    // high, draw font "0" at 0,0, low
+    let rom = [0x00, 0xFF, 0xD0, 0x05, 0x00, 0xFE];
+    let emu = run(&rom, 1);
+    assert!(emu.get_screen().is_hires());
+    assert_eq!(emu.get_screen().width(), 128);
+    assert_eq!(emu.get_screen().height(), 64);
+
+    let emu = run(&rom, 3);
+    assert!(!emu.get_screen().is_hires());
+    assert_eq!(emu.get_screen().pixels().len(), 64 * 32);
+    assert!(lit(&emu).is_empty());
+}
+
+#[test]
+fn scrolling_moves_the_screen() {
+    // high, draw the top row of font "0" at 0,0, scroll down 3, right 4, left 4
+    let rom = [0x00, 0xFF, 0xD0, 0x01, 0x00, 0xC3, 0x00, 0xFB, 0x00, 0xFC];
+    let row = |y| (0..4).map(|x| (x, y)).collect::<Vec<_>>();
+    assert_eq!(lit(&run(&rom, 2)), row(0));
+    assert_eq!(lit(&run(&rom, 3)), row(3));
+    let shifted = (4..8).map(|x| (x, 3)).collect::<Vec<_>>();
+    assert_eq!(lit(&run(&rom, 4)), shifted);
+    assert_eq!(lit(&run(&rom, 5)), row(3));
+
+    // Scrolled out of the screen is gone
+    let rom = [0x00, 0xFF, 0xD0, 0x01, 0x00, 0xFC];
+    assert!(lit(&run(&rom, 3)).is_empty());
+}
+
+#[test]
+fn big_sprites_are_16_by_16() {
+    // high, I := 208, draw 16x16 twice, then 32 bytes of FF
+    let mut rom = vec![0x00, 0xFF, 0xA2, 0x08, 0xD0, 0x00, 0xD0, 0x00];
+    rom.extend([0xFF; 32]);
+    let emu = run(&rom, 3);
+    let pixels = lit(&emu);
+    assert_eq!(pixels.len(), 256);
+    assert_eq!(pixels.last(), Some(&(15, 15)));
+    assert_eq!(emu.cpu_state().v[0xF], 0);
+
+    let emu = run(&rom, 4);
+    assert!(lit(&emu).is_empty());
+    assert_eq!(emu.cpu_state().v[0xF], 1);
+}
+
+#[test]
+fn big_font_follows_the_small_one() {
+    // V0 := 7, I := big digit V0
+    let emu = run(&[0x60, 0x07, 0xF0, 0x30], 2);
+    let i = emu.cpu_state().i;
+    assert_eq!(i as usize, FONTSET_SIZE + 70);
+    let digit = (0..10)
+        .map(|offset| emu.peek(i + offset))
+        .collect::<Vec<_>>();
+    assert_eq!(digit, BIG_FONTSET[70..80]);
+}
+
+#[test]
+fn flags_survive_a_reset() {
+    // V0..V2 := 1, 2, 3, save them to the flags, clear V0..V2, load V0..V1
+    let rom = [
+        0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xF2, 0x75, 0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF1,
+        0x85,
+    ];
+    let mut emu = run(&rom, 8);
+    assert_eq!(emu.cpu_state().v[..3], [1, 2, 0]);
+
+    // Only the flags are left after a reset, load all three first thing
+    emu.reset();
+    emu.poke(0x200, 0xF2);
+    emu.poke(0x201, 0x85);
+    emu.next().unwrap();
+    assert_eq!(emu.cpu_state().v[..3], [1, 2, 3]);
+}
+
+#[test]
+fn exit_stops_the_rom() {
+    // exit, V0 := 5
+    let mut emu = run(&[0x00, 0xFD, 0x60, 0x05], 1);
+    assert!(emu.has_exited());
+    emu.next().unwrap();
+    emu.tick_timers();
+    emu.next().unwrap();
+    assert_eq!(emu.cpu_state().v[0], 0);
+    assert_eq!(emu.counter, 0x202);
+
+    emu.reset();
+    assert!(!emu.has_exited());
+}
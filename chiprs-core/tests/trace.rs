@@ -0,0 +1,177 @@
+//! Checks the lines `Tracer` writes for small hand-assembled programs.
+
+use chiprs_core::instruction::InstructionClass;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::trace::{TraceFormat, Tracer};
+use chiprs_core::Emulator;
+use std::io::{self, BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+// The tracer owns its output, the test keeps a handle to read it back
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedOutput {
+    fn lines(&self) -> Vec<String> {
+        let bytes = self.0.lock().unwrap();
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+fn run(rom: &[u8], cycles: usize, setup: impl FnOnce(&mut Tracer)) -> Vec<String> {
+    run_with_format(rom, cycles, TraceFormat::Text, setup)
+}
+
+fn run_with_format(
+    rom: &[u8],
+    cycles: usize,
+    format: TraceFormat,
+    setup: impl FnOnce(&mut Tracer),
+) -> Vec<String> {
+    let output = SharedOutput::default();
+    let mut tracer = Tracer::new(Box::new(output.clone()), format);
+    setup(&mut tracer);
+
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(rom).unwrap();
+    emu.set_tracer(tracer);
+    for _ in 0..cycles {
+        if emu.next().is_err() {
+            break;
+        }
+    }
+    output.lines()
+}
+
+// V0 := 0C, I := 22A, V0 += 09, then loop
+const PROGRAM: [u8; 8] = [0x60, 0x0C, 0xA2, 0x2A, 0x70, 0x09, 0x12, 0x06];
+
+#[test]
+fn text_lines_hold_the_changed_registers() {
+    let lines = run(&PROGRAM, 4, |_| {});
+    assert_eq!(
+        lines,
+        [
+            "       0 0200 600C: SetVxNN { x: 0, nn: 0C } V0=0->C",
+            "       1 0202 A22A: SetI { nnn: 22A } I=0->22A",
+            "       2 0204 7009: AddVxNN { x: 0, nn: 09 } V0=C->15",
+            "       3 0206 1206: Jump { nnn: 206 }",
+        ]
+    );
+}
+
+#[test]
+fn jsonl_lines_hold_the_opcode_and_changes() {
+    let lines = run_with_format(&PROGRAM, 3, TraceFormat::Jsonl, |_| {});
+    assert_eq!(
+        lines[2],
+        r#"{"cycle":2,"pc":516,"opcode":"7009","instruction":"AddVxNN { x: 0, nn: 09 }","changes":{"V0":[12,21]}}"#
+    );
+}
+
+#[test]
+fn filters_keep_the_cycle_count() {
+    let lines = run(&PROGRAM, 6, |tracer| {
+        tracer.add_range(0x0202..=0x0206);
+        tracer.add_class(InstructionClass::Flow);
+    });
+    assert_eq!(
+        lines,
+        [
+            "       3 0206 1206: Jump { nnn: 206 }",
+            "       4 0206 1206: Jump { nnn: 206 }",
+            "       5 0206 1206: Jump { nnn: 206 }",
+        ]
+    );
+}
+
+#[test]
+fn ring_is_written_on_a_fault() {
+    // V0 := 01, then call itself until the stack overflows
+    let rom = [0x60, 0x01, 0x22, 0x02];
+    let lines = run(&rom, 100, |tracer| tracer.set_ring_size(2));
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("0202 2202: Call { nnn: 202 } SP=F->10"));
+    assert!(lines[1].ends_with("0202 2202: Call { nnn: 202 }"));
+
+    let lines = run(&PROGRAM, 100, |tracer| tracer.set_ring_size(2));
+    assert!(lines.is_empty());
+}
+
+// Takes `lines` lines, then fails every write
+struct FailingOutput {
+    lines: usize,
+    attempts: Arc<Mutex<usize>>,
+}
+
+impl Write for FailingOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        *self.attempts.lock().unwrap() += 1;
+        if self.lines == 0 {
+            return Err(io::Error::other("disk full"));
+        }
+        self.lines -= buf.iter().filter(|&&b| b == b'\n').count().min(self.lines);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_errors_stop_tracing_and_are_kept() {
+    let attempts = Arc::new(Mutex::new(0));
+    let output = FailingOutput {
+        lines: 2,
+        attempts: attempts.clone(),
+    };
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&PROGRAM).unwrap();
+    emu.set_tracer(Tracer::new(Box::new(output), TraceFormat::Text));
+    for _ in 0..2 {
+        emu.next().unwrap();
+    }
+    assert!(emu.tracer().unwrap().error().is_none());
+
+    for _ in 0..4 {
+        emu.next().unwrap();
+    }
+    let error = emu.tracer().unwrap().error().unwrap();
+    assert_eq!(error.to_string(), "disk full");
+    // Nothing more is written after the first failure
+    let after_failing = *attempts.lock().unwrap();
+    emu.next().unwrap();
+    assert_eq!(*attempts.lock().unwrap(), after_failing);
+
+    // Failing only when the buffered end is written at exit
+    let output = FailingOutput {
+        lines: 0,
+        attempts: Arc::new(Mutex::new(0)),
+    };
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&PROGRAM).unwrap();
+    let output = BufWriter::new(output);
+    emu.set_tracer(Tracer::new(Box::new(output), TraceFormat::Text));
+    for _ in 0..2 {
+        emu.next().unwrap();
+    }
+    // Still in the buffer
+    assert!(emu.tracer().unwrap().error().is_none());
+
+    let error = emu.finish_trace().unwrap();
+    assert_eq!(error.to_string(), "disk full");
+}
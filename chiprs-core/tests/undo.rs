@@ -0,0 +1,124 @@
+//! Steps the bundled roms forwards, all the way back and forwards again,
+//! checking every state is restored bit for bit.
+
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+use std::fs;
+use std::path::PathBuf;
+
+const STEPS: usize = 600;
+
+fn load(name: &str, quirks: Quirks) -> Emulator {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let rom = fs::read(root.join("../roms").join(name)).unwrap();
+    let mut emu = Emulator::new(quirks);
+    emu.load(&rom).unwrap();
+    emu
+}
+
+fn check_round_trip(name: &str, quirks: Quirks) {
+    let mut emu = load(name, quirks);
+    let start = emu.save_state();
+    emu.pause_or_resume();
+    for step in 1..=STEPS {
+        emu.next().unwrap();
+        // Timer ticks in between, as when running into a breakpoint
+        if step % TICKS_PER_FRAME == 0 {
+            emu.tick_timers();
+        }
+    }
+
+    let mut states = vec![emu.save_state()];
+    while emu.step_back() {
+        states.push(emu.save_state());
+    }
+    assert!(
+        *states.last().unwrap() == start,
+        "{}: stepping back to the start differs",
+        name
+    );
+
+    for (step, expected) in states.iter().rev().enumerate().skip(1) {
+        emu.next().unwrap();
+        assert!(
+            emu.save_state() == *expected,
+            "{}: stepping forward to {} differs",
+            name,
+            step
+        );
+    }
+}
+
+#[test]
+fn roms_step_back_and_forward_identically() {
+    for entry in fs::read_dir(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../roms")).unwrap() {
+        let name = entry.unwrap().file_name().into_string().unwrap();
+        if name.ends_with(".ch8") {
+            check_round_trip(&name, Quirks::default());
+            check_round_trip(&name, "xochip".parse().unwrap());
+        }
+    }
+}
+
+#[test]
+fn random_values_are_drawn_again() {
+    // V0 := random 0xFF, four times
+    let rom = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0xC3, 0xFF];
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&rom).unwrap();
+    emu.pause_or_resume();
+    (0..4).for_each(|_| emu.next().unwrap());
+    let expected = emu.cpu_state().v;
+
+    (0..4).for_each(|_| assert!(emu.step_back()));
+    assert_eq!(emu.cpu_state().v, [0; 16]);
+    (0..4).for_each(|_| emu.next().unwrap());
+    assert_eq!(emu.cpu_state().v, expected);
+}
+
+#[test]
+fn running_without_breakpoints_forgets_the_history() {
+    let mut emu = load("2-ibm-logo.ch8", Quirks::default());
+    emu.pause_or_resume();
+    emu.next().unwrap();
+    assert!(emu.can_step_back());
+
+    emu.pause_or_resume();
+    emu.run_frame(TICKS_PER_FRAME).unwrap();
+    assert!(!emu.can_step_back());
+}
+
+#[test]
+fn stepping_back_clears_a_fault() {
+    // Calls itself until the stack overflows
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&[0x22, 0x00]).unwrap();
+    emu.pause_or_resume();
+    while emu.next().is_ok() {}
+    assert!(emu.fault().is_some());
+
+    assert!(emu.step_back());
+    assert!(emu.fault().is_none());
+    assert_eq!(emu.cpu_state().stack.len(), 16);
+    assert_eq!(emu.counter, 0x200);
+}
+
+#[test]
+fn timers_hold_while_paused_only_with_history() {
+    // delay := 30, then loops
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load(&[0x60, 0x1E, 0xF0, 0x15, 0x12, 0x04]).unwrap();
+    emu.next().unwrap();
+    emu.next().unwrap();
+    emu.pause_or_resume();
+    emu.run_frame(TICKS_PER_FRAME).unwrap();
+    assert_eq!(emu.cpu_state().delay_timer, 29);
+
+    emu.next().unwrap();
+    emu.run_frame(TICKS_PER_FRAME).unwrap();
+    assert_eq!(emu.cpu_state().delay_timer, 29);
+    assert!(emu.step_back());
+    emu.run_frame(TICKS_PER_FRAME).unwrap();
+    assert_eq!(emu.cpu_state().delay_timer, 29);
+}
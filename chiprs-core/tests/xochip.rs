@@ -0,0 +1,105 @@
+//! Runs the XO-CHIP instructions one at a time on small hand-assembled
+//! programs.
+
+use chiprs_core::quirks::Quirks;
+use chiprs_core::Emulator;
+
+fn run(rom: &[u8], cycles: usize) -> Emulator {
+    let mut emu = Emulator::new(Quirks::XO_CHIP);
+    emu.load(rom).unwrap();
+    for _ in 0..cycles {
+        emu.next().unwrap();
+    }
+    emu
+}
+
+// V1..V3 := 1, 2, 3, I := 300
+const SETUP: [u8; 8] = [0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00];
+
+fn with_setup(rest: &[u8]) -> Vec<u8> {
+    let mut rom = SETUP.to_vec();
+    rom.extend_from_slice(rest);
+    rom
+}
+
+#[test]
+fn register_ranges_save_in_either_order() {
+    let memory = |emu: &Emulator| (0x300..0x304).map(|a| emu.peek(a)).collect::<Vec<_>>();
+
+    // save V1..V3
+    let emu = run(&with_setup(&[0x51, 0x32]), 5);
+    assert_eq!(memory(&emu), [1, 2, 3, 0]);
+    assert_eq!(emu.cpu_state().i, 0x300);
+
+    // save V3..V1
+    let emu = run(&with_setup(&[0x53, 0x12]), 5);
+    assert_eq!(memory(&emu), [3, 2, 1, 0]);
+
+    // save V2..V2
+    let emu = run(&with_setup(&[0x52, 0x22]), 5);
+    assert_eq!(memory(&emu), [2, 0, 0, 0]);
+}
+
+#[test]
+fn register_ranges_load_in_either_order() {
+    // save V1..V3, then load them into V6..V4
+    let emu = run(&with_setup(&[0x51, 0x32, 0x56, 0x43]), 6);
+    assert_eq!(emu.cpu_state().v[4..7], [3, 2, 1]);
+    assert_eq!(emu.cpu_state().i, 0x300);
+}
+
+#[test]
+fn long_loads_set_all_of_i() {
+    // I := long ABCD, V0 := 1
+    let emu = run(&[0xF0, 0x00, 0xAB, 0xCD, 0x60, 0x01], 1);
+    assert_eq!(emu.cpu_state().i, 0xABCD);
+    assert_eq!(emu.counter, 0x204);
+
+    // Memory goes all the way up to FFFF: save V0 there
+    let emu = run(&[0xF0, 0x00, 0xFF, 0xFF, 0x60, 0x01, 0xF0, 0x55], 3);
+    assert_eq!(emu.peek(0xFFFF), 1);
+}
+
+#[test]
+fn skips_step_over_long_loads() {
+    // if V0 == 0 skip the long load, V1 := 7
+    let emu = run(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x07], 2);
+    assert_eq!(emu.cpu_state().i, 0);
+    assert_eq!(emu.cpu_state().v[1], 7);
+    assert_eq!(emu.counter, 0x208);
+}
+
+#[test]
+fn drawing_uses_the_selected_planes() {
+    // I := 20C, select plane 2, draw one row, select both, draw one row each
+    let rom = [
+        0xA2, 0x0C, 0xF2, 0x01, 0xD0, 0x01, 0xF3, 0x01, 0xD0, 0x01, 0x00, 0x00, 0xC0, 0x60,
+    ];
+    let emu = run(&rom, 3);
+    assert_eq!(emu.get_screen().pixels()[..4], [0b10, 0b10, 0, 0]);
+
+    // The second plane reads the byte after the first plane's sprite
+    let emu = run(&rom, 5);
+    assert_eq!(emu.get_screen().pixels()[..4], [0b11, 0b01, 0b10, 0]);
+    assert_eq!(emu.cpu_state().v[0xF], 1);
+}
+
+#[test]
+fn clearing_and_scrolling_touch_the_selected_planes() {
+    // I := 210, select both, draw one row each, select plane 1, scroll up 1, clear
+    let rom = [
+        0xA2, 0x10, 0xF3, 0x01, 0xD0, 0x12, 0xF1, 0x01, 0x00, 0xD1, 0x00, 0xE0, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x80, 0x80, 0x00,
+    ];
+    let emu = run(&rom, 3);
+    let screen = emu.get_screen().pixels().to_vec();
+    assert_eq!((screen[0], screen[64]), (0b10, 0b01));
+
+    // Plane 1's second row moves up next to plane 2's first
+    let emu = run(&rom, 5);
+    let screen = emu.get_screen().pixels().to_vec();
+    assert_eq!((screen[0], screen[64]), (0b11, 0b00));
+
+    let emu = run(&rom, 6);
+    assert_eq!(emu.get_screen().pixels()[0], 0b10);
+}
@@ -0,0 +1,82 @@
+use chiprs_core::audio::{AudioSink, NullSink};
+
+/// Sink for the default audio device, silent when chiprs is built without
+/// the `audio` feature or no device is available.
+pub fn device_sink() -> Box<dyn AudioSink> {
+    #[cfg(feature = "audio")]
+    match device::DeviceSink::open() {
+        Ok(sink) => return Box::new(sink),
+        Err(e) => eprintln!("unable to open audio device: {}", e),
+    }
+
+    Box::new(NullSink)
+}
+
+#[cfg(feature = "audio")]
+mod device {
+    use chiprs_core::audio::AudioSink;
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    // Keep at most this many frames queued so latency can't build up
+    const MAX_QUEUED_FRAMES: u32 = 6;
+
+    pub struct DeviceSink {
+        queue: Arc<Mutex<VecDeque<f32>>>,
+        sample_rate: u32,
+        // Dropping the stream stops playback
+        _stream: cpal::Stream,
+    }
+
+    impl DeviceSink {
+        pub fn open() -> Result<Self, String> {
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or("no output device")?;
+            let config = device.default_output_config().map_err(|e| e.to_string())?;
+            let sample_rate = config.sample_rate().0;
+            let channels = config.channels() as usize;
+
+            let queue = Arc::new(Mutex::new(VecDeque::new()));
+            let callback_queue = Arc::clone(&queue);
+            let stream = device
+                .build_output_stream(
+                    &config.into(),
+                    move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                        let mut queue = callback_queue.lock().unwrap();
+                        for frame in data.chunks_mut(channels) {
+                            frame.fill(queue.pop_front().unwrap_or(0.0));
+                        }
+                    },
+                    |e| eprintln!("audio stream error: {}", e),
+                    None,
+                )
+                .map_err(|e| e.to_string())?;
+            stream.play().map_err(|e| e.to_string())?;
+
+            Ok(Self {
+                queue,
+                sample_rate,
+                _stream: stream,
+            })
+        }
+    }
+
+    impl AudioSink for DeviceSink {
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate
+        }
+
+        fn write(&mut self, samples: &[f32]) {
+            let mut queue = self.queue.lock().unwrap();
+            queue.extend(samples);
+
+            let max = (self.sample_rate / 60 * MAX_QUEUED_FRAMES) as usize;
+            if queue.len() > max {
+                let excess = queue.len() - max;
+                queue.drain(..excess);
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use crate::script::KeyScript;
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::symbols::SymbolMap;
+use chiprs_core::trace::TraceOptions;
+use chiprs_core::Emulator;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+mod png;
+mod script;
+
+const USAGE: &str = "\
+Usage: chiprs-headless <rom-file> [chip8|chip48|schip|xochip] [options]
+
+Options:
+  --frames <n>              frames to run (default 60)
+  --cycles <n>              instructions to run instead of whole frames
+  --press <frame:key[:n]>   press a hex key before a frame, held for n frames (default 4)
+  --keys <file>             read key presses from a file, one per line
+  --ascii <file>            write the framebuffer as ASCII art, - for stdout
+  --png <file>              write the framebuffer as a PNG image
+  --registers <file>        write the registers as JSON, - for stdout
+  --profile <file>          write the cycles spent per subroutine and address, - for stdout
+  --profile-folded <file>   write the cycles per call stack for flamegraph tools
+  --coverage <file>         write the instructions run and skips taken, for chiprs coverage
+  --trace <file>            write a line per executed instruction, - for stdout
+  --trace-format <format>   trace as text (default) or jsonl
+  --trace-range <a[-b]>     only trace instructions in a hex address range, repeatable
+  --trace-class <kind>      only trace instructions of a kind, repeatable
+  --trace-ring <n>          keep the last n traced instructions, written on a fault
+
+Without any output option the ASCII framebuffer is printed.";
+
+const DEFAULT_FRAMES: u64 = 60;
+// Same colors as the window frontend, indexed by the plane bits of a pixel
+const PALETTE: [[u8; 3]; 4] = [
+    [0, 0, 0],
+    [0xFF, 0xFF, 0xFF],
+    [0xFF, 0x66, 0],
+    [0xFF, 0xCC, 0],
+];
+
+enum RunLength {
+    Frames(u64),
+    Cycles(u64),
+}
+
+struct Options {
+    rom_path: String,
+    quirks: Quirks,
+    length: RunLength,
+    script: KeyScript,
+    ascii: Option<String>,
+    png: Option<String>,
+    registers: Option<String>,
+    profile: Option<String>,
+    profile_folded: Option<String>,
+    coverage: Option<String>,
+    trace: TraceOptions,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut rom_path = None;
+        let mut quirks = None;
+        let mut length = RunLength::Frames(DEFAULT_FRAMES);
+        let mut script = KeyScript::default();
+        let mut ascii = None;
+        let mut png = None;
+        let mut registers = None;
+        let mut profile = None;
+        let mut profile_folded = None;
+        let mut coverage = None;
+        let mut trace = TraceOptions::default();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--frames" => length = RunLength::Frames(parse_count(arg, value()?)?),
+                "--cycles" => length = RunLength::Cycles(parse_count(arg, value()?)?),
+                "--press" => script.add(value()?)?,
+                "--keys" => {
+                    let path = value()?;
+                    let text = fs::read_to_string(path)
+                        .map_err(|e| format!("unable to read {}: {}", path, e))?;
+                    script.add_lines(&text)?;
+                }
+                "--ascii" => ascii = Some(value()?.clone()),
+                "--png" => png = Some(value()?.clone()),
+                "--registers" => registers = Some(value()?.clone()),
+                "--profile" => profile = Some(value()?.clone()),
+                "--profile-folded" => profile_folded = Some(value()?.clone()),
+                "--coverage" => coverage = Some(value()?.clone()),
+                flag if flag.starts_with("--trace") => trace.parse_flag(flag, value()?)?,
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                _ if rom_path.is_none() => rom_path = Some(arg.clone()),
+                _ if quirks.is_none() => quirks = Some(arg.parse::<Quirks>()?),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        // Frames started, the last one may stop partway
+        let frames = match length {
+            RunLength::Frames(frames) => frames,
+            RunLength::Cycles(cycles) => cycles.div_ceil(TICKS_PER_FRAME as u64),
+        };
+        script.check_length(frames)?;
+
+        let outputs = [
+            &ascii,
+            &png,
+            &registers,
+            &profile,
+            &profile_folded,
+            &coverage,
+        ];
+        if outputs.iter().all(|output| output.is_none()) {
+            ascii = Some("-".to_string());
+        }
+
+        Ok(Self {
+            rom_path: rom_path.ok_or("missing rom file")?,
+            quirks: quirks.unwrap_or_default(),
+            length,
+            script,
+            ascii,
+            png,
+            registers,
+            profile,
+            profile_folded,
+            coverage,
+            trace,
+        })
+    }
+}
+
+fn parse_count(flag: &str, value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let rom = match fs::read(&options.rom_path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("unable to read {}: {}", options.rom_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut emu = Emulator::new(options.quirks);
+    if let Err(e) = emu.load(&rom) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+    let symbols = match SymbolMap::find_for_rom(options.rom_path.as_ref()) {
+        Some(path) => match SymbolMap::load(&path) {
+            Ok(symbols) => symbols,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => SymbolMap::default(),
+    };
+    match options.trace.tracer(&symbols) {
+        Ok(Some(tracer)) => emu.set_tracer(tracer),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if options.profile.is_some() || options.profile_folded.is_some() {
+        emu.start_profiling();
+    }
+    if options.coverage.is_some() {
+        emu.start_coverage();
+    }
+
+    let (frames, extra_cycles) = match options.length {
+        RunLength::Frames(frames) => (frames, 0),
+        RunLength::Cycles(cycles) => (
+            cycles / TICKS_PER_FRAME as u64,
+            (cycles % TICKS_PER_FRAME as u64) as usize,
+        ),
+    };
+    let mut result = Ok(());
+    for frame in 0..frames {
+        options.script.apply(frame, &mut emu);
+        result = emu.run_frame(TICKS_PER_FRAME);
+        if result.is_err() {
+            break;
+        }
+    }
+    if result.is_ok() && extra_cycles > 0 {
+        options.script.apply(frames, &mut emu);
+        result = (0..extra_cycles).try_for_each(|_| emu.tick());
+    }
+    if let Err(e) = &result {
+        eprintln!("{}", e);
+    }
+
+    let screen = emu.get_screen();
+    let outputs = [
+        (&options.ascii, screen.to_ascii().into_bytes()),
+        (
+            &options.png,
+            png::encode(screen.width(), screen.height(), &PALETTE, screen.pixels()),
+        ),
+        (&options.registers, registers_json(&emu).into_bytes()),
+        (
+            &options.profile,
+            emu.profile()
+                .map(|profile| profile.report(&symbols))
+                .unwrap_or_default()
+                .into_bytes(),
+        ),
+        (
+            &options.profile_folded,
+            emu.profile()
+                .map(|profile| profile.folded(&symbols))
+                .unwrap_or_default()
+                .into_bytes(),
+        ),
+        (
+            &options.coverage,
+            emu.coverage()
+                .map(|coverage| coverage.to_string())
+                .unwrap_or_default()
+                .into_bytes(),
+        ),
+    ];
+    for (path, data) in outputs {
+        if let Some(path) = path
+            && let Err(e) = write_output(path, &data)
+        {
+            eprintln!("unable to write {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(e) = emu.finish_trace() {
+        eprintln!("unable to write the trace: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if result.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn write_output(path: &str, data: &[u8]) -> io::Result<()> {
+    if path == "-" {
+        io::stdout().write_all(data)
+    } else {
+        fs::write(path, data)
+    }
+}
+
+fn registers_json(emu: &Emulator) -> String {
+    let cpu = emu.cpu_state();
+    let list = |values: Vec<String>| values.join(",");
+    let fault = match emu.fault() {
+        Some(fault) => format!(
+            "\"{}\"",
+            fault.to_string().replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        None => "null".to_string(),
+    };
+
+    format!(
+        "{{\"pc\":{},\"i\":{},\"v\":[{}],\"delay_timer\":{},\"sound_timer\":{},\"stack\":[{}],\"exited\":{},\"fault\":{}}}\n",
+        cpu.pc,
+        cpu.i,
+        list(cpu.v.iter().map(u8::to_string).collect()),
+        cpu.delay_timer,
+        cpu.sound_timer,
+        list(cpu.stack.iter().map(u16::to_string).collect()),
+        emu.has_exited(),
+        fault
+    )
+}
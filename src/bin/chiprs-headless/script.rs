@@ -0,0 +1,102 @@
+use chiprs_core::{ChipKey, Emulator};
+
+// Frames a key stays down when an entry doesn't say
+const DEFAULT_HOLD_FRAMES: u64 = 4;
+
+struct KeyEvent {
+    frame: u64,
+    key: ChipKey,
+    pressed: bool,
+    // What the press was added from, for errors
+    entry: String,
+}
+
+/// Scripted keypad input, built from `frame:key[:frames]` entries.
+///
+/// `30:5:2` presses key 5 before frame 30 runs and releases it before frame 32.
+#[derive(Default)]
+pub struct KeyScript {
+    events: Vec<KeyEvent>,
+}
+
+impl KeyScript {
+    pub fn add(&mut self, entry: &str) -> Result<(), String> {
+        let invalid = || format!("invalid key press '{}', expected frame:key[:frames]", entry);
+        let mut parts = entry.split(':');
+        let frame = parts
+            .next()
+            .and_then(|frame| frame.parse::<u64>().ok())
+            .ok_or_else(invalid)?;
+        let key = parts
+            .next()
+            .and_then(|key| u8::from_str_radix(key, 16).ok())
+            .and_then(ChipKey::from_hex)
+            .ok_or_else(invalid)?;
+        let hold = match parts.next() {
+            Some(hold) => hold.parse::<u64>().map_err(|_| invalid())?,
+            None => DEFAULT_HOLD_FRAMES,
+        };
+        if parts.next().is_some() || hold == 0 {
+            return Err(invalid());
+        }
+
+        // Later than any run can last
+        let release = frame
+            .checked_add(hold)
+            .ok_or_else(|| format!("key press '{}' is released after the last frame", entry))?;
+
+        self.events.push(KeyEvent {
+            frame,
+            key,
+            pressed: true,
+            entry: entry.to_string(),
+        });
+        self.events.push(KeyEvent {
+            frame: release,
+            key,
+            pressed: false,
+            entry: entry.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Fails on presses that don't happen or aren't released in a run of
+    /// `frames` frames. A release due right as the run ends holds the key to
+    /// the end.
+    pub fn check_length(&self, frames: u64) -> Result<(), String> {
+        match self
+            .events
+            .iter()
+            .find(|event| event.frame > frames || (event.pressed && event.frame == frames))
+        {
+            Some(event) if event.pressed => Err(format!(
+                "key press '{}' is after the last frame",
+                event.entry
+            )),
+            Some(event) => Err(format!(
+                "key press '{}' is released after the last frame",
+                event.entry
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds one entry per line, blank lines and `#` comments are skipped.
+    pub fn add_lines(&mut self, text: &str) -> Result<(), String> {
+        text.lines()
+            .map(|line| line.split('#').next().unwrap().trim())
+            .filter(|line| !line.is_empty())
+            .try_for_each(|line| self.add(line))
+    }
+
+    /// Feeds the emulator the key changes due before `frame` runs, releases first.
+    pub fn apply(&self, frame: u64, emu: &mut Emulator) {
+        let due = || self.events.iter().filter(|event| event.frame == frame);
+        due()
+            .filter(|event| !event.pressed)
+            .for_each(|event| emu.key_released(event.key));
+        due()
+            .filter(|event| event.pressed)
+            .for_each(|event| emu.key_pressed(event.key));
+    }
+}
@@ -0,0 +1,125 @@
+use chiprs_core::asm::assemble;
+use chiprs_core::coverage::Coverage;
+use chiprs_core::disasm::Disassembly;
+use chiprs_core::symbols::SymbolMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Runs a subcommand such as `chiprs disasm`, `None` if `args` don't start
+/// with one.
+pub fn run(args: &[String]) -> Option<Result<(), String>> {
+    let (command, args) = args.split_first()?;
+    match command.as_str() {
+        "asm" => Some(asm(args)),
+        "coverage" => Some(coverage(args)),
+        "disasm" => Some(disasm(args)),
+        "import-labels" => Some(import_labels(args)),
+        _ => None,
+    }
+}
+
+// chiprs disasm <rom-file>: prints the rom as Octo source, with the names and
+// data regions of its symbol file if there is one
+fn disasm(args: &[String]) -> Result<(), String> {
+    let [rom_path] = args else {
+        return Err("usage: chiprs disasm <rom-file>".to_string());
+    };
+    let rom = fs::read(rom_path).map_err(|e| format!("unable to read {}: {}", rom_path, e))?;
+    let symbols = symbols_for_rom(rom_path)?;
+
+    let disassembly = Disassembly::of_rom(&rom, &symbols);
+    write!(io::stdout(), "{}", disassembly).map_err(|e| e.to_string())
+}
+
+// chiprs asm <source-file> <rom-file>: assembles Octo source into a rom, and
+// writes its symbol file next to it
+fn asm(args: &[String]) -> Result<(), String> {
+    let [source_path, rom_path] = args else {
+        return Err("usage: chiprs asm <source-file> <rom-file>".to_string());
+    };
+    let source = fs::read_to_string(source_path)
+        .map_err(|e| format!("unable to read {}: {}", source_path, e))?;
+
+    let program = assemble(&source).map_err(|e| format!("{}: {}", source_path, e))?;
+    fs::write(rom_path, &program.rom)
+        .map_err(|e| format!("unable to write {}: {}", rom_path, e))?;
+
+    let symbols_path = Path::new(rom_path).with_extension("sym");
+    let source_file = relative_to(Path::new(source_path), &symbols_path);
+    write_symbols(&symbols_path, &program.symbols(&source_file))
+}
+
+// chiprs coverage <rom-file> <coverage-file>...: combines the coverage files
+// of several runs and prints an lcov tracefile if the rom's symbol file has
+// source lines, the annotated disassembly otherwise
+fn coverage(args: &[String]) -> Result<(), String> {
+    let Some((rom_path, coverage_paths)) =
+        args.split_first().filter(|(_, paths)| !paths.is_empty())
+    else {
+        return Err("usage: chiprs coverage <rom-file> <coverage-file>...".to_string());
+    };
+    let rom = fs::read(rom_path).map_err(|e| format!("unable to read {}: {}", rom_path, e))?;
+    let symbols = symbols_for_rom(rom_path)?;
+
+    let mut coverage = Coverage::new();
+    for path in coverage_paths {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("unable to read {}: {}", path, e))?;
+        coverage.merge(&Coverage::parse(&text).map_err(|e| format!("{}: {}", path, e))?);
+    }
+
+    let report = if symbols.has_source_lines() {
+        coverage.lcov(&rom, &symbols)
+    } else {
+        coverage.annotate(&rom, &symbols)
+    };
+    write!(io::stdout(), "{}", report).map_err(|e| e.to_string())
+}
+
+// chiprs import-labels <labels-file> <symbol-file>: adds a list of
+// `name address` lines to a symbol file
+fn import_labels(args: &[String]) -> Result<(), String> {
+    let [labels_path, symbols_path] = args else {
+        return Err("usage: chiprs import-labels <labels-file> <symbol-file>".to_string());
+    };
+    let text = fs::read_to_string(labels_path)
+        .map_err(|e| format!("unable to read {}: {}", labels_path, e))?;
+    let labels = SymbolMap::import_labels(&text).map_err(|e| format!("{}: {}", labels_path, e))?;
+
+    // Keep what the symbol file already has, with paths as they are written
+    let symbols_path = Path::new(symbols_path);
+    let mut symbols = match fs::read_to_string(symbols_path) {
+        Ok(text) => {
+            SymbolMap::parse(&text).map_err(|e| format!("{}: {}", symbols_path.display(), e))?
+        }
+        Err(_) => SymbolMap::default(),
+    };
+    symbols.merge(labels);
+    write_symbols(symbols_path, &symbols)
+}
+
+fn symbols_for_rom(rom_path: &str) -> Result<SymbolMap, String> {
+    match SymbolMap::find_for_rom(Path::new(rom_path)) {
+        Some(path) => SymbolMap::load(&path),
+        None => Ok(SymbolMap::default()),
+    }
+}
+
+fn write_symbols(path: &Path, symbols: &SymbolMap) -> Result<(), String> {
+    fs::write(path, symbols.to_string())
+        .map_err(|e| format!("unable to write {}: {}", path.display(), e))
+}
+
+// `path` relative to the directory of `file`, or absolute if it isn't below it
+fn relative_to(path: &Path, file: &Path) -> PathBuf {
+    let absolute = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let path = absolute(path);
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => absolute(dir),
+        _ => absolute(Path::new(".")),
+    };
+    path.strip_prefix(&dir)
+        .map(Path::to_path_buf)
+        .unwrap_or(path)
+}
@@ -1,8 +0,0 @@
-pub const EMU_SCREEN_WIDTH: usize = 64;
-pub const EMU_SCREEN_HEIGHT: usize = 32;
-pub const RAM_SIZE: usize = 4096;
-pub const V_SIZE: usize = 16;
-pub const STACK_SIZE: usize = 16;
-pub const KEYPAD_SIZE: usize = 16;
-
-pub const START_ADDR: u16 = 0x200;
\ No newline at end of file
@@ -1,5 +1,8 @@
-use crate::emu::keys::ChipKey;
-use crate::emu::Emulator;
+use crate::slots::{SaveSlots, SLOT_COUNT};
+use crate::ui::MemoryView;
+use chiprs_core::keys::ChipKey;
+use chiprs_core::rewind::RewindBuffer;
+use chiprs_core::Emulator;
 use minifb::{Key, KeyRepeat, Window};
 
 pub fn convert_key(key: &Key) -> Option<ChipKey> {
@@ -24,16 +27,118 @@ pub fn convert_key(key: &Key) -> Option<ChipKey> {
     }
 }
 
-pub fn handle_control_keys(window: &Window, emu: &mut Emulator) {
+const SLOT_KEYS: [Key; SLOT_COUNT] = [Key::F5, Key::F6, Key::F7, Key::F8];
+
+/// Handles the keys that control the emulator itself, resetting or loading a
+/// slot starts the rewind history over.
+pub fn handle_control_keys(
+    window: &Window,
+    emu: &mut Emulator,
+    slots: &SaveSlots,
+    rewind: &mut RewindBuffer,
+) {
     if window.is_key_pressed(Key::F1, KeyRepeat::No) {
-        emu.reset()
+        emu.reset();
+        rewind.clear();
     }
 
     if window.is_key_pressed(Key::F2, KeyRepeat::No) {
         emu.pause_or_resume()
     }
 
-    if window.is_key_pressed(Key::F3, KeyRepeat::Yes) {
-        emu.next()
+    if window.is_key_pressed(Key::F3, KeyRepeat::Yes)
+        && let Err(e) = emu.next()
+    {
+        eprintln!("{}", e);
+    }
+
+    if window.is_key_pressed(Key::F4, KeyRepeat::Yes) && !emu.step_back() {
+        eprintln!("nothing to step back over, instructions are recorded while paused or with breakpoints set");
+    }
+
+    let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+    for (idx, key) in SLOT_KEYS.iter().enumerate() {
+        if !window.is_key_pressed(*key, KeyRepeat::No) {
+            continue;
+        }
+
+        let slot = idx + 1;
+        let result = if shift {
+            slots.load(slot, emu).map(|_| {
+                rewind.clear();
+                "loaded"
+            })
+        } else {
+            slots.save(slot, emu).map(|_| "saved")
+        };
+        match result {
+            Ok(action) => eprintln!("{} slot {}", action, slot),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+const MEMORY_PAGE_ROWS: isize = 16;
+
+fn hex_digit(key: &Key) -> Option<u8> {
+    match key {
+        Key::Key0 | Key::NumPad0 => Some(0x0),
+        Key::Key1 | Key::NumPad1 => Some(0x1),
+        Key::Key2 | Key::NumPad2 => Some(0x2),
+        Key::Key3 | Key::NumPad3 => Some(0x3),
+        Key::Key4 | Key::NumPad4 => Some(0x4),
+        Key::Key5 | Key::NumPad5 => Some(0x5),
+        Key::Key6 | Key::NumPad6 => Some(0x6),
+        Key::Key7 | Key::NumPad7 => Some(0x7),
+        Key::Key8 | Key::NumPad8 => Some(0x8),
+        Key::Key9 | Key::NumPad9 => Some(0x9),
+        Key::A => Some(0xA),
+        Key::B => Some(0xB),
+        Key::C => Some(0xC),
+        Key::D => Some(0xD),
+        Key::E => Some(0xE),
+        Key::F => Some(0xF),
+        _ => None,
+    }
+}
+
+/// Scrolls the memory panel and edits its selected byte, returns whether the
+/// keyboard went to the editor rather than to the chip keypad.
+pub fn handle_memory_keys(window: &Window, emu: &mut Emulator, memory: &mut MemoryView) -> bool {
+    if window.is_key_pressed(Key::PageUp, KeyRepeat::Yes) {
+        memory.scroll(-MEMORY_PAGE_ROWS);
+    }
+    if window.is_key_pressed(Key::PageDown, KeyRepeat::Yes) {
+        memory.scroll(MEMORY_PAGE_ROWS);
+    }
+    if let Some((_, wheel)) = window.get_scroll_wheel() {
+        memory.scroll(-wheel.signum() as isize);
+    }
+    if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+        memory.toggle_heatmap();
+        emu.start_profiling();
+    }
+
+    // Bytes can only be edited while paused
+    if !emu.is_paused() {
+        memory.select(None);
+    }
+    if memory.selected().is_none() {
+        return false;
+    }
+
+    for key in window.get_keys_pressed(KeyRepeat::Yes) {
+        match key {
+            Key::Left => memory.move_selection(-1),
+            Key::Right => memory.move_selection(1),
+            Key::Up => memory.move_selection(-16),
+            Key::Down => memory.move_selection(16),
+            _ => {
+                if let Some(digit) = hex_digit(&key) {
+                    memory.type_digit(emu, digit)
+                }
+            }
+        }
     }
+    true
 }
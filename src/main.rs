@@ -1,32 +1,169 @@
-use crate::emu::Emulator;
-use crate::keys::{convert_key, handle_control_keys};
+use crate::audio::device_sink;
+use crate::keys::{convert_key, handle_control_keys, handle_memory_keys};
+use crate::options::{AudioOutput, Options, USAGE};
+use crate::slots::SaveSlots;
 use crate::ui::UiDrawer;
-use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use chiprs_core::audio::{Audio, AudioSink, NullSink, WavSink};
+use chiprs_core::constants::TICKS_PER_FRAME;
+use chiprs_core::dap::DapServer;
+use chiprs_core::debugger::Breakpoint;
+use chiprs_core::gdb::GdbServer;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::rewind::RewindBuffer;
+use chiprs_core::symbols::SymbolMap;
+use chiprs_core::Emulator;
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 use std::env;
-use std::fs::File;
-use std::io::Read;
+use std::fs;
+use std::path::Path;
+use std::process;
 
-mod emu;
+mod audio;
+mod commands;
 mod keys;
+mod options;
+mod slots;
 mod ui;
 
-const TICKS_PER_FRAME: usize = 11;
 const EMU_SCALE: usize = 10;
+const REWIND_INTERVAL: usize = 4;
+const REWIND_MAX_BYTES: usize = 4 * 1024 * 1024;
+const WAV_SAMPLE_RATE: u32 = 44100;
 
 fn main() {
-    let args = env::args().collect::<Vec<_>>();
-    if args.len() < 2 {
-        println!("Usage: {} <rom-file>", args[0]);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Some(result) = commands::run(&args) {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
         return;
     }
 
-    let mut rom = File::open(&args[1]).expect("unable to open rom");
-    let mut buffer = Vec::new();
-    rom.read_to_end(&mut buffer).expect("unable to read rom");
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}\n\n{}", e, USAGE);
+            return;
+        }
+    };
+
+    let mut dap = options.dap.then(DapServer::start);
+    let (rom_path, quirks, symbols_path) = match &mut dap {
+        Some(dap) => match dap.wait_for_launch() {
+            Some(launch) => (
+                launch.program,
+                launch.quirks.unwrap_or(options.quirks),
+                launch.symbols.or(options.symbols_path.clone()),
+            ),
+            None => return,
+        },
+        None => (
+            options.rom_path.clone().unwrap_or_default(),
+            options.quirks,
+            options.symbols_path.clone(),
+        ),
+    };
+
+    let mut emu = match load_rom(&rom_path, quirks) {
+        Ok(emu) => emu,
+        Err(e) => {
+            launch_failed(&mut dap, &e);
+            return;
+        }
+    };
+    let sink: Box<dyn AudioSink> = match &options.audio {
+        AudioOutput::Device => device_sink(),
+        AudioOutput::Wav(path) => match WavSink::create(path, WAV_SAMPLE_RATE) {
+            Ok(sink) => Box::new(sink),
+            Err(e) => {
+                launch_failed(
+                    &mut dap,
+                    &format!("unable to create {}: {}", path.display(), e),
+                );
+                return;
+            }
+        },
+        AudioOutput::Mute => Box::new(NullSink),
+    };
+    match Audio::new(sink, options.pitch, options.volume) {
+        Ok(audio) => emu.set_audio(audio),
+        Err(e) => {
+            launch_failed(&mut dap, &e);
+            return;
+        }
+    }
+
+    let mut errors = Vec::new();
+    let symbols = match symbols_path.or_else(|| SymbolMap::find_for_rom(&rom_path)) {
+        Some(path) => SymbolMap::load(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            errors.push(e);
+            SymbolMap::default()
+        }),
+        None => SymbolMap::default(),
+    };
+    match options.trace.tracer(&symbols) {
+        Ok(Some(tracer)) => emu.set_tracer(tracer),
+        Ok(None) => {}
+        Err(e) => {
+            launch_failed(&mut dap, &e);
+            return;
+        }
+    }
+    if options.profile.is_some() || options.profile_folded.is_some() {
+        emu.start_profiling();
+    }
+
+    let debugger = emu.debugger_mut();
+    for option in &options.breakpoints {
+        let breakpoint = option.address(&symbols).and_then(|address| {
+            Breakpoint::new(option.condition.as_deref(), option.log_message.as_deref())
+                .map(|breakpoint| (address, breakpoint))
+                .map_err(|e| e.to_string())
+        });
+        match breakpoint {
+            Ok((address, breakpoint)) => debugger.set_breakpoint(address, breakpoint),
+            Err(e) => {
+                let error = format!("breakpoint {}: {}", option.location, e);
+                eprintln!("{}", error);
+                errors.push(error);
+            }
+        }
+    }
+    options
+        .class_breakpoints
+        .iter()
+        .for_each(|&class| debugger.add_class_breakpoint(class));
+    debugger.set_break_on_new_address(options.break_on_new_address);
+    options
+        .watchpoints
+        .iter()
+        .for_each(|watchpoint| debugger.add_watchpoint(watchpoint.clone()));
 
-    let mut emu = Emulator::new();
-    emu.load(&buffer);
-    let ui = UiDrawer::new(EMU_SCALE);
+    let mut gdb = None;
+    if let Some(port) = options.gdb_port {
+        match GdbServer::bind(("127.0.0.1", port)) {
+            Ok(server) => {
+                eprintln!("listening for GDB on 127.0.0.1:{}", port);
+                gdb = Some(server);
+            }
+            Err(e) => {
+                let error = format!("unable to listen for GDB on port {}: {}", port, e);
+                launch_failed(&mut dap, &error);
+                return;
+            }
+        }
+    }
+
+    if let Some(dap) = &mut dap {
+        dap.launched(&mut emu, symbols.clone());
+        errors.iter().for_each(|error| dap.output(error));
+    }
+
+    let slots = SaveSlots::new(&rom_path);
+    let mut rewind = RewindBuffer::new(REWIND_INTERVAL, REWIND_MAX_BYTES);
+    let mut ui = UiDrawer::new(EMU_SCALE, symbols.clone());
 
     let mut window = Window::new(
         "Chiprs",
@@ -44,30 +181,76 @@ fn main() {
     window.set_target_fps(60);
     window.set_background_color(0, 0, 0);
 
+    let mut mouse_was_down = false;
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        handle_control_keys(&window, &mut emu);
+        handle_control_keys(&window, &mut emu, &slots, &mut rewind);
+        if let Some(gdb) = &mut gdb
+            && let Err(e) = gdb.poll(&mut emu)
+        {
+            eprintln!("GDB connection lost: {}", e);
+        }
+        if let Some(dap) = &mut dap {
+            let connected = dap.poll(&mut emu);
+            dap.take_errors().iter().for_each(|e| eprintln!("{}", e));
+            if !connected {
+                break;
+            }
+        }
 
-        window
-            .get_keys_pressed(KeyRepeat::No)
-            .iter()
-            .for_each(|key| {
-                if let Some(key) = convert_key(key) {
-                    emu.key_pressed(key)
-                }
-            });
+        // Clicking a line of the instruction list toggles a breakpoint on it,
+        // clicking a byte of the memory panel while paused selects it for editing
+        let mouse_down = window.get_mouse_down(MouseButton::Left);
+        if mouse_down
+            && !mouse_was_down
+            && let Some(pos) = window.get_mouse_pos(MouseMode::Discard)
+        {
+            if let Some(address) = ui.instruction_at(&emu, pos) {
+                emu.debugger_mut().toggle_breakpoint(address);
+            }
+            let selected = ui.memory_at(pos).filter(|_| emu.is_paused());
+            ui.memory.select(selected);
+        }
+        mouse_was_down = mouse_down;
 
-        for _ in 0..TICKS_PER_FRAME {
-            emu.tick();
+        // Keys typed into the memory editor don't reach the chip, pressed or released
+        let editing_memory = handle_memory_keys(&window, &mut emu, &mut ui.memory);
+        if !editing_memory {
+            window
+                .get_keys_pressed(KeyRepeat::No)
+                .iter()
+                .for_each(|key| {
+                    if let Some(key) = convert_key(key) {
+                        emu.key_pressed(key)
+                    }
+                });
         }
-        emu.tick_timers();
 
-        window.get_keys_released().iter().for_each(|key| {
-            if let Some(key) = convert_key(key) {
-                emu.key_released(key)
+        if window.is_key_down(Key::Backspace) {
+            if let Err(e) = rewind.rewind(&mut emu) {
+                eprintln!("unable to rewind: {}", e);
             }
-        });
+        } else {
+            if let Err(e) = emu.run_frame(TICKS_PER_FRAME) {
+                eprintln!("{}", e);
+            }
+            for message in emu.debugger_mut().take_logs() {
+                match &mut dap {
+                    Some(dap) => dap.output(&message),
+                    None => println!("{}", message),
+                }
+            }
+            rewind.record(&emu);
+        }
+
+        if !editing_memory {
+            window.get_keys_released().iter().for_each(|key| {
+                if let Some(key) = convert_key(key) {
+                    emu.key_released(key)
+                }
+            });
+        }
 
-        let window_buffer = ui.draw(&mut emu);
+        let window_buffer = ui.draw(&mut emu, &errors);
         window
             .update_with_buffer(
                 window_buffer.as_slice(),
@@ -76,4 +259,47 @@ fn main() {
             )
             .unwrap();
     }
+
+    if let Err(e) = emu.finish_audio() {
+        eprintln!("unable to write sound: {}", e);
+    }
+    if let Some(e) = emu.finish_trace() {
+        eprintln!("unable to write the trace: {}", e);
+    }
+    if let Some(profile) = emu.profile() {
+        let outputs = [
+            (&options.profile, profile.report(&symbols)),
+            (&options.profile_folded, profile.folded(&symbols)),
+        ];
+        for (path, text) in outputs {
+            if let Some(path) = path
+                && let Err(e) = fs::write(path, text)
+            {
+                eprintln!("unable to write {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    if let Some(dap) = &mut dap {
+        dap.terminated();
+    }
+}
+
+// Stdout carries the protocol when debugging over DAP, so errors ending the
+// launch are its reply instead. Without DAP they end the process with a failure.
+fn launch_failed(dap: &mut Option<DapServer>, message: &str) {
+    match dap {
+        Some(dap) => dap.launch_failed(message),
+        None => {
+            eprintln!("{}", message);
+            process::exit(1);
+        }
+    }
+}
+
+fn load_rom(path: &Path, quirks: Quirks) -> Result<Emulator, String> {
+    let rom = fs::read(path).map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+    let mut emu = Emulator::new(quirks);
+    emu.load(&rom).map_err(|e| e.to_string())?;
+    Ok(emu)
 }
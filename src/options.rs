@@ -0,0 +1,256 @@
+use chiprs_core::audio::{check_pitch, check_volume, DEFAULT_PITCH, DEFAULT_VOLUME};
+use chiprs_core::debugger::{WatchKind, Watchpoint};
+use chiprs_core::instruction::InstructionClass;
+use chiprs_core::quirks::Quirks;
+use chiprs_core::symbols::SymbolMap;
+use chiprs_core::trace::TraceOptions;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: chiprs <rom-file> [chip8|chip48|schip|xochip] [options]
+       chiprs --dap [options]
+       chiprs <command> <arguments>
+
+Commands:
+  disasm <rom-file> print a rom as Octo source
+  asm <source-file> <rom-file>
+                    assemble Octo source into a rom and its symbol file
+  coverage <rom-file> <coverage-file>...
+                    report the instructions and skip directions the runs of
+                    chiprs-headless --coverage exercised, as lcov if the rom's
+                    symbol file has source lines
+  import-labels <labels-file> <symbol-file>
+                    add a list of 'name address' lines to a symbol file
+
+Options:
+  --mute            disable sound
+  --wav <file>      write sound to a WAV file instead of the audio device
+  --pitch <hz>      buzzer pitch (default 440)
+  --volume <0-1>    buzzer volume (default 0.25)
+  --symbols <file>  read labels, source lines and data regions from a symbol file,
+                    by default the .sym file next to the rom
+  --break <addr> [if <condition>]
+                    pause before the instruction at a hex address or label, only
+                    when the condition holds, e.g. \"230 if v3 == 0x10 && hits > 5\"
+  --log <addr> [if <condition>:] <message>
+                    print a message instead of pausing, {expr} and {expr:x} are
+                    replaced by values, e.g. \"240 score={v3} at {i:x}\"
+  --break-on <kind> pause before any instruction of a kind: draw, screen, flow, skip,
+                    keys, timers, sound, memory, arithmetic or unknown
+  --break-new       pause before the first execution of every address
+  --watch <addr[-addr]>[:read|write|change]
+                    pause after an instruction accesses a hex address range,
+                    writes are watched by default
+  --trace <file>    write a line per executed instruction to a file, - for stdout
+  --trace-format <text|jsonl>
+                    trace as text (default) or one JSON object per line
+  --trace-range <addr[-addr]>
+                    only trace instructions in a hex address range, repeatable
+  --trace-class <kind>
+                    only trace instructions of a kind, repeatable
+  --trace-ring <n>  keep the last n traced instructions, written on a fault
+  --profile <file>  count the cycles spent per subroutine and address, the report is
+                    written to a file on exit, F9 shows them as a heatmap
+  --profile-folded <file>
+                    write the cycles per call stack on exit, for flamegraph tools
+  --gdb <port>      accept a GDB remote protocol connection on a local port
+  --dap             serve the Debug Adapter Protocol over stdio, the rom and
+                    platform come from the launch request";
+
+/// A `--break` or `--log` breakpoint, its expressions are parsed by the debugger.
+pub struct BreakpointOption {
+    /// A hex address or a label, resolved once the symbol file is loaded.
+    pub location: String,
+    pub condition: Option<String>,
+    pub log_message: Option<String>,
+}
+
+impl BreakpointOption {
+    pub fn address(&self, symbols: &SymbolMap) -> Result<u16, String> {
+        match symbols.address_of_label(&self.location) {
+            Some(address) => Ok(address),
+            None => u16::from_str_radix(self.location.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("unknown label or address '{}'", self.location)),
+        }
+    }
+}
+
+pub enum AudioOutput {
+    Device,
+    Wav(PathBuf),
+    Mute,
+}
+
+pub struct Options {
+    /// Always set unless `dap` is.
+    pub rom_path: Option<PathBuf>,
+    pub symbols_path: Option<PathBuf>,
+    pub quirks: Quirks,
+    pub audio: AudioOutput,
+    pub pitch: f32,
+    pub volume: f32,
+    pub breakpoints: Vec<BreakpointOption>,
+    pub class_breakpoints: Vec<InstructionClass>,
+    pub break_on_new_address: bool,
+    pub watchpoints: Vec<Watchpoint>,
+    pub trace: TraceOptions,
+    pub profile: Option<PathBuf>,
+    pub profile_folded: Option<PathBuf>,
+    pub gdb_port: Option<u16>,
+    pub dap: bool,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut rom_path = None;
+        let mut symbols_path = None;
+        let mut quirks = None;
+        let mut audio = AudioOutput::Device;
+        let mut pitch = DEFAULT_PITCH;
+        let mut volume = DEFAULT_VOLUME;
+        let mut breakpoints = Vec::new();
+        let mut class_breakpoints = Vec::new();
+        let mut break_on_new_address = false;
+        let mut watchpoints = Vec::new();
+        let mut trace = TraceOptions::default();
+        let mut profile = None;
+        let mut profile_folded = None;
+        let mut gdb_port = None;
+        let mut dap = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--mute" => audio = AudioOutput::Mute,
+                "--wav" => audio = AudioOutput::Wav(PathBuf::from(value()?)),
+                "--pitch" => pitch = parse_number(arg, value()?, check_pitch)?,
+                "--volume" => volume = parse_number(arg, value()?, check_volume)?,
+                "--symbols" => symbols_path = Some(PathBuf::from(value()?)),
+                "--break" => breakpoints.push(parse_breakpoint(arg, value()?, false)?),
+                "--log" => breakpoints.push(parse_breakpoint(arg, value()?, true)?),
+                "--break-on" => class_breakpoints.push(value()?.parse()?),
+                "--break-new" => break_on_new_address = true,
+                "--watch" => watchpoints.push(parse_watchpoint(arg, value()?)?),
+                "--profile" => profile = Some(PathBuf::from(value()?)),
+                "--profile-folded" => profile_folded = Some(PathBuf::from(value()?)),
+                "--dap" => dap = true,
+                "--gdb" => {
+                    let port = value()?;
+                    gdb_port = Some(
+                        port.parse()
+                            .map_err(|_| format!("invalid port '{}' for {}", port, arg))?,
+                    );
+                }
+                flag if flag.starts_with("--trace") => trace.parse_flag(flag, value()?)?,
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                _ if rom_path.is_none() => rom_path = Some(PathBuf::from(arg)),
+                _ if quirks.is_none() => quirks = Some(arg.parse::<Quirks>()?),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        if rom_path.is_none() && !dap {
+            return Err("missing rom file".to_string());
+        }
+        if dap && trace.path.as_deref() == Some("-") {
+            return Err("--trace - can't be used with --dap, which talks over stdout".to_string());
+        }
+
+        Ok(Self {
+            rom_path,
+            symbols_path,
+            quirks: quirks.unwrap_or_default(),
+            audio,
+            pitch,
+            volume,
+            breakpoints,
+            class_breakpoints,
+            break_on_new_address,
+            watchpoints,
+            trace,
+            profile,
+            profile_folded,
+            gdb_port,
+            dap,
+        })
+    }
+}
+
+fn parse_number(
+    flag: &str,
+    value: &str,
+    check: fn(f32) -> Result<(), String>,
+) -> Result<f32, String> {
+    let number = value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, flag))?;
+    check(number).map_err(|e| format!("{}: {}", flag, e))?;
+    Ok(number)
+}
+
+fn parse_address(flag: &str, value: &str) -> Result<u16, String> {
+    let digits = value.trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address '{}' for {}", value, flag))
+}
+
+// `<addr> [if <condition>]`, followed by `[:] <message>` for logpoints
+fn parse_breakpoint(flag: &str, value: &str, log: bool) -> Result<BreakpointOption, String> {
+    let value = value.trim();
+    let (location, mut rest) = value.split_once(char::is_whitespace).unwrap_or((value, ""));
+    rest = rest.trim_start();
+
+    let mut condition = None;
+    if let Some(after_if) = rest.strip_prefix("if ") {
+        let (expr, after) = if log {
+            after_if
+                .split_once(':')
+                .ok_or_else(|| format!("missing ':' before the message for {}", flag))?
+        } else {
+            (after_if, "")
+        };
+        condition = Some(expr.trim().to_string());
+        rest = after.trim_start();
+    }
+
+    let log_message = if log {
+        if rest.is_empty() {
+            return Err(format!("missing message for {}", flag));
+        }
+        Some(rest.to_string())
+    } else {
+        if !rest.is_empty() {
+            return Err(format!(
+                "expected 'if <condition>' after the address for {}",
+                flag
+            ));
+        }
+        None
+    };
+
+    Ok(BreakpointOption {
+        location: location.to_string(),
+        condition,
+        log_message,
+    })
+}
+
+fn parse_watchpoint(flag: &str, value: &str) -> Result<Watchpoint, String> {
+    let (range, kind) = match value.split_once(':') {
+        Some((range, kind)) => (range, kind.parse()?),
+        None => (value, WatchKind::Write),
+    };
+    let (from, to) = range.split_once('-').unwrap_or((range, range));
+    let (from, to) = (parse_address(flag, from)?, parse_address(flag, to)?);
+    if from > to {
+        return Err(format!("invalid address range '{}' for {}", range, flag));
+    }
+
+    Ok(Watchpoint {
+        range: from..=to,
+        kind,
+    })
+}
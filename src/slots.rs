@@ -0,0 +1,38 @@
+use chiprs_core::Emulator;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SLOT_COUNT: usize = 4;
+
+/// Numbered save state files stored next to the rom, e.g. `game.ch8.state1`.
+pub struct SaveSlots {
+    rom_path: PathBuf,
+}
+
+impl SaveSlots {
+    pub fn new(rom_path: &Path) -> Self {
+        Self {
+            rom_path: rom_path.to_path_buf(),
+        }
+    }
+
+    fn slot_path(&self, slot: usize) -> PathBuf {
+        let mut path = self.rom_path.clone().into_os_string();
+        path.push(format!(".state{}", slot));
+        PathBuf::from(path)
+    }
+
+    pub fn save(&self, slot: usize, emu: &Emulator) -> Result<(), String> {
+        let path = self.slot_path(slot);
+        fs::write(&path, emu.save_state())
+            .map_err(|e| format!("unable to write {}: {}", path.display(), e))
+    }
+
+    pub fn load(&self, slot: usize, emu: &mut Emulator) -> Result<(), String> {
+        let path = self.slot_path(slot);
+        let state =
+            fs::read(&path).map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        emu.load_state(&state)
+            .map_err(|e| format!("unable to load {}: {}", path.display(), e))
+    }
+}
@@ -4,8 +4,21 @@ use crate::ui::text::CHAR_SIZE;
 const GAP: usize = 4;
 const SCALE: usize = 1;
 const JMP: usize = (CHAR_SIZE + GAP) * SCALE;
-const LINES: [&str; 4] = ["F1: reset", "F2: pause/resume", "F3: step", "ESC: exit"];
-const MAX_CHARS_WIDTH: usize = 16 * CHAR_SIZE;
+const LINES: [&str; 12] = [
+    "F1: reset",
+    "F2: pause/resume",
+    "F3: step",
+    "F4: step back",
+    "CLICK: breakpoint",
+    "PGUP/PGDN: memory",
+    "CLICK+HEX: edit",
+    "F9: heatmap",
+    "F5-F8: save slot",
+    "+SHIFT: load slot",
+    "BACKSPACE: rewind",
+    "ESC: exit",
+];
+const MAX_CHARS_WIDTH: usize = 17 * CHAR_SIZE;
 
 pub const CONTROL_KEYS_WIDTH: usize = MAX_CHARS_WIDTH + 2 * (GAP + BORDER_WIDTH);
 
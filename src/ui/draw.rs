@@ -1,6 +1,10 @@
 use crate::ui::text::TextDrawer;
 
 pub const FOREGROUND: u32 = 0x00FFFFFF; // white
+pub const HIGHLIGHT: u32 = 0x00FFCC00; // yellow
+
+// Colors for the XO-CHIP plane combinations, indexed by a pixel's plane bits
+pub const PLANE_COLORS: [u32; 4] = [0x00000000, FOREGROUND, 0x00FF6600, 0x00FFCC00];
 
 pub const GAP: usize = 4;
 pub const LINE_SIZE: usize = 2;
@@ -18,11 +22,11 @@ impl ShapeDrawer {
         }
     }
 
-    pub fn text(self: &Self) -> &TextDrawer {
+    pub fn text(&self) -> &TextDrawer {
         &self.text_drawer
     }
 
-    pub fn rect(self: &Self, window_buffer: &mut [u32], x: usize, y: usize, scale: usize) {
+    pub fn rect(&self, window_buffer: &mut [u32], x: usize, y: usize, scale: usize, color: u32) {
         let start_x = x * scale;
         let start_y = y * scale;
 
@@ -32,18 +36,13 @@ impl ShapeDrawer {
                 let window_y = start_y + y_offset;
                 let idx = window_y * self.width + window_x;
 
-                window_buffer[idx] = FOREGROUND;
+                window_buffer[idx] = color;
             }
         }
     }
 
-    pub fn vertical_line(
-        self: &Self,
-        window_buffer: &mut [u32],
-        x: usize,
-        from_y: usize,
-        to_y: usize,
-    ) {
+    #[allow(dead_code)]
+    pub fn vertical_line(&self, window_buffer: &mut [u32], x: usize, from_y: usize, to_y: usize) {
         let start_x = x;
 
         for y in from_y..to_y {
@@ -55,6 +54,7 @@ impl ShapeDrawer {
         }
     }
 
+    #[allow(dead_code)]
     pub fn horizontal_line(
         &self,
         window_buffer: &mut [u32],
@@ -72,6 +72,24 @@ impl ShapeDrawer {
         }
     }
 
+    /// Fills the black pixels from `(from_x, from_y)` up to `(to_x, to_y)`,
+    /// excluded, shading behind text already drawn there.
+    pub fn fill_behind(
+        &self,
+        window_buffer: &mut [u32],
+        (from_x, from_y): (usize, usize),
+        (to_x, to_y): (usize, usize),
+        color: u32,
+    ) {
+        for y in from_y..to_y {
+            let row = y * self.width;
+            window_buffer[row + from_x..row + to_x]
+                .iter_mut()
+                .filter(|pixel| **pixel == 0)
+                .for_each(|pixel| *pixel = color);
+        }
+    }
+
     pub fn border(
         &self,
         window_buffer: &mut [u32],
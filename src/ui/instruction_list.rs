@@ -1,43 +1,94 @@
-use crate::emu::constants::RAM_SIZE;
-use crate::emu::Emulator;
 use crate::ui::draw::{ShapeDrawer, BORDER_WIDTH, GAP};
 use crate::ui::text::CHAR_SIZE;
-use std::cmp::max;
+use chiprs_core::disasm::Disassembly;
+use chiprs_core::Emulator;
 
 const MAX_CHARS_WIDTH: usize = CHAR_SIZE * 50;
+const LINE_HEIGHT: usize = CHAR_SIZE + GAP;
+// Rows shown before the current instruction, and in total
+const ROWS_BEFORE: usize = 10;
+const ROWS: usize = 2 * ROWS_BEFORE;
 pub const INSTRUCTION_LIST_MAX_WIDTH: usize = MAX_CHARS_WIDTH + 2 * (GAP + BORDER_WIDTH);
+
+// Rows around the program counter with the address each belongs to, labels
+// get a row of their own above their line
+fn visible_rows(emu: &Emulator, disassembly: &Disassembly) -> Vec<(u16, String)> {
+    let counter = emu.counter;
+    let current = disassembly
+        .line_containing(counter)
+        .map_or(counter, |(address, _)| address);
+
+    let rows = |address: u16, text: String| {
+        let label = disassembly
+            .label(address)
+            .map(|label| (address, format!("{:9}: {}", "", label)));
+        let breakpoint = if emu.debugger().has_breakpoint(address) {
+            "*"
+        } else {
+            " "
+        };
+        let marker = if address == current { ">" } else { " " };
+        let line = (
+            address,
+            format!("{}{} {:04X}  {}", breakpoint, marker, address, text),
+        );
+        label.into_iter().chain([line])
+    };
+
+    let mut before = Vec::new();
+    for (address, line) in disassembly.lines_in(..current).rev() {
+        if before.len() >= ROWS_BEFORE {
+            break;
+        }
+        before.extend(rows(address, disassembly.format_line(line)).rev());
+    }
+    before.truncate(ROWS_BEFORE);
+    before.reverse();
+
+    let mut visible = before;
+    for (address, line) in disassembly.lines_in(current..) {
+        if visible.len() >= ROWS {
+            break;
+        }
+        visible.extend(rows(address, disassembly.format_line(line)));
+    }
+    visible.truncate(ROWS);
+    visible
+}
+
 pub fn draw_instruction_list(
     buffer: &mut [u32],
     emu: &Emulator,
+    disassembly: &Disassembly,
     shape_drawer: &ShapeDrawer,
     (x, y): (usize, usize),
 ) -> usize {
-    let counter = emu.counter;
-    let from = max(counter - 10, 0);
-    let to = if counter + 10 > RAM_SIZE as u16 {
-        (RAM_SIZE - 1) as u16
-    } else {
-        counter + 10
-    };
-
     let curr_x = x + GAP;
     let mut curr_y = y + GAP;
-    for i in from..to {
-        shape_drawer.text().draw(
-            buffer,
-            (curr_x, curr_y),
-            1,
-            &format!(
-                "{} {} > {}",
-                if i == counter { ">" } else { " " },
-                &i,
-                emu.fetch(i as usize),
-            ),
-        );
-        curr_y += CHAR_SIZE + GAP;
+    for (_, text) in visible_rows(emu, disassembly) {
+        shape_drawer.text().draw(buffer, (curr_x, curr_y), 1, &text);
+        curr_y += LINE_HEIGHT;
     }
+    // Keep the height when the rom is too short to fill the list
+    curr_y = curr_y.max(y + GAP + ROWS * LINE_HEIGHT);
 
     shape_drawer.border(buffer, (x, y), (x + MAX_CHARS_WIDTH + GAP, curr_y + GAP));
 
     curr_y + GAP + BORDER_WIDTH
 }
+
+/// Address of the line under `(mouse_x, mouse_y)` in a list drawn at `(x, y)`.
+pub fn instruction_list_address(
+    emu: &Emulator,
+    disassembly: &Disassembly,
+    (x, y): (usize, usize),
+    (mouse_x, mouse_y): (usize, usize),
+) -> Option<u16> {
+    if mouse_x < x || mouse_x >= x + MAX_CHARS_WIDTH + GAP || mouse_y < y + GAP {
+        return None;
+    }
+    let row = (mouse_y - y - GAP) / LINE_HEIGHT;
+    visible_rows(emu, disassembly)
+        .get(row)
+        .map(|(address, _)| *address)
+}
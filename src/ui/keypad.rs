@@ -1,8 +1,8 @@
-use crate::emu::constants::KEYPAD_SIZE;
-use crate::emu::keys::ChipKey;
-use crate::emu::Emulator;
 use crate::ui::draw::{ShapeDrawer, BORDER_WIDTH};
 use crate::ui::text::CHAR_SIZE;
+use chiprs_core::constants::KEYPAD_SIZE;
+use chiprs_core::keys::ChipKey;
+use chiprs_core::Emulator;
 
 const GAP: usize = 4;
 const SCALE: usize = 2;
@@ -31,21 +31,21 @@ pub fn draw_keypad(
     for (idx, ch) in KEYPAD.iter().enumerate() {
         let pos_x = start_x + ((idx % 4) * JMP);
         let pos_y = start_y + ((idx / 4) * JMP);
-        if let Some(key) = ChipKey::from_char(ch) {
-            if key_states.get(&key) == Some(&true) {
-                shape_drawer.border(
-                    buffer,
-                    (pos_x, pos_y),
-                    (pos_x + pressed_gap, pos_y + pressed_gap),
-                );
-            }
+        if let Some(key) = ChipKey::from_char(ch)
+            && key_states.get(&key) == Some(&true)
+        {
+            shape_drawer.border(
+                buffer,
+                (pos_x, pos_y),
+                (pos_x + pressed_gap, pos_y + pressed_gap),
+            );
         }
 
         shape_drawer.text().draw(
             buffer,
             (
-                pos_x + BORDER_WIDTH + 1 * SCALE,
-                pos_y + BORDER_WIDTH + 1 * SCALE,
+                pos_x + BORDER_WIDTH + SCALE,
+                pos_y + BORDER_WIDTH + SCALE,
             ),
             SCALE,
             &ch.to_string(),
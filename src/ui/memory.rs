@@ -0,0 +1,322 @@
+use crate::ui::draw::{ShapeDrawer, BORDER_WIDTH, FOREGROUND, GAP, HIGHLIGHT};
+use crate::ui::text::CHAR_SIZE;
+use chiprs_core::constants::{RAM_SIZE, START_ADDR};
+use chiprs_core::{Emulator, Instruction};
+use std::ops::Range;
+
+const BYTES_PER_ROW: usize = 16;
+pub const MEMORY_ROWS: usize = 16;
+const LINE_HEIGHT: usize = CHAR_SIZE + GAP;
+// "0200  00 E0 .. 0A  ................"
+const HEX_COLUMN: usize = 6;
+const ASCII_COLUMN: usize = HEX_COLUMN + 3 * BYTES_PER_ROW + 1;
+const MAX_CHARS_WIDTH: usize = (ASCII_COLUMN + BYTES_PER_ROW) * CHAR_SIZE;
+const LAST_TOP: usize = RAM_SIZE - MEMORY_ROWS * BYTES_PER_ROW;
+
+const I_COLOR: u32 = 0x00FF6600; // orange
+const SPRITE_COLOR: u32 = 0x0066CCFF; // light blue
+const SELECTED_COLOR: u32 = 0x0000FF66; // green
+
+// Backgrounds of the least and the most executed instructions
+const COLD_COLOR: [u32; 3] = [0x10, 0x20, 0x60];
+const HOT_COLOR: [u32; 3] = [0xC0, 0x20, 0x10];
+
+pub const MEMORY_WIDTH: usize = MAX_CHARS_WIDTH + 2 * (GAP + BORDER_WIDTH);
+pub const MEMORY_HEIGHT: usize = MEMORY_ROWS * LINE_HEIGHT + 2 * (GAP + BORDER_WIDTH);
+
+/// Scroll position and byte being edited in the memory panel.
+pub struct MemoryView {
+    // Address of the first row
+    top: usize,
+    selected: Option<u16>,
+    // First digit typed into the selected byte
+    high_nibble: Option<u8>,
+    edited: bool,
+    heatmap: bool,
+}
+
+impl MemoryView {
+    pub fn new() -> Self {
+        Self {
+            top: START_ADDR as usize,
+            selected: None,
+            high_nibble: None,
+            edited: false,
+            heatmap: false,
+        }
+    }
+
+    pub fn scroll(&mut self, rows: isize) {
+        let top = self.top as isize + rows * BYTES_PER_ROW as isize;
+        self.top = top.clamp(0, LAST_TOP as isize) as usize;
+    }
+
+    pub fn selected(&self) -> Option<u16> {
+        self.selected
+    }
+
+    pub fn select(&mut self, address: Option<u16>) {
+        self.selected = address;
+        self.high_nibble = None;
+    }
+
+    /// Moves the selection by `offset` bytes, scrolling to keep it visible.
+    pub fn move_selection(&mut self, offset: isize) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+        let address = (selected as isize + offset).clamp(0, RAM_SIZE as isize - 1) as usize;
+        self.select(Some(address as u16));
+
+        let row_start = address - address % BYTES_PER_ROW;
+        if row_start < self.top {
+            self.top = row_start;
+        } else if row_start >= self.top + MEMORY_ROWS * BYTES_PER_ROW {
+            self.top = row_start + BYTES_PER_ROW - MEMORY_ROWS * BYTES_PER_ROW;
+        }
+    }
+
+    /// Types a hex digit into the selected byte, the second digit writes it
+    /// and selects the next byte.
+    pub fn type_digit(&mut self, emu: &mut Emulator, digit: u8) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+        match self.high_nibble.take() {
+            None => self.high_nibble = Some(digit),
+            Some(high) => {
+                emu.poke(selected, (high << 4) | digit);
+                self.edited = true;
+                self.move_selection(1);
+            }
+        }
+    }
+
+    /// Whether a byte was written since the last call.
+    pub fn take_edited(&mut self) -> bool {
+        std::mem::take(&mut self.edited)
+    }
+
+    /// Shades the instructions by how often they ran, from the emulator profile.
+    pub fn toggle_heatmap(&mut self) {
+        self.heatmap = !self.heatmap;
+    }
+
+    fn visible_addresses(&self) -> Range<usize> {
+        self.top..self.top + MEMORY_ROWS * BYTES_PER_ROW
+    }
+}
+
+// Bytes the Draw instruction at PC reads, one sprite per selected plane
+fn sprite_bytes(emu: &Emulator) -> Range<usize> {
+    let cpu = emu.cpu_state();
+    let Instruction::Draw { n, .. } = emu.fetch(cpu.pc as usize) else {
+        return 0..0;
+    };
+    let sprite_size = if n == 0 { 32 } else { n as usize };
+    let planes = emu.get_screen().selected_planes().count_ones() as usize;
+    cpu.i as usize..cpu.i as usize + sprite_size * planes
+}
+
+// Between the cold and hot colors, on a log scale as a few loops dwarf everything else
+fn heat_color(count: u64, max_count: u64) -> u32 {
+    let heat = if max_count > 1 {
+        (count as f64).ln() / (max_count as f64).ln()
+    } else {
+        1.0
+    };
+    COLD_COLOR
+        .iter()
+        .zip(HOT_COLOR)
+        .map(|(&cold, hot)| (cold as f64 + (hot as f64 - cold as f64) * heat) as u32)
+        .fold(0, |color, channel| (color << 8) | channel)
+}
+
+// Shades behind the bytes of every instruction executed in the view
+fn draw_heatmap(
+    buffer: &mut [u32],
+    emu: &Emulator,
+    view: &MemoryView,
+    shape_drawer: &ShapeDrawer,
+    (x, y): (usize, usize),
+) {
+    let Some(profile) = emu.profile() else {
+        return;
+    };
+    let max_count = profile.max_count();
+    let visible = view.visible_addresses();
+    // An instruction starting just before the view may reach into it
+    for start in visible.start.saturating_sub(3)..visible.end {
+        let count = profile.count(start as u16);
+        if count == 0 {
+            continue;
+        }
+        let color = heat_color(count, max_count);
+        let size = emu.fetch(start).size() as usize;
+        for address in (start..start + size).filter(|address| visible.contains(address)) {
+            let offset = address - visible.start;
+            let (row, column) = (offset / BYTES_PER_ROW, offset % BYTES_PER_ROW);
+            let left = x + (HEX_COLUMN + 3 * column) * CHAR_SIZE;
+            let top = y + row * LINE_HEIGHT;
+            shape_drawer.fill_behind(
+                buffer,
+                (left, top),
+                (left + 2 * CHAR_SIZE, top + CHAR_SIZE),
+                color,
+            );
+        }
+    }
+}
+
+pub fn draw_memory(
+    buffer: &mut [u32],
+    emu: &Emulator,
+    view: &MemoryView,
+    shape_drawer: &ShapeDrawer,
+    (x, y): (usize, usize),
+) -> usize {
+    let text = shape_drawer.text();
+    let cpu = emu.cpu_state();
+    let sprite = sprite_bytes(emu);
+    let pc = cpu.pc as usize..cpu.pc as usize + emu.fetch(cpu.pc as usize).size() as usize;
+
+    let curr_x = x + GAP;
+    let mut curr_y = y + GAP;
+    for row in view.visible_addresses().step_by(BYTES_PER_ROW) {
+        text.draw(buffer, (curr_x, curr_y), 1, &format!("{:04X}", row));
+
+        for address in row..row + BYTES_PER_ROW {
+            let column = address - row;
+            let value = emu.peek(address as u16);
+            let color = if view.selected == Some(address as u16) {
+                SELECTED_COLOR
+            } else if pc.contains(&address) {
+                HIGHLIGHT
+            } else if address == cpu.i as usize {
+                I_COLOR
+            } else if sprite.contains(&address) {
+                SPRITE_COLOR
+            } else {
+                FOREGROUND
+            };
+
+            let hex = match view.high_nibble {
+                Some(high) if view.selected == Some(address as u16) => format!("{:X}_", high),
+                _ => format!("{:02X}", value),
+            };
+            let ascii = if value.is_ascii_graphic() || value == b' ' {
+                value as char
+            } else {
+                '.'
+            };
+            text.draw_colored(
+                buffer,
+                (curr_x + (HEX_COLUMN + 3 * column) * CHAR_SIZE, curr_y),
+                1,
+                &hex,
+                color,
+            );
+            text.draw_colored(
+                buffer,
+                (curr_x + (ASCII_COLUMN + column) * CHAR_SIZE, curr_y),
+                1,
+                &ascii.to_string(),
+                color,
+            );
+        }
+        curr_y += LINE_HEIGHT;
+    }
+
+    if view.heatmap {
+        draw_heatmap(buffer, emu, view, shape_drawer, (curr_x, y + GAP));
+    }
+    shape_drawer.border(buffer, (x, y), (x + MAX_CHARS_WIDTH + GAP, curr_y + GAP));
+
+    curr_y + GAP + BORDER_WIDTH
+}
+
+/// Address of the hex digits under `(mouse_x, mouse_y)` in a panel drawn at `(x, y)`.
+pub fn memory_address(
+    view: &MemoryView,
+    (x, y): (usize, usize),
+    (mouse_x, mouse_y): (usize, usize),
+) -> Option<u16> {
+    let start_x = x + GAP + HEX_COLUMN * CHAR_SIZE;
+    if mouse_x < start_x || mouse_y < y + GAP {
+        return None;
+    }
+    let column = (mouse_x - start_x) / (3 * CHAR_SIZE);
+    let row = (mouse_y - y - GAP) / LINE_HEIGHT;
+    if column >= BYTES_PER_ROW || row >= MEMORY_ROWS {
+        return None;
+    }
+    Some((view.top + row * BYTES_PER_ROW + column) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chiprs_core::quirks::Quirks;
+
+    const PAGE: usize = MEMORY_ROWS * BYTES_PER_ROW;
+
+    #[test]
+    fn scrolling_stays_within_memory() {
+        let mut view = MemoryView::new();
+        view.scroll(2);
+        assert_eq!(view.visible_addresses().start, START_ADDR as usize + 32);
+        view.scroll(-1000);
+        assert_eq!(view.visible_addresses(), 0..PAGE);
+        view.scroll(isize::MAX / 32);
+        assert_eq!(view.visible_addresses(), LAST_TOP..RAM_SIZE);
+    }
+
+    #[test]
+    fn moving_the_selection_scrolls_to_it() {
+        let mut view = MemoryView::new();
+        view.move_selection(1);
+        assert_eq!(view.selected(), None);
+
+        let top = START_ADDR as usize;
+        view.select(Some((top + PAGE - 1) as u16));
+        view.move_selection(16);
+        assert_eq!(view.selected(), Some((top + PAGE + 15) as u16));
+        assert_eq!(view.visible_addresses().start, top + 16);
+
+        view.move_selection(-(PAGE as isize));
+        assert_eq!(view.selected(), Some((top + 15) as u16));
+        assert_eq!(view.visible_addresses().start, top);
+
+        view.move_selection(-0x1000);
+        assert_eq!(view.selected(), Some(0));
+        assert_eq!(view.visible_addresses().start, 0);
+        view.move_selection(0x10000);
+        assert_eq!(view.selected(), Some((RAM_SIZE - 1) as u16));
+        assert_eq!(view.visible_addresses().start, LAST_TOP);
+    }
+
+    #[test]
+    fn typing_two_digits_writes_a_byte() {
+        let mut emu = Emulator::new(Quirks::default());
+        emu.load(&[0x00, 0xE0]).unwrap();
+        let mut view = MemoryView::new();
+        view.select(Some(0x300));
+
+        view.type_digit(&mut emu, 0xA);
+        assert_eq!(emu.peek(0x300), 0);
+        assert!(!view.take_edited());
+        view.type_digit(&mut emu, 0x5);
+        assert_eq!(emu.peek(0x300), 0xA5);
+        assert_eq!(view.selected(), Some(0x301));
+        assert!(view.take_edited());
+        assert!(!view.take_edited());
+
+        // Selecting another byte drops a half typed one
+        view.type_digit(&mut emu, 0x1);
+        view.select(Some(0x310));
+        view.type_digit(&mut emu, 0x2);
+        view.type_digit(&mut emu, 0x3);
+        assert_eq!(emu.peek(0x301), 0);
+        assert_eq!(emu.peek(0x310), 0x23);
+    }
+}
@@ -2,16 +2,31 @@ mod control_keys;
 mod draw;
 mod instruction_list;
 mod keypad;
+mod memory;
+mod registers;
 mod text;
 
-use crate::emu::constants::{EMU_SCREEN_HEIGHT, EMU_SCREEN_WIDTH};
-use crate::emu::Emulator;
-use crate::ui::control_keys::draw_control_keys;
-use crate::ui::draw::{ShapeDrawer, BORDER_WIDTH, GAP, LINE_SIZE};
-use crate::ui::instruction_list::{draw_instruction_list, INSTRUCTION_LIST_MAX_WIDTH};
+use crate::ui::control_keys::{draw_control_keys, CONTROL_KEYS_WIDTH};
+use crate::ui::draw::{ShapeDrawer, BORDER_WIDTH, GAP, LINE_SIZE, PLANE_COLORS};
+use crate::ui::instruction_list::{
+    draw_instruction_list, instruction_list_address, INSTRUCTION_LIST_MAX_WIDTH,
+};
 use crate::ui::keypad::{draw_keypad, KEYPAD_HEIGHT, KEYPAD_WIDTH};
+use crate::ui::memory::{draw_memory, memory_address, MEMORY_HEIGHT, MEMORY_WIDTH};
+use crate::ui::registers::{draw_registers, REGISTERS_WIDTH};
+use crate::ui::text::CHAR_SIZE;
+use chiprs_core::constants::{EMU_SCREEN_HEIGHT, EMU_SCREEN_WIDTH};
+use chiprs_core::cpu::CpuState;
+use chiprs_core::disasm::{Disassembly, Line};
+use chiprs_core::symbols::SymbolMap;
+use chiprs_core::Emulator;
 use std::cmp::max;
 
+pub use crate::ui::memory::MemoryView;
+
+// Error lines listed above the emulator status, leaving it room below
+const MAX_ERROR_LINES: usize = 4;
+
 pub struct Size {
     pub width: usize,
     pub height: usize,
@@ -22,15 +37,27 @@ pub struct UiDrawer {
     pub emu_scale: usize,
     pub window_size: Size,
     shape_drawer: ShapeDrawer,
+    // The registers as last drawn and before they last changed, to highlight
+    // what the last step changed
+    cpu_state: Option<CpuState>,
+    previous_cpu_state: CpuState,
+    symbols: SymbolMap,
+    // Refreshed when the program counter leaves the code it found, or memory
+    // is edited
+    disassembly: Disassembly,
+    pub memory: MemoryView,
+    // Where the memory panel was last drawn, it moves with the panels above it
+    memory_pos: (usize, usize),
 }
 
 impl UiDrawer {
-    pub fn new(emu_scale: usize) -> Self {
+    pub fn new(emu_scale: usize, symbols: SymbolMap) -> Self {
         let emu_width = EMU_SCREEN_WIDTH * emu_scale;
         let emu_height = EMU_SCREEN_HEIGHT * emu_scale;
 
-        let window_width = emu_width + GAP + LINE_SIZE + GAP + INSTRUCTION_LIST_MAX_WIDTH;
-        let window_height = emu_height + GAP + LINE_SIZE + GAP + KEYPAD_HEIGHT;
+        let window_width =
+            emu_width + GAP + LINE_SIZE + GAP + INSTRUCTION_LIST_MAX_WIDTH + GAP + REGISTERS_WIDTH;
+        let window_height = emu_height + GAP + LINE_SIZE + GAP + max(KEYPAD_HEIGHT, MEMORY_HEIGHT);
 
         Self {
             emu_size: Size {
@@ -43,18 +70,33 @@ impl UiDrawer {
                 height: window_height,
             },
             shape_drawer: ShapeDrawer::new(window_width),
+            cpu_state: None,
+            previous_cpu_state: CpuState::default(),
+            symbols,
+            disassembly: Disassembly::default(),
+            memory: MemoryView::new(),
+            memory_pos: (0, 0),
         }
     }
 
-    pub fn draw(&self, emu: &mut Emulator) -> Vec<u32> {
+    /// Draws the window, `errors` are listed next to the emulator status.
+    pub fn draw(&mut self, emu: &mut Emulator, errors: &[String]) -> Vec<u32> {
         let mut window_buffer: Vec<u32> = vec![0; self.window_size.width * self.window_size.height];
 
-        for (i, pixel) in emu.get_screen().iter().enumerate() {
-            if *pixel {
-                let x = i % EMU_SCREEN_WIDTH;
-                let y = i / EMU_SCREEN_WIDTH;
-                self.shape_drawer
-                    .rect(window_buffer.as_mut_slice(), x, y, self.emu_scale);
+        let screen = emu.get_screen();
+        // `emu_scale` is relative to low-res mode, high-res pixels get half of it
+        let pixel_scale = self.emu_scale * EMU_SCREEN_WIDTH / screen.width();
+        for (i, planes) in screen.pixels().iter().enumerate() {
+            if *planes != 0 {
+                let x = i % screen.width();
+                let y = i / screen.width();
+                self.shape_drawer.rect(
+                    window_buffer.as_mut_slice(),
+                    x,
+                    y,
+                    pixel_scale,
+                    PLANE_COLORS[*planes as usize],
+                );
             }
         }
 
@@ -64,43 +106,172 @@ impl UiDrawer {
             (self.emu_size.width + GAP, self.emu_size.height + GAP),
         );
 
-        let mut curr_x = self.emu_size.width + GAP + BORDER_WIDTH + GAP;
-        let mut curr_y = self.emu_size.height + GAP + LINE_SIZE + GAP;
-
+        let counter = emu.counter;
+        let known = matches!(
+            self.disassembly.line_containing(counter),
+            Some((address, Line::Instruction(_))) if address == counter
+        );
+        if !known || self.memory.take_edited() {
+            self.disassembly = emu.disassemble(&self.symbols);
+        }
         let end_y = draw_instruction_list(
             window_buffer.as_mut_slice(),
-            &emu,
+            emu,
+            &self.disassembly,
+            &self.shape_drawer,
+            self.instruction_list_pos(),
+        );
+
+        let cpu_state = emu.cpu_state();
+        if self.cpu_state.as_ref() != Some(&cpu_state) {
+            self.previous_cpu_state = self.cpu_state.take().unwrap_or_else(|| cpu_state.clone());
+            self.cpu_state = Some(cpu_state);
+        }
+        let (list_x, list_y) = self.instruction_list_pos();
+        let registers_end_y = draw_registers(
+            window_buffer.as_mut_slice(),
+            self.cpu_state.as_ref().unwrap(),
+            &self.previous_cpu_state,
             &self.shape_drawer,
-            (curr_x, 0),
+            (list_x + INSTRUCTION_LIST_MAX_WIDTH + GAP, list_y),
         );
 
-        curr_x = 0;
-        curr_y = max(self.emu_size.height, end_y) + BORDER_WIDTH + GAP;
+        let mut curr_x = 0;
+        let mut curr_y =
+            max(self.emu_size.height, max(end_y, registers_end_y)) + BORDER_WIDTH + GAP;
         draw_keypad(
             window_buffer.as_mut_slice(),
-            &emu,
+            emu,
             &self.shape_drawer,
             (curr_x, curr_y),
         );
 
         curr_x += KEYPAD_WIDTH + GAP;
 
-        let end_y = draw_control_keys(
+        draw_control_keys(
             window_buffer.as_mut_slice(),
             &self.shape_drawer,
             (curr_x, curr_y),
         );
-        curr_y = end_y + GAP;
+        curr_x += CONTROL_KEYS_WIDTH + GAP;
 
-        if emu.is_paused() {
+        self.memory_pos = (curr_x, curr_y);
+        draw_memory(
+            window_buffer.as_mut_slice(),
+            emu,
+            &self.memory,
+            &self.shape_drawer,
+            self.memory_pos,
+        );
+        curr_x += MEMORY_WIDTH + GAP;
+
+        self.shape_drawer.text().draw(
+            window_buffer.as_mut_slice(),
+            (curr_x, curr_y),
+            1,
+            &format!("PLATFORM: {}", emu.quirks()),
+        );
+        curr_y += CHAR_SIZE + GAP;
+
+        // Lines are cut at the window edge, the rest of the errors are counted
+        let max_chars = (self.window_size.width - curr_x) / CHAR_SIZE;
+        let shown = if errors.len() > MAX_ERROR_LINES {
+            MAX_ERROR_LINES - 1
+        } else {
+            errors.len()
+        };
+        let more = (shown < errors.len()).then(|| format!("{} more errors", errors.len() - shown));
+        for error in errors[..shown].iter().chain(&more) {
+            let line = error.chars().take(max_chars).collect::<String>();
+            self.shape_drawer
+                .text()
+                .draw(window_buffer.as_mut_slice(), (curr_x, curr_y), 1, &line);
+            curr_y += CHAR_SIZE + GAP;
+        }
+
+        if let Some(fault) = emu.fault() {
             self.shape_drawer.text().draw(
                 window_buffer.as_mut_slice(),
                 (curr_x, curr_y),
                 2,
-                "PAUSED",
+                "FAULT",
+            );
+            self.shape_drawer.text().draw(
+                window_buffer.as_mut_slice(),
+                (curr_x, curr_y + 2 * CHAR_SIZE + GAP),
+                1,
+                &fault.to_string(),
             )
+        } else if emu.has_exited() {
+            self.shape_drawer.text().draw(
+                window_buffer.as_mut_slice(),
+                (curr_x, curr_y),
+                2,
+                "EXITED",
+            )
+        } else if emu.is_paused() {
+            self.shape_drawer.text().draw(
+                window_buffer.as_mut_slice(),
+                (curr_x, curr_y),
+                2,
+                "PAUSED",
+            );
+            curr_y += 2 * CHAR_SIZE + GAP;
+            if let Some(stop) = emu.debugger().last_stop() {
+                self.shape_drawer.text().draw(
+                    window_buffer.as_mut_slice(),
+                    (curr_x, curr_y),
+                    1,
+                    &stop.to_string(),
+                );
+                curr_y += CHAR_SIZE + GAP;
+            }
+            if let Some(location) = self.source_location(emu.counter) {
+                self.shape_drawer.text().draw(
+                    window_buffer.as_mut_slice(),
+                    (curr_x, curr_y),
+                    1,
+                    &location,
+                )
+            }
         }
 
         window_buffer
     }
+
+    // Label and source line of an address, when there is a symbol file
+    fn source_location(&self, address: u16) -> Option<String> {
+        if self.symbols.is_empty() {
+            return None;
+        }
+        let mut location = format!("in {}", self.symbols.describe(address));
+        if let Some(source) = self.symbols.source_line(address) {
+            let file = source.file.file_name().unwrap_or_default();
+            location += &format!(" {}:{}", file.to_string_lossy(), source.line);
+        }
+        Some(location)
+    }
+
+    fn instruction_list_pos(&self) -> (usize, usize) {
+        (self.emu_size.width + GAP + BORDER_WIDTH + GAP, 0)
+    }
+
+    /// Address of the instruction list line under the mouse, if any.
+    pub fn instruction_at(&self, emu: &Emulator, (mouse_x, mouse_y): (f32, f32)) -> Option<u16> {
+        instruction_list_address(
+            emu,
+            &self.disassembly,
+            self.instruction_list_pos(),
+            (mouse_x as usize, mouse_y as usize),
+        )
+    }
+
+    /// Address of the memory panel byte under the mouse, if any.
+    pub fn memory_at(&self, (mouse_x, mouse_y): (f32, f32)) -> Option<u16> {
+        memory_address(
+            &self.memory,
+            self.memory_pos,
+            (mouse_x as usize, mouse_y as usize),
+        )
+    }
 }
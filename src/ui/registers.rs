@@ -0,0 +1,89 @@
+use crate::ui::draw::{ShapeDrawer, BORDER_WIDTH, FOREGROUND, GAP, HIGHLIGHT};
+use crate::ui::text::CHAR_SIZE;
+use chiprs_core::constants::{STACK_SIZE, V_SIZE};
+use chiprs_core::cpu::CpuState;
+
+const LINE_HEIGHT: usize = CHAR_SIZE + GAP;
+// Values are laid out in two columns
+const COLUMN_WIDTH: usize = 8 * CHAR_SIZE;
+const MAX_CHARS_WIDTH: usize = COLUMN_WIDTH + 7 * CHAR_SIZE;
+const V_ROWS: usize = V_SIZE / 2;
+const STACK_ROWS: usize = STACK_SIZE / 2;
+// V registers, I and PC, the timers and the stack header
+const STACK_FIRST_ROW: usize = V_ROWS + 3;
+
+pub const REGISTERS_WIDTH: usize = MAX_CHARS_WIDTH + 2 * (GAP + BORDER_WIDTH);
+
+/// Draws the registers, timers and call stack of `current`, the values that
+/// differ from `previous` are highlighted.
+pub fn draw_registers(
+    buffer: &mut [u32],
+    current: &CpuState,
+    previous: &CpuState,
+    shape_drawer: &ShapeDrawer,
+    (x, y): (usize, usize),
+) -> usize {
+    let cell = |buffer: &mut [u32], (column, row): (usize, usize), text: &str, changed: bool| {
+        shape_drawer.text().draw_colored(
+            buffer,
+            (x + GAP + column * COLUMN_WIDTH, y + GAP + row * LINE_HEIGHT),
+            1,
+            text,
+            if changed { HIGHLIGHT } else { FOREGROUND },
+        );
+    };
+
+    for (idx, (v, previous_v)) in current.v.iter().zip(previous.v).enumerate() {
+        cell(
+            buffer,
+            (idx / V_ROWS, idx % V_ROWS),
+            &format!("V{:X} {:02X}", idx, v),
+            *v != previous_v,
+        );
+    }
+    cell(
+        buffer,
+        (0, V_ROWS),
+        &format!("I  {:04X}", current.i),
+        current.i != previous.i,
+    );
+    cell(
+        buffer,
+        (1, V_ROWS),
+        &format!("PC {:04X}", current.pc),
+        current.pc != previous.pc,
+    );
+    cell(
+        buffer,
+        (0, V_ROWS + 1),
+        &format!("DT {:02X}", current.delay_timer),
+        current.delay_timer != previous.delay_timer,
+    );
+    cell(
+        buffer,
+        (1, V_ROWS + 1),
+        &format!("ST {:02X}", current.sound_timer),
+        current.sound_timer != previous.sound_timer,
+    );
+
+    cell(
+        buffer,
+        (0, V_ROWS + 2),
+        &format!("STACK {}/{}", current.stack.len(), STACK_SIZE),
+        current.stack.len() != previous.stack.len(),
+    );
+    // Innermost return address first
+    for (idx, (depth, address)) in current.stack.iter().enumerate().rev().enumerate() {
+        cell(
+            buffer,
+            (idx / STACK_ROWS, STACK_FIRST_ROW + idx % STACK_ROWS),
+            &format!("{:04X}", address),
+            previous.stack.get(depth) != Some(address),
+        );
+    }
+
+    let bottom = y + GAP + (STACK_FIRST_ROW + STACK_ROWS) * LINE_HEIGHT;
+    shape_drawer.border(buffer, (x, y), (x + MAX_CHARS_WIDTH + GAP, bottom + GAP));
+
+    bottom + GAP + BORDER_WIDTH
+}
@@ -30,7 +30,18 @@ impl TextDrawer {
         Self { texture, width }
     }
 
-    pub fn draw(&self, screen: &mut [u32], (x, y): (usize, usize), scale: usize, text: &str) {
+    pub fn draw(&self, screen: &mut [u32], pos: (usize, usize), scale: usize, text: &str) {
+        self.draw_colored(screen, pos, scale, text, FOREGROUND);
+    }
+
+    pub fn draw_colored(
+        &self,
+        screen: &mut [u32],
+        (x, y): (usize, usize),
+        scale: usize,
+        text: &str,
+        color: u32,
+    ) {
         let mut curr_x = x;
         for c in text.chars() {
             let mut index = c as usize - ' ' as usize;
@@ -47,7 +58,8 @@ impl TextDrawer {
                     let tx = fx / scale;
                     let pixel = texture_offset + (ty * 128) + tx;
                     if pixel != 0 {
-                        screen[((y + fy) * self.width) + fx + curr_x] = self.texture[pixel];
+                        // Texture pixels are either black or FOREGROUND, masking recolors them
+                        screen[((y + fy) * self.width) + fx + curr_x] = self.texture[pixel] & color;
                     }
                 }
             }
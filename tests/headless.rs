@@ -0,0 +1,197 @@
+//! Runs the chiprs-headless binary and checks its outputs and errors.
+
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn rom(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("roms")
+        .join(name)
+}
+
+// A rom written to a temporary file for the run
+fn temp_rom(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = env::temp_dir().join(format!(
+        "chiprs-headless-{}-{}.ch8",
+        name,
+        std::process::id()
+    ));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn headless(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_chiprs-headless"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn ascii_matches_the_golden_image() {
+    let output = headless(&[rom("2-ibm-logo.ch8").to_str().unwrap()]);
+    assert!(output.status.success());
+    let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("chiprs-core/tests/golden/2-ibm-logo-chip8.txt");
+    assert_eq!(stdout(&output), fs::read_to_string(golden).unwrap());
+}
+
+#[test]
+fn registers_are_json() {
+    // V3 := 2A, I := 123, call 208, loop, 208: loop
+    let path = temp_rom(
+        "registers",
+        &[0x63, 0x2A, 0xA1, 0x23, 0x22, 0x08, 0x12, 0x06, 0x12, 0x08],
+    );
+    let output = headless(&[path.to_str().unwrap(), "--cycles", "5", "--registers", "-"]);
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+
+    let registers: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(registers["pc"], 0x208);
+    assert_eq!(registers["i"], 0x123);
+    assert_eq!(registers["v"][3], 0x2A);
+    assert_eq!(registers["v"].as_array().unwrap().len(), 16);
+    assert_eq!(registers["stack"], serde_json::json!([0x206]));
+    assert_eq!(registers["exited"], false);
+    assert_eq!(registers["fault"], Value::Null);
+}
+
+#[test]
+fn faults_fail_the_run() {
+    let path = temp_rom("fault", &[0x00, 0xEE]);
+    let output = headless(&[path.to_str().unwrap(), "--registers", "-"]);
+    fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("stack underflow at 0200"));
+
+    let registers: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(registers["fault"], "stack underflow at 0200");
+}
+
+#[test]
+fn png_holds_the_screen() {
+    let path = env::temp_dir().join(format!("chiprs-headless-{}.png", std::process::id()));
+    let output = headless(&[
+        rom("2-ibm-logo.ch8").to_str().unwrap(),
+        "--png",
+        path.to_str().unwrap(),
+        "--ascii",
+        "-",
+    ]);
+    let png = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+
+    assert_eq!(
+        png[..8],
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']
+    );
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(png[16..24], [0, 0, 0, 64, 0, 0, 0, 32]);
+
+    // The image data sits in stored deflate blocks: each row is a filter
+    // byte followed by one palette index per pixel
+    let idat = png.windows(4).position(|kind| kind == b"IDAT").unwrap();
+    let len = u32::from_be_bytes(png[idat - 4..idat].try_into().unwrap()) as usize;
+    let zlib = &png[idat + 4..idat + 4 + len];
+    let mut raw = Vec::new();
+    let mut pos = 2;
+    while pos + 5 <= zlib.len() - 4 {
+        let block = u16::from_le_bytes([zlib[pos + 1], zlib[pos + 2]]) as usize;
+        raw.extend_from_slice(&zlib[pos + 5..pos + 5 + block]);
+        pos += 5 + block;
+    }
+
+    let ascii = stdout(&output)
+        .lines()
+        .map(|line| {
+            let row = line.chars().map(|c| (c == '#') as u8);
+            std::iter::once(0).chain(row).collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+        .concat();
+    assert_eq!(raw, ascii);
+}
+
+#[test]
+fn bad_arguments_print_the_usage() {
+    let output = headless(&[rom("2-ibm-logo.ch8").to_str().unwrap(), "--frobnicate"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("unknown option --frobnicate\n\nUsage:"));
+
+    let output = headless(&[]);
+    assert!(stderr(&output).starts_with("missing rom file"));
+}
+
+#[test]
+fn scripted_keys_reach_the_rom() {
+    // The same presses as the golden keypad test, from a file and an option
+    let keys = env::temp_dir().join(format!("chiprs-headless-{}.keys", std::process::id()));
+    fs::write(&keys, "# FX0A test\n100:3\n").unwrap();
+    let output = headless(&[
+        rom("6-keypad.ch8").to_str().unwrap(),
+        "--frames",
+        "400",
+        "--keys",
+        keys.to_str().unwrap(),
+        "--press",
+        "200:A",
+    ]);
+    fs::remove_file(&keys).unwrap();
+    assert!(output.status.success());
+    let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("chiprs-core/tests/golden/6-keypad-chip8.txt");
+    assert_eq!(stdout(&output), fs::read_to_string(golden).unwrap());
+}
+
+#[test]
+fn key_presses_are_checked() {
+    let rom = rom("6-keypad.ch8");
+    let rom = rom.to_str().unwrap();
+    for press in ["5", "5:G", "5:A:0", "5:A:1:2", "x:A"] {
+        let output = headless(&[rom, "--press", press]);
+        assert!(!output.status.success());
+        assert!(
+            stderr(&output).starts_with("invalid key press"),
+            "{}",
+            press
+        );
+    }
+
+    let output = headless(&[rom, "--press", "18446744073709551615:A"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("released after the last frame"));
+
+    // Presses have to fit in the run, wherever the length is given
+    for (length, press, error) in [
+        (["--frames", "10"], "10:A", "'10:A' is after the last frame"),
+        (
+            ["--frames", "10"],
+            "8:A:3",
+            "'8:A:3' is released after the last frame",
+        ),
+        (["--cycles", "99"], "9:A", "'9:A' is after the last frame"),
+    ] {
+        let output = headless(&[rom, "--press", press, length[0], length[1]]);
+        assert!(!output.status.success());
+        assert!(stderr(&output).contains(error), "{}", stderr(&output));
+    }
+    // Held until the end
+    let output = headless(&[rom, "--frames", "10", "--press", "8:A:2"]);
+    assert!(output.status.success());
+    // Nine whole frames of 11 cycles and one cut short
+    let output = headless(&[rom, "--cycles", "100", "--press", "9:A:1"]);
+    assert!(output.status.success());
+}